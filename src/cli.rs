@@ -54,6 +54,16 @@ pub enum Commands {
         /// Show what would be imported without writing config.
         #[arg(long)]
         dry_run: bool,
+
+        /// Key for decrypting an encrypted sshore export: an SSH private key or
+        /// an age identity file. Repeatable. Default: ~/.ssh/id_ed25519.
+        #[arg(short, long, value_name = "FILE")]
+        identity: Vec<String>,
+
+        /// Require a sshore export signed by this SSH public key (or any key in
+        /// this file).
+        #[arg(long, value_name = "KEY")]
+        signer: Option<String>,
     },
 
     /// Manage stored passwords in OS keychain.
@@ -83,6 +93,39 @@ pub enum Commands {
         /// Resume a partially downloaded file instead of starting over.
         #[arg(long)]
         resume: bool,
+        /// Preserve the file's mode and modification/access times (like `scp -p`).
+        #[arg(short, long)]
+        preserve: bool,
+    },
+
+    /// Sync a directory tree between local and remote (like `rsync -rt`).
+    ///
+    /// The source directory's contents are mirrored into the destination; files
+    /// are compared by size and modification time unless --checksum is given.
+    Sync {
+        /// Source directory (bookmark:path or local path).
+        source: String,
+        /// Destination directory (bookmark:path or local path).
+        destination: String,
+        /// Show what would change without changing anything.
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+        /// Compare file contents (SHA-256) instead of size and mtime.
+        #[arg(short, long)]
+        checksum: bool,
+        /// Delete destination files that don't exist in the source.
+        #[arg(long)]
+        delete: bool,
+        /// Only sync files matching this glob (can be specified multiple times).
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+        /// Skip files and directories matching this glob (can be specified multiple times).
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+        /// Skip the production deletion confirmation (scripts only;
+        /// refused when stdin is a terminal).
+        #[arg(short, long)]
+        yes: bool,
     },
 
     /// Open dual-pane file browser to a bookmark.
@@ -105,15 +148,23 @@ pub enum Commands {
         action: TunnelAction,
     },
 
-    /// List all bookmarks (non-interactive).
+    /// List all bookmarks and group sessions (non-interactive).
     List {
         /// Filter by environment.
         #[arg(short, long)]
         env: Option<String>,
 
+        /// Filter by tag (can be specified multiple times, AND logic).
+        #[arg(short, long)]
+        tag: Vec<String>,
+
+        /// Filter by name pattern (glob-style: "prod-*").
+        #[arg(short, long)]
+        name: Option<String>,
+
         /// Output format.
-        #[arg(short, long, default_value = "table")]
-        format: String,
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: ListFormat,
     },
 
     /// Generate shell completions.
@@ -128,7 +179,8 @@ pub enum Commands {
         #[arg(value_name = "BOOKMARK")]
         bookmark: Option<String>,
 
-        /// Command to execute on the remote host(s).
+        /// Command to execute on the remote host(s), or the script's arguments
+        /// with --script.
         #[arg(last = true)]
         command: Vec<String>,
 
@@ -140,15 +192,73 @@ pub enum Commands {
         #[arg(short, long)]
         env: Option<String>,
 
+        /// Run a local script on the remote host(s), sent over stdin.
+        #[arg(long, value_name = "FILE")]
+        script: Option<String>,
+
+        /// Interpreter for --script (e.g. bash, python3).
+        #[arg(long, requires = "script", default_value = "sh")]
+        interpreter: String,
+
+        /// Send local stdin to the command. Piped stdin is forwarded automatically
+        /// for single-host exec; for multi-host exec it is read once and sent to
+        /// every host.
+        #[arg(long, conflicts_with = "script")]
+        stdin: bool,
+
+        /// Don't forward local stdin to single-host exec, like `ssh -n`. Use it
+        /// inside `while read` loops so the remote command doesn't consume the
+        /// loop's input.
+        #[arg(short = 'n', long, conflicts_with = "stdin")]
+        no_stdin: bool,
+
         /// Maximum concurrent SSH connections for multi-host exec.
         #[arg(long, default_value = "10")]
         concurrency: usize,
+
+        /// Result format for multi-host exec.
+        #[arg(long, value_enum, default_value = "text")]
+        output: ExecOutputFormat,
+
+        /// Stream output line by line as it arrives, prefixed with the host name
+        /// (text output only).
+        #[arg(long)]
+        stream: bool,
+
+        /// Stop starting new hosts after the first failure.
+        #[arg(long, conflicts_with = "ignore_errors")]
+        fail_fast: bool,
+
+        /// Exit 0 even when hosts fail or are unreachable.
+        #[arg(long)]
+        ignore_errors: bool,
+
+        /// Skip production and destructive-command confirmations (scripts only;
+        /// refused when stdin is a terminal).
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Rolling mode: run on the first host alone before the rest.
+        #[arg(long)]
+        canary: bool,
+
+        /// Rolling mode: hosts per batch, as a count ("5") or percentage ("25%").
+        #[arg(long, value_name = "N|P%")]
+        batch_size: Option<String>,
+
+        /// Rolling mode: seconds to wait between batches.
+        #[arg(long, value_name = "SECS", default_value = "0")]
+        pause: u64,
+
+        /// Rolling mode: failures tolerated per batch before halting ("0", "2", "10%").
+        #[arg(long, value_name = "N|P%", default_value = "0")]
+        max_failures: String,
     },
 
     /// Reset terminal tab title and color (use after abnormal sshore exit).
     ResetTab,
 
-    /// Export bookmarks to a portable TOML file.
+    /// Export bookmarks, groups and their profiles to a portable TOML file.
     Export {
         /// Filter by environment.
         #[arg(short, long)]
@@ -169,6 +279,20 @@ pub enum Commands {
         /// Include settings (env_colors, global snippets) in export.
         #[arg(long)]
         include_settings: bool,
+
+        /// Encrypt the export (age format). Prompts for a passphrase unless
+        /// --recipient is given.
+        #[arg(long)]
+        encrypt: bool,
+
+        /// Encrypt to a recipient: an age key (age1...), an SSH ed25519/RSA
+        /// public key, or a file of them. Repeatable; implies --encrypt.
+        #[arg(short, long = "recipient", value_name = "KEY")]
+        recipients: Vec<String>,
+
+        /// Sign the export with an SSH private key. `import` verifies the signature.
+        #[arg(long, value_name = "KEY_FILE")]
+        sign: Option<String>,
     },
 
     /// Internal: PTY hangup probe for integration tests.
@@ -199,14 +323,22 @@ pub enum TunnelAction {
         /// Bookmark name.
         bookmark: String,
 
-        /// Local port forwarding spec (local:remote_host:remote_port).
+        /// Local port forwarding spec ([bind_address:]local:remote_host:remote_port).
         #[arg(short = 'L')]
         local_forward: Vec<String>,
 
-        /// Remote port forwarding spec (remote:local_host:local_port).
+        /// Remote port forwarding spec ([bind_address:]remote:local_host:local_port).
         #[arg(short = 'R')]
         remote_forward: Vec<String>,
 
+        /// Dynamic SOCKS forwarding spec ([bind_address:]port).
+        #[arg(short = 'D')]
+        dynamic_forward: Vec<String>,
+
+        /// Name for this tunnel, so it can be stopped on its own.
+        #[arg(long)]
+        name: Option<String>,
+
         /// Keep tunnel alive across disconnects.
         #[arg(long)]
         persist: bool,
@@ -216,14 +348,26 @@ pub enum TunnelAction {
         daemon: bool,
     },
 
-    /// Stop a tunnel.
+    /// Stop a tunnel by ID or name, or all tunnels of a bookmark.
     Stop {
-        /// Bookmark name.
-        bookmark: String,
+        /// Tunnel ID, tunnel name, or bookmark name.
+        target: String,
     },
 
     /// Show active tunnels.
     Status,
+
+    /// Start saved tunnels from the config (autostart ones if no target).
+    Up {
+        /// Saved tunnel name, or bookmark name to start all of its tunnels.
+        target: Option<String>,
+    },
+
+    /// Stop running saved tunnels (all of them if no target).
+    Down {
+        /// Saved tunnel name, or bookmark name to stop all of its tunnels.
+        target: Option<String>,
+    },
 }
 
 /// Source format for import.
@@ -247,6 +391,32 @@ pub enum ImportSource {
     Sshore,
 }
 
+/// Output format for `sshore list`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+    /// Human-readable table
+    Table,
+    /// JSON array of objects
+    Json,
+    /// YAML list of objects
+    Yaml,
+    /// CSV with a header row
+    Csv,
+    /// Tab-separated values with a header row
+    Tsv,
+}
+
+/// Result format for multi-host `sshore exec`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExecOutputFormat {
+    /// Colored per-host output followed by a summary table
+    Text,
+    /// JSON array of per-host results, printed at the end
+    Json,
+    /// One JSON object per host, printed as each host finishes
+    Jsonl,
+}
+
 #[cfg(test)]
 mod tests {
     use clap::Parser;
@@ -382,12 +552,39 @@ mod tests {
     fn test_parse_list_subcommand() {
         let cli = Cli::try_parse_from(["sshore", "list"]).unwrap();
         match cli.command {
-            Some(Commands::List { env, format }) => {
+            Some(Commands::List {
+                env,
+                tag,
+                name,
+                format,
+            }) => {
                 assert!(env.is_none());
-                assert_eq!(format, "table");
+                assert!(tag.is_empty());
+                assert!(name.is_none());
+                assert_eq!(format, ListFormat::Table);
+            }
+            _ => panic!("Expected List command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_list_with_format_and_filters() {
+        let cli = Cli::try_parse_from([
+            "sshore", "list", "--format", "json", "--tag", "web", "-t", "prod", "--name", "db-*",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Commands::List {
+                tag, name, format, ..
+            }) => {
+                assert_eq!(tag, vec!["web", "prod"]);
+                assert_eq!(name, Some("db-*".into()));
+                assert_eq!(format, ListFormat::Json);
             }
             _ => panic!("Expected List command"),
         }
+
+        assert!(Cli::try_parse_from(["sshore", "list", "--format", "xml"]).is_err());
     }
 
     #[test]
@@ -427,10 +624,12 @@ mod tests {
                 source,
                 destination,
                 resume,
+                preserve,
             }) => {
                 assert_eq!(source, "myhost:/tmp/file");
                 assert_eq!(destination, "/local/path");
                 assert!(!resume);
+                assert!(!preserve);
             }
             _ => panic!("Expected Scp command"),
         }
@@ -454,6 +653,55 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_scp_with_preserve() {
+        let cli =
+            Cli::try_parse_from(["sshore", "scp", "-p", "./deploy.sh", "myhost:/srv/"]).unwrap();
+        match cli.command {
+            Some(Commands::Scp { preserve, .. }) => {
+                assert!(preserve);
+            }
+            _ => panic!("Expected Scp command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_sync() {
+        let cli = Cli::try_parse_from([
+            "sshore",
+            "sync",
+            "./site/",
+            "web:/var/www",
+            "-n",
+            "--delete",
+            "--exclude",
+            "*.log",
+            "--exclude",
+            ".git",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Commands::Sync {
+                source,
+                destination,
+                dry_run,
+                checksum,
+                delete,
+                include,
+                exclude,
+                yes,
+            }) => {
+                assert_eq!(source, "./site/");
+                assert_eq!(destination, "web:/var/www");
+                assert!(dry_run && delete);
+                assert!(!checksum && !yes);
+                assert!(include.is_empty());
+                assert_eq!(exclude, vec!["*.log", ".git"]);
+            }
+            _ => panic!("Expected Sync command"),
+        }
+    }
+
     #[test]
     fn test_parse_connect() {
         let cli = Cli::try_parse_from(["sshore", "connect", "user@host:2222"]).unwrap();
@@ -483,6 +731,8 @@ mod tests {
                         bookmark,
                         local_forward,
                         remote_forward,
+                        dynamic_forward,
+                        name,
                         persist,
                         daemon,
                     },
@@ -490,6 +740,8 @@ mod tests {
                 assert_eq!(bookmark, "myhost");
                 assert_eq!(local_forward, vec!["5432:localhost:5432"]);
                 assert!(remote_forward.is_empty());
+                assert!(dynamic_forward.is_empty());
+                assert_eq!(name, None);
                 assert!(!persist);
                 assert!(!daemon);
             }
@@ -554,19 +806,90 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_tunnel_start_dynamic() {
+        let cli = Cli::try_parse_from([
+            "sshore",
+            "tunnel",
+            "start",
+            "bastion",
+            "-D",
+            "1080",
+            "-D",
+            "0.0.0.0:1081",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Commands::Tunnel {
+                action:
+                    TunnelAction::Start {
+                        local_forward,
+                        dynamic_forward,
+                        ..
+                    },
+            }) => {
+                assert!(local_forward.is_empty());
+                assert_eq!(dynamic_forward, vec!["1080", "0.0.0.0:1081"]);
+            }
+            _ => panic!("Expected Tunnel Start command"),
+        }
+    }
+
     #[test]
     fn test_parse_tunnel_stop() {
         let cli = Cli::try_parse_from(["sshore", "tunnel", "stop", "myhost"]).unwrap();
         match cli.command {
             Some(Commands::Tunnel {
-                action: TunnelAction::Stop { bookmark },
+                action: TunnelAction::Stop { target },
             }) => {
-                assert_eq!(bookmark, "myhost");
+                assert_eq!(target, "myhost");
             }
             _ => panic!("Expected Tunnel Stop command"),
         }
     }
 
+    #[test]
+    fn test_parse_tunnel_up_down() {
+        let cli = Cli::try_parse_from(["sshore", "tunnel", "up"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Tunnel {
+                action: TunnelAction::Up { target: None }
+            })
+        ));
+
+        let cli = Cli::try_parse_from(["sshore", "tunnel", "down", "db"]).unwrap();
+        match cli.command {
+            Some(Commands::Tunnel {
+                action: TunnelAction::Down { target },
+            }) => assert_eq!(target.as_deref(), Some("db")),
+            _ => panic!("Expected Tunnel Down command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_tunnel_start_named() {
+        let cli = Cli::try_parse_from([
+            "sshore",
+            "tunnel",
+            "start",
+            "bastion",
+            "-L",
+            "5432:db:5432",
+            "--name",
+            "db",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Commands::Tunnel {
+                action: TunnelAction::Start { name, .. },
+            }) => {
+                assert_eq!(name.as_deref(), Some("db"));
+            }
+            _ => panic!("Expected Tunnel Start command"),
+        }
+    }
+
     #[test]
     fn test_parse_tunnel_status() {
         let cli = Cli::try_parse_from(["sshore", "tunnel", "status"]).unwrap();
@@ -587,18 +910,88 @@ mod tests {
                 command,
                 tag,
                 env,
+                script,
+                interpreter,
+                stdin,
+                no_stdin,
                 concurrency,
+                output,
+                stream,
+                fail_fast,
+                ignore_errors,
+                yes,
+                canary,
+                batch_size,
+                pause,
+                max_failures,
             }) => {
                 assert_eq!(bookmark, Some("myhost".into()));
                 assert_eq!(command, vec!["uptime"]);
                 assert!(tag.is_empty());
                 assert!(env.is_none());
+                assert!(script.is_none());
+                assert_eq!(interpreter, "sh");
+                assert!(!stdin);
+                assert!(!no_stdin);
                 assert_eq!(concurrency, 10);
+                assert_eq!(output, ExecOutputFormat::Text);
+                assert!(!stream);
+                assert!(!fail_fast);
+                assert!(!ignore_errors);
+                assert!(!yes);
+                assert!(!canary);
+                assert!(batch_size.is_none());
+                assert_eq!(pause, 0);
+                assert_eq!(max_failures, "0");
             }
             _ => panic!("Expected Exec command"),
         }
     }
 
+    #[test]
+    fn test_parse_exec_output_and_error_modes() {
+        let cli = Cli::try_parse_from([
+            "sshore",
+            "exec",
+            "--env",
+            "production",
+            "--output",
+            "jsonl",
+            "--fail-fast",
+            "--yes",
+            "--",
+            "uptime",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Commands::Exec {
+                output,
+                fail_fast,
+                ignore_errors,
+                yes,
+                ..
+            }) => {
+                assert_eq!(output, ExecOutputFormat::Jsonl);
+                assert!(fail_fast);
+                assert!(!ignore_errors);
+                assert!(yes);
+            }
+            _ => panic!("Expected Exec command"),
+        }
+
+        let result = Cli::try_parse_from([
+            "sshore",
+            "exec",
+            "--tag",
+            "web",
+            "--fail-fast",
+            "--ignore-errors",
+            "--",
+            "uptime",
+        ]);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_parse_exec_multi_host() {
         let cli = Cli::try_parse_from([
@@ -622,6 +1015,7 @@ mod tests {
                 tag,
                 env,
                 concurrency,
+                ..
             }) => {
                 assert!(bookmark.is_none());
                 assert_eq!(command, vec!["df", "-h"]);
@@ -633,6 +1027,117 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_exec_script() {
+        let cli = Cli::try_parse_from([
+            "sshore",
+            "exec",
+            "web-1",
+            "--script",
+            "deploy.sh",
+            "--interpreter",
+            "bash",
+            "--",
+            "v1.2",
+            "--force",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Commands::Exec {
+                bookmark,
+                command,
+                script,
+                interpreter,
+                ..
+            }) => {
+                assert_eq!(bookmark, Some("web-1".into()));
+                assert_eq!(script, Some("deploy.sh".into()));
+                assert_eq!(interpreter, "bash");
+                assert_eq!(command, vec!["v1.2", "--force"]);
+            }
+            _ => panic!("Expected Exec command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_exec_stdin_fan_out() {
+        let cli = Cli::try_parse_from([
+            "sshore", "exec", "--tag", "db", "--stdin", "--", "psql", "-q",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Commands::Exec { stdin, command, .. }) => {
+                assert!(stdin);
+                assert_eq!(command, vec!["psql", "-q"]);
+            }
+            _ => panic!("Expected Exec command"),
+        }
+
+        let result =
+            Cli::try_parse_from(["sshore", "exec", "web-1", "--stdin", "--script", "x.sh"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_exec_no_stdin() {
+        let cli = Cli::try_parse_from(["sshore", "exec", "-n", "web-1", "--", "uptime"]).unwrap();
+        match cli.command {
+            Some(Commands::Exec { no_stdin, .. }) => assert!(no_stdin),
+            _ => panic!("Expected Exec command"),
+        }
+
+        let result = Cli::try_parse_from([
+            "sshore",
+            "exec",
+            "web-1",
+            "--stdin",
+            "--no-stdin",
+            "--",
+            "uptime",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_exec_rolling() {
+        let cli = Cli::try_parse_from([
+            "sshore",
+            "exec",
+            "--tag",
+            "web",
+            "--stream",
+            "--canary",
+            "--batch-size",
+            "25%",
+            "--pause",
+            "30",
+            "--max-failures",
+            "1",
+            "--",
+            "systemctl",
+            "restart",
+            "nginx",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Commands::Exec {
+                stream,
+                canary,
+                batch_size,
+                pause,
+                max_failures,
+                ..
+            }) => {
+                assert!(stream);
+                assert!(canary);
+                assert_eq!(batch_size, Some("25%".into()));
+                assert_eq!(pause, 30);
+                assert_eq!(max_failures, "1");
+            }
+            _ => panic!("Expected Exec command"),
+        }
+    }
+
     #[test]
     fn test_parse_export() {
         let cli = Cli::try_parse_from([
@@ -652,6 +1157,7 @@ mod tests {
                 name,
                 output,
                 include_settings,
+                ..
             }) => {
                 assert_eq!(env, Some("production".into()));
                 assert!(tag.is_empty());
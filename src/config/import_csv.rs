@@ -133,6 +133,9 @@ pub fn parse_csv(
             ssh_options: BTreeMap::new(),
             connect_timeout_secs: None,
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         });
     }
 
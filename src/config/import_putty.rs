@@ -243,6 +243,9 @@ fn session_to_bookmark(
         ssh_options: BTreeMap::new(),
         connect_timeout_secs: None,
         profile: None,
+        use_agent: None,
+        certificate_file: None,
+        tunnels: vec![],
     }
 }
 
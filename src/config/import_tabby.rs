@@ -218,6 +218,9 @@ fn profile_to_bookmark(
         ssh_options: std::collections::BTreeMap::new(),
         connect_timeout_secs: None,
         profile: None,
+        use_agent: None,
+        certificate_file: None,
+        tunnels: vec![],
     }
 }
 
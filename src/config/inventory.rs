@@ -0,0 +1,300 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::config::matches_filters;
+use crate::config::model::AppConfig;
+use crate::ssh::DEFAULT_CONNECT_TIMEOUT_SECS;
+
+/// Machine-readable output formats for `sshore list`.
+///
+/// This mirrors `cli::ListFormat` minus the human table, which the CLI renders itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InventoryFormat {
+    Json,
+    Yaml,
+    Csv,
+    Tsv,
+}
+
+/// One bookmark or group session with its settings resolved through the
+/// profile chain (the values sshore would actually connect with).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InventoryEntry {
+    /// Bookmark name, or "group/session" for group sessions.
+    pub name: String,
+    /// Owning group for group sessions; `None` for flat bookmarks.
+    pub group: Option<String>,
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub env: String,
+    pub tags: Vec<String>,
+    pub profile: Option<String>,
+    pub identity_file: Option<String>,
+    pub proxy_jump: Option<String>,
+    pub connect_timeout_secs: u64,
+}
+
+/// Column headers for CSV/TSV output, in `InventoryEntry` field order.
+const COLUMNS: &[&str] = &[
+    "name",
+    "group",
+    "host",
+    "port",
+    "user",
+    "env",
+    "tags",
+    "profile",
+    "identity_file",
+    "proxy_jump",
+    "connect_timeout_secs",
+];
+
+/// Collect bookmarks and group sessions matching the `--env`, `--tag` and
+/// `--name` filters. Group sessions are matched on "group/session", the
+/// group's env and the group's tags.
+pub fn collect(
+    config: &AppConfig,
+    env_filter: Option<&str>,
+    tag_filters: &[String],
+    name_pattern: Option<&str>,
+) -> Vec<InventoryEntry> {
+    let settings = &config.settings;
+    let profiles = &config.profiles;
+    let mut entries = Vec::new();
+
+    for b in &config.bookmarks {
+        if !matches_filters(
+            &b.name,
+            &b.env,
+            &b.tags,
+            env_filter,
+            tag_filters,
+            name_pattern,
+        ) {
+            continue;
+        }
+        entries.push(InventoryEntry {
+            name: b.name.clone(),
+            group: None,
+            host: b.host.clone(),
+            port: b.port,
+            user: b.effective_user(settings, profiles),
+            env: b.env.clone(),
+            tags: b.tags.clone(),
+            profile: b.profile.clone(),
+            identity_file: b.effective_identity_file(profiles),
+            proxy_jump: b.effective_proxy_jump(profiles),
+            connect_timeout_secs: b
+                .effective_connect_timeout(settings, profiles)
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS),
+        });
+    }
+
+    for group in &config.groups {
+        for session in &group.sessions {
+            let name = session.display_name(group);
+            if !matches_filters(
+                &name,
+                &group.env,
+                &group.tags,
+                env_filter,
+                tag_filters,
+                name_pattern,
+            ) {
+                continue;
+            }
+            entries.push(InventoryEntry {
+                name,
+                group: Some(group.name.clone()),
+                host: session.effective_host(group),
+                port: session.effective_port(group),
+                user: session.effective_user(group, settings, profiles),
+                env: session.effective_env(group),
+                tags: group.tags.clone(),
+                profile: group.profile.clone(),
+                identity_file: session.effective_identity_file(group, profiles),
+                proxy_jump: session.effective_proxy_jump(group, profiles),
+                connect_timeout_secs: session
+                    .effective_connect_timeout(group, settings, profiles)
+                    .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS),
+            });
+        }
+    }
+
+    entries
+}
+
+/// Render entries in a machine-readable format.
+///
+/// JSON and YAML emit a list of objects. CSV and TSV emit a header row and
+/// one row per entry, with tags joined by `,` and unset values left empty.
+pub fn render(entries: &[InventoryEntry], format: InventoryFormat) -> Result<String> {
+    match format {
+        InventoryFormat::Json => {
+            let mut out =
+                serde_json::to_string_pretty(entries).context("Failed to serialize as JSON")?;
+            out.push('\n');
+            Ok(out)
+        }
+        InventoryFormat::Yaml => {
+            serde_yaml::to_string(entries).context("Failed to serialize as YAML")
+        }
+        InventoryFormat::Csv => render_delimited(entries, b','),
+        InventoryFormat::Tsv => render_delimited(entries, b'\t'),
+    }
+}
+
+fn render_delimited(entries: &[InventoryEntry], delimiter: u8) -> Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new());
+    writer.write_record(COLUMNS)?;
+
+    for e in entries {
+        writer.write_record([
+            e.name.as_str(),
+            e.group.as_deref().unwrap_or(""),
+            e.host.as_str(),
+            &e.port.to_string(),
+            e.user.as_str(),
+            e.env.as_str(),
+            &e.tags.join(","),
+            e.profile.as_deref().unwrap_or(""),
+            e.identity_file.as_deref().unwrap_or(""),
+            e.proxy_jump.as_deref().unwrap_or(""),
+            &e.connect_timeout_secs.to_string(),
+        ])?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| anyhow::anyhow!("Failed to flush output: {e}"))?;
+    String::from_utf8(bytes).context("Output is not valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[settings]
+default_user = "me"
+connect_timeout_secs = 20
+
+[[profiles]]
+name = "ops"
+user = "deploy"
+identity_file = "~/.ssh/ops"
+proxy_jump = "bastion"
+connect_timeout_secs = 30
+
+[[bookmarks]]
+name = "prod-web"
+host = "10.0.0.1"
+env = "production"
+tags = ["web", "frontend"]
+profile = "ops"
+
+[[bookmarks]]
+name = "dev-box"
+host = "dev.local"
+port = 2222
+user = "alice"
+env = "development"
+
+[[groups]]
+name = "prod-db"
+host = "db.internal"
+env = "production"
+tags = ["db"]
+profile = "ops"
+
+[[groups.sessions]]
+name = "psql"
+user = "postgres"
+"#;
+
+    fn config() -> AppConfig {
+        toml::from_str(CONFIG).unwrap()
+    }
+
+    #[test]
+    fn test_collect_resolves_profile_chain() {
+        let entries = collect(&config(), None, &[], None);
+        assert_eq!(entries.len(), 3);
+
+        let web = &entries[0];
+        assert_eq!(web.user, "deploy");
+        assert_eq!(web.identity_file.as_deref(), Some("~/.ssh/ops"));
+        assert_eq!(web.proxy_jump.as_deref(), Some("bastion"));
+        assert_eq!(web.connect_timeout_secs, 30);
+
+        let dev = &entries[1];
+        assert_eq!(dev.user, "alice");
+        assert_eq!(dev.identity_file, None);
+        assert_eq!(dev.connect_timeout_secs, 20);
+    }
+
+    #[test]
+    fn test_collect_includes_group_sessions() {
+        let entries = collect(&config(), None, &[], None);
+        let psql = &entries[2];
+        assert_eq!(psql.name, "prod-db/psql");
+        assert_eq!(psql.group.as_deref(), Some("prod-db"));
+        assert_eq!(psql.host, "db.internal");
+        assert_eq!(psql.user, "postgres");
+        assert_eq!(psql.proxy_jump.as_deref(), Some("bastion"));
+    }
+
+    #[test]
+    fn test_collect_filters() {
+        let cfg = config();
+        let prod = collect(&cfg, Some("production"), &[], None);
+        assert_eq!(prod.len(), 2);
+
+        let web = collect(&cfg, None, &["web".to_string()], None);
+        assert_eq!(web.len(), 1);
+        assert_eq!(web[0].name, "prod-web");
+
+        let named = collect(&cfg, None, &[], Some("prod-*"));
+        let names: Vec<&str> = named.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["prod-web", "prod-db/psql"]);
+    }
+
+    #[test]
+    fn test_render_json_and_yaml() {
+        let entries = collect(&config(), None, &[], Some("dev-box"));
+
+        let json = render(&entries, InventoryFormat::Json).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[0]["name"], "dev-box");
+        assert_eq!(parsed[0]["port"], 2222);
+        assert!(parsed[0]["group"].is_null());
+
+        let yaml = render(&entries, InventoryFormat::Yaml).unwrap();
+        assert!(yaml.contains("name: dev-box"));
+        assert!(yaml.contains("port: 2222"));
+    }
+
+    #[test]
+    fn test_render_csv_and_tsv() {
+        let entries = collect(&config(), None, &["web".to_string()], None);
+
+        let csv = render(&entries, InventoryFormat::Csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), COLUMNS.join(","));
+        assert_eq!(
+            lines.next().unwrap(),
+            "prod-web,,10.0.0.1,22,deploy,production,\"web,frontend\",ops,~/.ssh/ops,bastion,30"
+        );
+
+        let tsv = render(&entries, InventoryFormat::Tsv).unwrap();
+        assert!(
+            tsv.lines()
+                .nth(1)
+                .unwrap()
+                .starts_with("prod-web\t\t10.0.0.1\t22\t")
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use anyhow::{Context, Result, bail};
+use toml::{Table, Value};
+
+use crate::config::model::AppConfig;
+use crate::config::ssh_import::{IMPORTED_TUNNEL_NAME, normalize_forward};
+
+/// Schema version written by this build.
+///
+/// Bump it together with a new entry in [`MIGRATIONS`] whenever the on-disk
+/// layout changes in a way older files need rewriting for.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// One step of the migration pipeline: upgrades a raw config table from
+/// schema `from` to `from + 1`.
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(&mut Table) -> Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "add schema_version",
+        // Unversioned files already match the v1 layout; stamping the version is enough.
+        apply: |_| Ok(()),
+    },
+    Migration {
+        from: 1,
+        description: "move ssh_config forwards into saved tunnels",
+        apply: forwards_to_tunnels,
+    },
+];
+
+/// Forward directives older ssh_config imports stored in `ssh_options`, with
+/// the saved tunnel field each one moves to.
+const FORWARD_OPTIONS: &[(&str, &str)] = &[
+    ("localforward", "local"),
+    ("remoteforward", "remote"),
+    ("dynamicforward", "dynamic"),
+];
+
+/// v1 -> v2: move `LocalForward`/`RemoteForward`/`DynamicForward` out of each
+/// bookmark's `ssh_options` into its imported saved tunnel, as the ssh_config
+/// importer does today. Left in place they do nothing but warn on every connect.
+fn forwards_to_tunnels(table: &mut Table) -> Result<()> {
+    let Some(Value::Array(bookmarks)) = table.get_mut("bookmarks") else {
+        return Ok(());
+    };
+    for bookmark in bookmarks.iter_mut().filter_map(Value::as_table_mut) {
+        let Some(Value::Table(options)) = bookmark.get_mut("ssh_options") else {
+            continue;
+        };
+        let keys: Vec<String> = options.keys().cloned().collect();
+        let mut moved: Vec<(&str, String)> = Vec::new();
+        for key in keys {
+            let lower = key.to_ascii_lowercase();
+            let Some(&(directive, field)) = FORWARD_OPTIONS.iter().find(|(d, _)| *d == lower)
+            else {
+                continue;
+            };
+            if let Some(Value::String(value)) = options.remove(&key) {
+                let forward = if directive == "dynamicforward" {
+                    value.trim().to_string()
+                } else {
+                    normalize_forward(&value)
+                };
+                moved.push((field, forward));
+            }
+        }
+        if moved.is_empty() {
+            continue;
+        }
+
+        let tunnels = bookmark
+            .entry("tunnels")
+            .or_insert_with(|| Value::Array(Vec::new()))
+            .as_array_mut()
+            .context("bookmark tunnels is not an array")?;
+        let existing = tunnels
+            .iter()
+            .position(|t| t.get("name").and_then(Value::as_str) == Some(IMPORTED_TUNNEL_NAME));
+        let index = match existing {
+            Some(index) => index,
+            None => {
+                let mut tunnel = Table::new();
+                tunnel.insert("name".into(), Value::String(IMPORTED_TUNNEL_NAME.into()));
+                tunnels.push(Value::Table(tunnel));
+                tunnels.len() - 1
+            }
+        };
+        let tunnel = tunnels[index]
+            .as_table_mut()
+            .context("saved tunnel is not a table")?;
+        for (field, forward) in moved {
+            tunnel
+                .entry(field)
+                .or_insert_with(|| Value::Array(Vec::new()))
+                .as_array_mut()
+                .with_context(|| format!("saved tunnel field '{field}' is not an array"))?
+                .push(Value::String(forward));
+        }
+    }
+    Ok(())
+}
+
+/// Read `schema_version` from a raw config table (missing means 0).
+pub fn schema_version(table: &Table) -> Result<u32> {
+    match table.get("schema_version") {
+        None => Ok(0),
+        Some(Value::Integer(v)) => {
+            u32::try_from(*v).with_context(|| format!("Invalid schema_version {v}"))
+        }
+        Some(other) => bail!("Invalid schema_version {other}: expected an integer"),
+    }
+}
+
+/// Parse config TOML, running the migrations its schema version needs.
+///
+/// Returns the config and, if migrations ran, the version it was migrated
+/// from. A config from a newer schema is returned as-is — it keeps its
+/// `schema_version`, which makes `atomic_write` refuse to save it.
+pub fn parse_and_migrate(content: &str) -> Result<(AppConfig, Option<u32>)> {
+    let mut table: Table = content.parse()?;
+    let from = schema_version(&table)?;
+    let migrated = if from < CURRENT_SCHEMA_VERSION {
+        run(&mut table, from, CURRENT_SCHEMA_VERSION, MIGRATIONS)?;
+        Some(from)
+    } else {
+        None
+    };
+    let config: AppConfig = table.try_into()?;
+    Ok((config, migrated))
+}
+
+/// Whether `config` came from a schema newer than this build understands.
+pub fn is_newer_schema(config: &AppConfig) -> bool {
+    config.schema_version > CURRENT_SCHEMA_VERSION
+}
+
+/// Apply `migrations` in order to bring `table` from `from` up to `to`.
+fn run(table: &mut Table, from: u32, to: u32, migrations: &[Migration]) -> Result<()> {
+    for version in from..to {
+        let migration = migrations
+            .iter()
+            .find(|m| m.from == version)
+            .with_context(|| format!("No migration from config schema v{version}"))?;
+        (migration.apply)(table).with_context(|| {
+            format!(
+                "Config migration v{version} -> v{} ({}) failed",
+                version + 1,
+                migration.description
+            )
+        })?;
+        table.insert(
+            "schema_version".into(),
+            Value::Integer(i64::from(version + 1)),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_version_defaults_to_zero() {
+        let table: Table = "[settings]\n".parse().unwrap();
+        assert_eq!(schema_version(&table).unwrap(), 0);
+
+        let table: Table = "schema_version = 3\n".parse().unwrap();
+        assert_eq!(schema_version(&table).unwrap(), 3);
+
+        let table: Table = "schema_version = \"one\"\n".parse().unwrap();
+        assert!(schema_version(&table).is_err());
+        let table: Table = "schema_version = -1\n".parse().unwrap();
+        assert!(schema_version(&table).is_err());
+    }
+
+    #[test]
+    fn test_parse_and_migrate_legacy_config() {
+        let (config, migrated) =
+            parse_and_migrate("[[bookmarks]]\nname = \"web\"\nhost = \"10.0.0.1\"\n").unwrap();
+        assert_eq!(migrated, Some(0));
+        assert_eq!(config.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(config.bookmarks[0].name, "web");
+    }
+
+    #[test]
+    fn test_parse_current_config_needs_no_migration() {
+        let content = format!("schema_version = {CURRENT_SCHEMA_VERSION}\n");
+        let (config, migrated) = parse_and_migrate(&content).unwrap();
+        assert_eq!(migrated, None);
+        assert!(!is_newer_schema(&config));
+    }
+
+    #[test]
+    fn test_newer_schema_is_kept() {
+        let content = format!(
+            "schema_version = {}\nfuture_field = true\n",
+            CURRENT_SCHEMA_VERSION + 1
+        );
+        let (config, migrated) = parse_and_migrate(&content).unwrap();
+        assert_eq!(migrated, None);
+        assert!(is_newer_schema(&config));
+    }
+
+    #[test]
+    fn test_v1_forwards_move_into_saved_tunnel() {
+        let content = r#"
+schema_version = 1
+
+[[bookmarks]]
+name = "bastion"
+host = "bastion.example.com"
+
+[bookmarks.ssh_options]
+LocalForward = "5432 db.internal:5432"
+RemoteForward = "9000 localhost:9000"
+DynamicForward = "1080"
+ServerAliveInterval = "60"
+
+[[bookmarks]]
+name = "web"
+host = "10.0.0.1"
+"#;
+        let (config, migrated) = parse_and_migrate(content).unwrap();
+        assert_eq!(migrated, Some(1));
+
+        let bastion = &config.bookmarks[0];
+        assert_eq!(bastion.ssh_options.len(), 1);
+        assert!(bastion.ssh_options.contains_key("ServerAliveInterval"));
+        assert_eq!(bastion.tunnels.len(), 1);
+        let tunnel = &bastion.tunnels[0];
+        assert_eq!(tunnel.name, IMPORTED_TUNNEL_NAME);
+        assert_eq!(tunnel.local, vec!["5432:db.internal:5432"]);
+        assert_eq!(tunnel.remote, vec!["9000:localhost:9000"]);
+        assert_eq!(tunnel.dynamic, vec!["1080"]);
+        assert!(!tunnel.autostart);
+
+        assert!(config.bookmarks[1].tunnels.is_empty());
+    }
+
+    #[test]
+    fn test_run_chains_migrations_in_order() {
+        const STEPS: &[Migration] = &[
+            Migration {
+                from: 1,
+                description: "rename hosts to bookmarks",
+                apply: |t| {
+                    if let Some(hosts) = t.remove("hosts") {
+                        t.insert("bookmarks".into(), hosts);
+                    }
+                    Ok(())
+                },
+            },
+            Migration {
+                from: 0,
+                description: "noop",
+                apply: |_| Ok(()),
+            },
+        ];
+        let mut table: Table = "[[hosts]]\nname = \"web\"\nhost = \"h\"\n".parse().unwrap();
+        run(&mut table, 0, 2, STEPS).unwrap();
+        assert!(table.contains_key("bookmarks"));
+        assert_eq!(schema_version(&table).unwrap(), 2);
+
+        // A gap in the pipeline is an error rather than a silent skip
+        let mut table = Table::new();
+        assert!(run(&mut table, 0, 3, STEPS).is_err());
+    }
+}
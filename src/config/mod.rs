@@ -1,3 +1,4 @@
+pub mod bundle;
 pub mod env;
 pub mod import_csv;
 pub mod import_json;
@@ -5,14 +6,17 @@ pub mod import_mobaxterm;
 pub mod import_putty;
 pub mod import_securecrt;
 pub mod import_tabby;
+pub mod inventory;
+pub mod migrate;
 pub mod model;
 pub mod ssh_import;
+pub mod team;
 pub mod writer;
 
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 
 use std::collections::HashSet;
 
@@ -20,6 +24,7 @@ use crate::config::model::{
     AppConfig, Bookmark, BookmarkGroup, Profile, Settings, validate_bookmark_name,
     validate_hostname,
 };
+use crate::config::ssh_import::ImportedStructure;
 use crate::config::writer::atomic_write;
 
 /// Return the XDG-compliant config file path.
@@ -115,7 +120,11 @@ fn resolve_config_path(custom_path: Option<&str>) -> PathBuf {
     }
 }
 
-/// Load config from a specific path.
+/// Load config from a specific path, with any team config sources merged
+/// beneath it (see [`team::merge_team_layers`]).
+///
+/// Configs from an older schema are migrated and saved back, after copying
+/// the original to `config.toml.v<N>.bak`.
 ///
 /// If the file doesn't exist, creates a default config, saves it, and prints
 /// a welcome message suggesting `sshore import`.
@@ -131,8 +140,8 @@ pub fn load_from(path: &Path) -> Result<AppConfig> {
     let content = fs::read_to_string(path)
         .with_context(|| format!("Failed to read config file: {}", path.display()))?;
 
-    let config: AppConfig = match toml::from_str(&content) {
-        Ok(c) => c,
+    let (mut config, migrated_from) = match migrate::parse_and_migrate(&content) {
+        Ok(parsed) => parsed,
         Err(e) => {
             // If a .bak file exists, hint at recovery
             let backup_path = path.with_extension("toml.bak");
@@ -149,12 +158,44 @@ pub fn load_from(path: &Path) -> Result<AppConfig> {
 
     validate_profiles(&config.profiles)?;
     validate_on_connect_fields(&config.profiles, &config.bookmarks)?;
-    validate_groups(&config.groups, &config.profiles)?;
-
-    warn_dangling_profiles(&config.bookmarks, &config.profiles);
     warn_duplicate_names(&config.bookmarks);
     validate_bookmarks(&config.bookmarks);
 
+    // Personal entries may use team profiles, so profile references are only
+    // checked once the team layers are in. Merging appends, so the personal
+    // entries stay at the front.
+    let personal_bookmarks = config.bookmarks.len();
+    let personal_groups = config.groups.len();
+    team::merge_team_layers(&mut config, path);
+    validate_groups(&config.groups[..personal_groups], &config.profiles)?;
+    warn_dangling_profiles(&config.bookmarks[..personal_bookmarks], &config.profiles);
+
+    if let Some(from) = migrated_from {
+        // Keep the pre-migration file: an older sshore can't read the new one
+        let backup_path = path.with_extension(format!("toml.v{from}.bak"));
+        fs::copy(path, &backup_path).with_context(|| {
+            format!(
+                "Failed to back up config to {} before migrating",
+                backup_path.display()
+            )
+        })?;
+        save_to(&config, path)?;
+        eprintln!(
+            "Migrated config {} from schema v{from} to v{} (backup: {})",
+            path.display(),
+            migrate::CURRENT_SCHEMA_VERSION,
+            backup_path.display()
+        );
+    } else if migrate::is_newer_schema(&config) {
+        eprintln!(
+            "Warning: config {} was written by a newer sshore (schema v{}, this build supports v{}). \
+             It is read-only until you upgrade.",
+            path.display(),
+            config.schema_version,
+            migrate::CURRENT_SCHEMA_VERSION
+        );
+    }
+
     Ok(config)
 }
 
@@ -423,6 +464,55 @@ pub enum ImportSourceKind {
     Json,
 }
 
+/// Read the profiles and groups from a sshore TOML export, which other
+/// sources don't have. Returns an empty structure for every other format
+/// (including ssh_config files under auto-detection).
+pub fn import_structure(path: &Path, source: &ImportSourceKind) -> Result<ImportedStructure> {
+    if !matches!(source, ImportSourceKind::Sshore | ImportSourceKind::Auto) {
+        return Ok(ImportedStructure::default());
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read import file: {}", path.display()))?;
+    if *source == ImportSourceKind::Auto && !ssh_import::is_sshore_toml(&content) {
+        return Ok(ImportedStructure::default());
+    }
+    let config = parse_sshore_toml(&content)?;
+    Ok(ImportedStructure {
+        profiles: config.profiles,
+        groups: config.groups,
+    })
+}
+
+/// Parse the bookmarks, profiles and groups of a sshore export held in memory
+/// (e.g. a decrypted export bundle).
+pub fn parse_sshore_export(content: &str) -> Result<(Vec<Bookmark>, ImportedStructure)> {
+    let config = parse_sshore_toml(content)?;
+    Ok((
+        config.bookmarks,
+        ImportedStructure {
+            profiles: config.profiles,
+            groups: config.groups,
+        },
+    ))
+}
+
+/// Parse a sshore export, migrating it if an older sshore wrote it.
+/// Exports from a newer sshore are refused: fields this build doesn't know
+/// would be dropped without notice.
+pub(crate) fn parse_sshore_toml(content: &str) -> Result<AppConfig> {
+    let (config, _) =
+        migrate::parse_and_migrate(content).context("Failed to parse sshore TOML export file")?;
+    if migrate::is_newer_schema(&config) {
+        bail!(
+            "Export was written by a newer sshore (schema v{}, this build supports v{}). \
+             Upgrade sshore to import it.",
+            config.schema_version,
+            migrate::CURRENT_SCHEMA_VERSION
+        );
+    }
+    Ok(config)
+}
+
 /// Import bookmarks from a file using the specified source format.
 pub fn import_from_source(
     path: &Path,
@@ -439,8 +529,7 @@ pub fn import_from_source(
         ImportSourceKind::Sshore => {
             let content = fs::read_to_string(path)
                 .with_context(|| format!("Failed to read import file: {}", path.display()))?;
-            let config: AppConfig =
-                toml::from_str(&content).context("Failed to parse sshore TOML export file")?;
+            let config = parse_sshore_toml(&content)?;
             Ok(config.bookmarks)
         }
         ImportSourceKind::Putty => {
@@ -476,7 +565,7 @@ pub fn import_from_source(
     }
 }
 
-/// Export filtered bookmarks as a TOML string.
+/// Export filtered bookmarks, groups and the profiles they reference as a TOML string.
 /// Passwords are NEVER exported (they live in OS keychain).
 pub fn export_bookmarks(
     config: &AppConfig,
@@ -489,27 +578,21 @@ pub fn export_bookmarks(
         .bookmarks
         .iter()
         .filter(|b| {
-            if let Some(env) = env_filter
-                && !b.env.eq_ignore_ascii_case(env)
-            {
-                return false;
-            }
-            for tag in tag_filters {
-                if !b.tags.contains(tag) {
-                    return false;
-                }
-            }
-            if let Some(pattern) = name_pattern
-                && !glob_match(pattern, &b.name)
-            {
-                return false;
-            }
-            true
+            matches_filters(
+                &b.name,
+                &b.env,
+                &b.tags,
+                env_filter,
+                tag_filters,
+                name_pattern,
+            )
         })
         .collect();
 
-    if filtered.is_empty() {
-        anyhow::bail!("No bookmarks match the given filters");
+    let export_groups = filter_groups(&config.groups, env_filter, tag_filters, name_pattern);
+
+    if filtered.is_empty() && export_groups.is_empty() {
+        anyhow::bail!("No bookmarks or groups match the given filters");
     }
 
     let mut export_bookmarks: Vec<Bookmark> = filtered.into_iter().cloned().collect();
@@ -522,32 +605,60 @@ pub fn export_bookmarks(
 
     // Warn about dangling proxy_jump references
     let exported_names: HashSet<&str> = export_bookmarks.iter().map(|b| b.name.as_str()).collect();
-    for bookmark in &export_bookmarks {
-        if let Some(ref pj) = bookmark.proxy_jump
+    let proxy_jumps = export_bookmarks
+        .iter()
+        .map(|b| (b.name.clone(), b.proxy_jump.as_ref()))
+        .chain(
+            export_groups
+                .iter()
+                .map(|g| (g.name.clone(), g.proxy_jump.as_ref())),
+        )
+        .chain(export_groups.iter().flat_map(|g| {
+            g.sessions
+                .iter()
+                .map(move |s| (s.display_name(g), s.proxy_jump.as_ref()))
+        }));
+    for (name, proxy_jump) in proxy_jumps {
+        if let Some(pj) = proxy_jump
             && !exported_names.contains(pj.as_str())
         {
             eprintln!(
                 "Warning: {} references proxy_jump \"{}\" which is not in the export.\n   \
                  The recipient will need to configure \"{}\" separately.",
-                bookmark.name, pj, pj
+                name, pj, pj
             );
         }
     }
 
-    let export_config = if include_settings {
-        AppConfig {
-            settings: config.settings.clone(),
-            bookmarks: export_bookmarks,
-            profiles: config.profiles.clone(),
-            groups: vec![],
-        }
+    // Profiles the exported entries inherit from travel with them; settings
+    // (and unreferenced profiles) only with --include-settings
+    let profiles: Vec<Profile> = if include_settings {
+        config.profiles.clone()
     } else {
-        AppConfig {
-            settings: Settings::default(),
-            bookmarks: export_bookmarks,
-            profiles: Vec::new(),
-            groups: vec![],
-        }
+        let referenced: HashSet<&str> = export_bookmarks
+            .iter()
+            .filter_map(|b| b.profile.as_deref())
+            .chain(export_groups.iter().filter_map(|g| g.profile.as_deref()))
+            .collect();
+        config
+            .profiles
+            .iter()
+            .filter(|p| referenced.contains(p.name.as_str()))
+            .cloned()
+            .collect()
+    };
+    let settings = if include_settings {
+        config.settings.clone()
+    } else {
+        Settings::default()
+    };
+    let export_config = AppConfig {
+        schema_version: migrate::CURRENT_SCHEMA_VERSION,
+        settings,
+        bookmarks: export_bookmarks,
+        profiles,
+        groups: export_groups,
+        team: Default::default(),
     };
 
     let toml_string =
@@ -557,14 +668,86 @@ pub fn export_bookmarks(
         "# sshore bookmark export\n\
          # Generated: {}\n\
          # Bookmarks: {}\n\
+         # Groups: {}\n\
+         # Profiles: {}\n\
          # Passwords are stored in the OS keychain and are NOT included.\n\n",
         chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
         export_config.bookmarks.len(),
+        export_config.groups.len(),
+        export_config.profiles.len(),
     );
 
     Ok(format!("{}{}", header, toml_string))
 }
 
+/// Select the groups to export. Env and tags are matched on the group; the
+/// name pattern keeps the whole group when it matches the group name, and
+/// otherwise only the sessions whose "group/session" name matches.
+fn filter_groups(
+    groups: &[BookmarkGroup],
+    env_filter: Option<&str>,
+    tag_filters: &[String],
+    name_pattern: Option<&str>,
+) -> Vec<BookmarkGroup> {
+    let mut selected = Vec::new();
+    for group in groups {
+        if !matches_filters(
+            &group.name,
+            &group.env,
+            &group.tags,
+            env_filter,
+            tag_filters,
+            None,
+        ) {
+            continue;
+        }
+        match name_pattern {
+            Some(pattern) if !glob_match(pattern, &group.name) => {
+                let sessions: Vec<_> = group
+                    .sessions
+                    .iter()
+                    .filter(|s| glob_match(pattern, &s.display_name(group)))
+                    .cloned()
+                    .collect();
+                if !sessions.is_empty() {
+                    selected.push(BookmarkGroup {
+                        sessions,
+                        ..group.clone()
+                    });
+                }
+            }
+            _ => selected.push(group.clone()),
+        }
+    }
+    selected
+}
+
+/// Whether an entry passes the `--env`, `--tag` (all must match) and `--name`
+/// (glob) filters shared by `export` and `list`.
+pub(crate) fn matches_filters(
+    name: &str,
+    env: &str,
+    tags: &[String],
+    env_filter: Option<&str>,
+    tag_filters: &[String],
+    name_pattern: Option<&str>,
+) -> bool {
+    if let Some(wanted) = env_filter
+        && !env.eq_ignore_ascii_case(wanted)
+    {
+        return false;
+    }
+    if !tag_filters.iter().all(|tag| tags.contains(tag)) {
+        return false;
+    }
+    if let Some(pattern) = name_pattern
+        && !glob_match(pattern, name)
+    {
+        return false;
+    }
+    true
+}
+
 /// Simple glob matching: `*` matches any sequence of characters.
 fn glob_match(pattern: &str, text: &str) -> bool {
     let regex_pattern = format!("^{}$", regex::escape(pattern).replace(r"\*", ".*"));
@@ -635,6 +818,9 @@ mod tests {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         });
         save_to(&config, &path).unwrap();
 
@@ -679,6 +865,9 @@ mod tests {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         }
     }
 
@@ -693,6 +882,7 @@ mod tests {
                 sample_bookmark("staging-api", "staging", vec!["web".into(), "api".into()]),
                 sample_bookmark("dev-local", "development", vec![]),
             ],
+            ..Default::default()
         }
     }
 
@@ -767,6 +957,117 @@ mod tests {
         assert!(result.contains("env_colors"));
     }
 
+    /// Config with two profiles (one unused), a bookmark and a group with two sessions.
+    fn sample_config_with_groups() -> AppConfig {
+        let mut config = sample_config_with_bookmarks();
+        config.profiles = vec![
+            Profile {
+                name: "ops".into(),
+                user: Some("deploy".into()),
+                ..Default::default()
+            },
+            Profile {
+                name: "personal".into(),
+                user: Some("me".into()),
+                ..Default::default()
+            },
+        ];
+        config.bookmarks[0].profile = Some("ops".into());
+        config.groups = vec![model::BookmarkGroup {
+            name: "prod-app".into(),
+            tags: vec!["web".into()],
+            profile: Some("ops".into()),
+            sessions: vec![
+                model::Session {
+                    name: "api".into(),
+                    on_connect: Some("cd /srv/api".into()),
+                    ..sample_session()
+                },
+                model::Session {
+                    name: "worker".into(),
+                    ..sample_session()
+                },
+            ],
+            ..sample_group()
+        }];
+        config
+    }
+
+    fn parse_export(result: &str) -> AppConfig {
+        toml::from_str(result).unwrap()
+    }
+
+    #[test]
+    fn test_export_includes_groups_and_referenced_profiles() {
+        let config = sample_config_with_groups();
+        let exported =
+            parse_export(&export_bookmarks(&config, Some("production"), &[], None, false).unwrap());
+        assert_eq!(exported.groups, config.groups);
+        let profile_names: Vec<&str> = exported.profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(profile_names, vec!["ops"]);
+        // Personal settings stay behind without --include-settings
+        assert_eq!(exported.settings, Settings::default());
+    }
+
+    #[test]
+    fn test_export_groups_follow_filters() {
+        let config = sample_config_with_groups();
+
+        let exported =
+            parse_export(&export_bookmarks(&config, Some("staging"), &[], None, false).unwrap());
+        assert!(exported.groups.is_empty());
+
+        // Name pattern on a session keeps the group with only matching sessions
+        let exported = parse_export(
+            &export_bookmarks(&config, None, &[], Some("prod-app/w*"), false).unwrap(),
+        );
+        assert!(exported.bookmarks.is_empty());
+        assert_eq!(exported.groups.len(), 1);
+        assert_eq!(exported.groups[0].sessions.len(), 1);
+        assert_eq!(exported.groups[0].sessions[0].name, "worker");
+
+        // Group-only export still succeeds
+        let exported = parse_export(
+            &export_bookmarks(&config, None, &["web".into()], Some("prod-app"), false).unwrap(),
+        );
+        assert_eq!(exported.groups[0].sessions.len(), 2);
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let config = sample_config_with_groups();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.toml");
+        std::fs::write(
+            &path,
+            export_bookmarks(&config, None, &[], None, false).unwrap(),
+        )
+        .unwrap();
+
+        for source in [ImportSourceKind::Sshore, ImportSourceKind::Auto] {
+            let bookmarks = import_from_source(&path, source.clone(), None, &[]).unwrap();
+            let structure = import_structure(&path, &source).unwrap();
+
+            let mut target = AppConfig::default();
+            ssh_import::merge_imports(&mut target.bookmarks, bookmarks, false);
+            ssh_import::merge_structure(&mut target, structure, false);
+
+            assert_eq!(target.groups, config.groups);
+            assert_eq!(target.profiles, vec![config.profiles[0].clone()]);
+            assert_eq!(target.bookmarks.len(), config.bookmarks.len());
+            for (imported, original) in target.bookmarks.iter().zip(&config.bookmarks) {
+                assert_eq!(
+                    imported,
+                    &Bookmark {
+                        last_connected: None,
+                        connect_count: 0,
+                        ..original.clone()
+                    }
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_glob_match_patterns() {
         assert!(glob_match("prod-*", "prod-web-01"));
@@ -791,6 +1092,7 @@ mod tests {
             profiles: Vec::new(),
             groups: vec![],
             bookmarks: vec![sample_bookmark("prod-web-01", "production", vec![])],
+            ..Default::default()
         };
         let toml_str = toml::to_string_pretty(&config).unwrap();
         std::fs::write(&path, &toml_str).unwrap();
@@ -997,6 +1299,66 @@ mod tests {
         assert_eq!(config.bookmarks[0].name, "test");
     }
 
+    #[test]
+    fn test_load_migrates_legacy_config_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let legacy = "[[bookmarks]]\nname = \"web\"\nhost = \"10.0.0.1\"\n";
+        std::fs::write(&path, legacy).unwrap();
+
+        let config = load_from(&path).unwrap();
+        assert_eq!(config.schema_version, migrate::CURRENT_SCHEMA_VERSION);
+        assert_eq!(config.bookmarks[0].name, "web");
+
+        // Original kept untouched, migrated file stamped with the version
+        let backup = dir.path().join("config.toml.v0.bak");
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), legacy);
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.contains(&format!(
+            "schema_version = {}",
+            migrate::CURRENT_SCHEMA_VERSION
+        )));
+
+        // Second load needs no migration and leaves the backup alone
+        std::fs::remove_file(&backup).unwrap();
+        load_from(&path).unwrap();
+        assert!(!backup.exists());
+    }
+
+    #[test]
+    fn test_config_from_newer_schema_is_read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let content = format!(
+            "schema_version = {}\n\n[[bookmarks]]\nname = \"web\"\nhost = \"10.0.0.1\"\n",
+            migrate::CURRENT_SCHEMA_VERSION + 1
+        );
+        std::fs::write(&path, &content).unwrap();
+
+        let config = load_from(&path).unwrap();
+        assert_eq!(config.bookmarks.len(), 1);
+        let err = save_to(&config, &path).unwrap_err();
+        assert!(err.to_string().contains("newer sshore"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_import_export_from_newer_schema_is_refused() {
+        let content = format!(
+            "schema_version = {}\n\n[[bookmarks]]\nname = \"web\"\nhost = \"10.0.0.1\"\n",
+            migrate::CURRENT_SCHEMA_VERSION + 1
+        );
+        let err = parse_sshore_export(&content).unwrap_err();
+        assert!(err.to_string().contains("newer sshore"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.toml");
+        std::fs::write(&path, &content).unwrap();
+        for source in [ImportSourceKind::Auto, ImportSourceKind::Sshore] {
+            assert!(import_from_source(&path, source, None, &[]).is_err());
+        }
+    }
+
     #[test]
     fn test_import_nonexistent_file_returns_error() {
         let path = std::path::Path::new("/tmp/sshore_test_nonexistent_file_12345.csv");
@@ -1500,7 +1862,7 @@ profile = "deleted-profile"
 
     #[test]
     fn test_validate_groups_duplicate_group_names_rejected() {
-        let mut g1 = sample_group();
+        let g1 = sample_group();
         let mut g2 = sample_group();
         g2.name = "prod-servers".into();
         let groups = vec![g1, g2];
@@ -1765,6 +2127,7 @@ profile = "deleted-profile"
                     },
                 ],
             }],
+            ..Default::default()
         };
 
         // Serialize to TOML
@@ -1861,7 +2224,7 @@ profile = "deleted-profile"
             }],
             ..model::BookmarkGroup::default()
         };
-        assert!(validate_groups(&vec![g1, g2], &[]).is_ok());
+        assert!(validate_groups(&[g1, g2], &[]).is_ok());
     }
 
     #[test]
@@ -1881,7 +2244,7 @@ profile = "deleted-profile"
             ],
             ..model::BookmarkGroup::default()
         };
-        let err = validate_groups(&vec![g], &[]).unwrap_err();
+        let err = validate_groups(&[g], &[]).unwrap_err();
         assert!(err.to_string().contains("Duplicate session name"));
     }
 
@@ -1894,7 +2257,7 @@ profile = "deleted-profile"
             on_connect: Some("echo hello\x1b[31mred".into()),
             ..model::BookmarkGroup::default()
         };
-        let err = validate_groups(&vec![g1], &[]).unwrap_err();
+        let err = validate_groups(&[g1], &[]).unwrap_err();
         assert!(err.to_string().contains("escape sequences"));
 
         // Session-level escape sequence
@@ -1908,7 +2271,7 @@ profile = "deleted-profile"
             }],
             ..model::BookmarkGroup::default()
         };
-        let err = validate_groups(&vec![g2], &[]).unwrap_err();
+        let err = validate_groups(&[g2], &[]).unwrap_err();
         assert!(err.to_string().contains("escape sequences"));
     }
 }
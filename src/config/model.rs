@@ -1,9 +1,12 @@
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 
 use anyhow::{Result, anyhow, bail};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::config::migrate::CURRENT_SCHEMA_VERSION;
+
 /// A named command shortcut for a bookmark.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Snippet {
@@ -19,6 +22,33 @@ pub struct Snippet {
     pub auto_execute: bool,
 }
 
+/// A named set of port forwards saved with a bookmark or profile.
+///
+/// Started as a persistent tunnel by `sshore tunnel up` and stopped by
+/// `sshore tunnel down`. Forward specs use the same syntax as the
+/// `tunnel start` flags.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedTunnel {
+    /// Tunnel name, unique per bookmark (e.g. "db", "metrics").
+    pub name: String,
+
+    /// Local forwards, as for `-L` (e.g. "5432:localhost:5432").
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub local: Vec<String>,
+
+    /// Remote forwards, as for `-R`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remote: Vec<String>,
+
+    /// Dynamic SOCKS forwards, as for `-D` (e.g. "1080").
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dynamic: Vec<String>,
+
+    /// Start this tunnel on a bare `sshore tunnel up`.
+    #[serde(default)]
+    pub autostart: bool,
+}
+
 /// Characters forbidden in hostnames to prevent shell injection.
 const SHELL_METACHARACTERS: &[char] = &[
     ';', '|', '&', '$', '`', '(', ')', '{', '}', '<', '>', '\n', '\r',
@@ -62,6 +92,19 @@ pub struct Profile {
     /// Connection timeout in seconds.
     #[serde(default)]
     pub connect_timeout_secs: Option<u64>,
+
+    /// Offer keys held by a running ssh-agent before on-disk keys.
+    /// Unset means enabled; set to false to opt out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_agent: Option<bool>,
+
+    /// Path to an OpenSSH user certificate (supports ~ expansion).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate_file: Option<String>,
+
+    /// Saved tunnels inherited by every bookmark using this profile.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tunnels: Vec<SavedTunnel>,
 }
 
 /// A group of SSH sessions sharing the same server connection.
@@ -326,8 +369,12 @@ impl Session {
 }
 
 /// Top-level application configuration.
-#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AppConfig {
+    /// Config schema version. Missing in files written before versioning
+    /// (treated as 0); see `config::migrate`.
+    #[serde(default)]
+    pub schema_version: u32,
     #[serde(default)]
     pub settings: Settings,
     #[serde(default)]
@@ -336,6 +383,69 @@ pub struct AppConfig {
     pub bookmarks: Vec<Bookmark>,
     #[serde(default)]
     pub groups: Vec<BookmarkGroup>,
+    /// Entries merged in from read-only `settings.team_configs` sources.
+    /// Never serialized — see `config::team`.
+    #[serde(skip)]
+    pub team: TeamLayer,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            settings: Settings::default(),
+            profiles: Vec::new(),
+            bookmarks: Vec::new(),
+            groups: Vec::new(),
+            team: TeamLayer::default(),
+        }
+    }
+}
+
+/// Entries contributed by team config sources, as they were loaded.
+///
+/// An entry in `AppConfig` that still matches its team original is team-owned
+/// and is left out when the personal config is saved. Once edited it no longer
+/// matches and is saved as a personal override.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TeamLayer {
+    pub profiles: Vec<Profile>,
+    pub bookmarks: Vec<Bookmark>,
+    pub groups: Vec<BookmarkGroup>,
+    /// Every listed team source, resolved and canonicalized at load time
+    /// (including ones that were missing or invalid). Writes to these are refused.
+    pub sources: Vec<PathBuf>,
+}
+
+impl TeamLayer {
+    /// Whether no team entries were merged.
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty() && self.bookmarks.is_empty() && self.groups.is_empty()
+    }
+
+    /// Whether `bookmark` is an unmodified team entry. Connection stats are
+    /// ignored so connecting to a team bookmark doesn't turn it into an override;
+    /// those stats are therefore not saved and start over on every load.
+    pub fn owns_bookmark(&self, bookmark: &Bookmark) -> bool {
+        self.bookmarks.iter().any(|t| {
+            t.name == bookmark.name
+                && Bookmark {
+                    last_connected: t.last_connected,
+                    connect_count: t.connect_count,
+                    ..bookmark.clone()
+                } == *t
+        })
+    }
+
+    /// Whether `group` is an unmodified team entry.
+    pub fn owns_group(&self, group: &BookmarkGroup) -> bool {
+        self.groups.iter().any(|t| t == group)
+    }
+
+    /// Whether `profile` is an unmodified team entry.
+    pub fn owns_profile(&self, profile: &Profile) -> bool {
+        self.profiles.iter().any(|t| t == profile)
+    }
 }
 
 /// Global application settings.
@@ -402,6 +512,19 @@ pub struct Settings {
     /// Set higher for slow networks, lower for fast local connections.
     pub connect_timeout_secs: Option<u64>,
 
+    /// Regex patterns for destructive commands. Multi-host `sshore exec`
+    /// asks for an extra confirmation when the command matches one.
+    /// Set to `[]` to disable.
+    #[serde(default = "default_exec_denylist")]
+    pub exec_denylist: Vec<String>,
+
+    /// Read-only team config files (e.g. a TOML file in a git checkout) whose
+    /// profiles, bookmarks and groups are merged beneath this config. Entries
+    /// defined here take precedence. Relative paths are resolved against the
+    /// directory of this config file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub team_configs: Vec<String>,
+
     /// Whether the first-run import wizard has been dismissed.
     /// Set to true after the user skips or completes the wizard.
     #[serde(default)]
@@ -489,6 +612,20 @@ pub struct Bookmark {
     /// References a `Profile.name` in `AppConfig.profiles`.
     #[serde(default)]
     pub profile: Option<String>,
+
+    /// Offer keys held by a running ssh-agent (via SSH_AUTH_SOCK) before on-disk keys.
+    /// Falls back to the profile, then enabled. Set to false to opt out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_agent: Option<bool>,
+
+    /// Path to an OpenSSH user certificate (`*-cert.pub`) for the identity key.
+    /// Falls back to the profile. Without it, `<key>-cert.pub` is used when present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate_file: Option<String>,
+
+    /// Saved tunnels (`[[bookmarks.tunnels]]`), started with `sshore tunnel up`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tunnels: Vec<SavedTunnel>,
 }
 
 impl Default for Settings {
@@ -507,6 +644,8 @@ impl Default for Settings {
             snippets: Vec::new(),
             host_key_checking: default_host_key_checking(),
             connect_timeout_secs: None,
+            exec_denylist: default_exec_denylist(),
+            team_configs: Vec::new(),
             import_wizard_dismissed: false,
         }
     }
@@ -586,10 +725,24 @@ impl Bookmark {
             .or(settings.connect_timeout_secs)
     }
 
+    /// Resolve whether ssh-agent authentication is enabled: bookmark -> profile -> true.
+    pub fn effective_use_agent(&self, profiles: &[Profile]) -> bool {
+        self.profile_field(profiles, |p| p.use_agent, &self.use_agent)
+            .unwrap_or(true)
+    }
+
+    /// Resolve the effective certificate file path: bookmark -> profile.
+    pub fn effective_certificate_file(&self, profiles: &[Profile]) -> Option<String> {
+        self.profile_field(
+            profiles,
+            |p| p.certificate_file.clone(),
+            &self.certificate_file,
+        )
+    }
+
     /// Resolve effective SSH options by merging profile and bookmark options.
     /// Merge semantics: start with profile's options, overlay bookmark's options.
     /// Bookmark keys win on collision.
-    #[cfg_attr(not(test), allow(dead_code))]
     pub fn effective_ssh_options(&self, profiles: &[Profile]) -> BTreeMap<String, String> {
         let profile = self.resolve_profile(profiles);
         let mut merged = profile.map(|p| p.ssh_options.clone()).unwrap_or_default();
@@ -597,6 +750,23 @@ impl Bookmark {
         merged
     }
 
+    /// Resolve saved tunnels by merging profile and bookmark tunnels.
+    /// Profile tunnels come first; a bookmark tunnel replaces a profile tunnel
+    /// with the same name.
+    pub fn effective_tunnels(&self, profiles: &[Profile]) -> Vec<SavedTunnel> {
+        let mut merged: Vec<SavedTunnel> = self
+            .resolve_profile(profiles)
+            .map(|p| p.tunnels.clone())
+            .unwrap_or_default();
+        for tunnel in &self.tunnels {
+            match merged.iter_mut().find(|t| t.name == tunnel.name) {
+                Some(existing) => *existing = tunnel.clone(),
+                None => merged.push(tunnel.clone()),
+            }
+        }
+        merged
+    }
+
     /// Resolve identity file path with tilde AND environment variable expansion.
     /// Supports: ~/path, $HOME/path, ${SSHKEY}, $VAR/subpath
     /// Returns None if the field is not set.
@@ -621,6 +791,21 @@ impl Bookmark {
                 .map_err(|e| anyhow!("Failed to expand identity file path '{}': {}", p, e))
         })
     }
+
+    /// Resolve the effective certificate file (bookmark -> profile), with tilde
+    /// and environment variable expansion.
+    /// Returns None if neither bookmark nor profile specifies a certificate file.
+    /// Returns Err if env var expansion fails.
+    pub fn resolved_effective_certificate_file(
+        &self,
+        profiles: &[Profile],
+    ) -> Option<Result<String>> {
+        self.effective_certificate_file(profiles).as_ref().map(|p| {
+            shellexpand::full(p)
+                .map(|expanded| expanded.to_string())
+                .map_err(|e| anyhow!("Failed to expand certificate file path '{}': {}", p, e))
+        })
+    }
 }
 
 /// Expand shell variables and tilde in a string.
@@ -740,6 +925,22 @@ fn default_host_key_checking() -> String {
     "strict".to_string()
 }
 
+fn default_exec_denylist() -> Vec<String> {
+    [
+        r"\brm\s+-[a-zA-Z]*[rf]",
+        r"\b(shutdown|reboot|halt|poweroff)\b",
+        r"\bmkfs(\.\w+)?\b",
+        r"\bdd\b.*\bof=",
+        // Not restart: rolling restarts are routine multi-host exec
+        r"\bsystemctl\s+(stop|disable|mask)\b",
+        r"\bkill(all)?\s+-9\b",
+        r"(?i)\b(drop|truncate)\s+(table|database)\b",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
 fn default_env_colors() -> EnvColorMap {
     let mut map = EnvColorMap::new();
     map.insert(
@@ -813,6 +1014,9 @@ mod tests {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         }
     }
 
@@ -829,6 +1033,9 @@ mod tests {
                 m
             },
             connect_timeout_secs: Some(30),
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         }
     }
 
@@ -841,6 +1048,7 @@ mod tests {
             profiles: vec![],
             bookmarks: vec![sample_bookmark()],
             groups: vec![],
+            ..Default::default()
         }
     }
 
@@ -1586,6 +1794,105 @@ mod tests {
         assert_eq!(merged.get("TCPKeepAlive").unwrap(), "yes");
     }
 
+    #[test]
+    fn test_effective_use_agent_defaults_true() {
+        let bookmark = sample_bookmark();
+        assert!(bookmark.effective_use_agent(&[]));
+    }
+
+    #[test]
+    fn test_effective_use_agent_from_profile() {
+        let profiles = vec![Profile {
+            name: "hsm".into(),
+            use_agent: Some(false),
+            ..Profile::default()
+        }];
+        let bookmark = Bookmark {
+            profile: Some("hsm".into()),
+            ..sample_bookmark()
+        };
+        assert!(!bookmark.effective_use_agent(&profiles));
+    }
+
+    #[test]
+    fn test_effective_use_agent_bookmark_overrides_profile() {
+        let profiles = vec![Profile {
+            name: "hsm".into(),
+            use_agent: Some(false),
+            ..Profile::default()
+        }];
+        let bookmark = Bookmark {
+            profile: Some("hsm".into()),
+            use_agent: Some(true),
+            ..sample_bookmark()
+        };
+        assert!(bookmark.effective_use_agent(&profiles));
+    }
+
+    #[test]
+    fn test_effective_tunnels_merge_profile_and_bookmark() {
+        let tunnel = |name: &str, port: &str| SavedTunnel {
+            name: name.into(),
+            local: vec![format!("{port}:localhost:{port}")],
+            remote: vec![],
+            dynamic: vec![],
+            autostart: false,
+        };
+        let profiles = vec![Profile {
+            name: "bastion".into(),
+            tunnels: vec![tunnel("db", "5432"), tunnel("metrics", "9090")],
+            ..Profile::default()
+        }];
+        let toml_str = r#"
+name = "jump"
+host = "jump.example.com"
+profile = "bastion"
+
+[[tunnels]]
+name = "db"
+local = ["6432:localhost:5432"]
+autostart = true
+
+[[tunnels]]
+name = "redis"
+local = ["6379:localhost:6379"]
+"#;
+        let bookmark: Bookmark = toml::from_str(toml_str).unwrap();
+
+        let tunnels = bookmark.effective_tunnels(&profiles);
+        let names: Vec<&str> = tunnels.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["db", "metrics", "redis"]);
+        // Bookmark's "db" replaces the profile's
+        assert_eq!(tunnels[0].local, vec!["6432:localhost:5432"]);
+        assert!(tunnels[0].autostart);
+    }
+
+    #[test]
+    fn test_effective_certificate_file_from_profile() {
+        let profiles = vec![Profile {
+            name: "ca".into(),
+            certificate_file: Some("~/.ssh/id_ed25519-cert.pub".into()),
+            ..Profile::default()
+        }];
+        let bookmark = Bookmark {
+            profile: Some("ca".into()),
+            ..sample_bookmark()
+        };
+        assert_eq!(
+            bookmark.effective_certificate_file(&profiles).as_deref(),
+            Some("~/.ssh/id_ed25519-cert.pub")
+        );
+
+        let own = Bookmark {
+            certificate_file: Some("/etc/ssh/user-cert.pub".into()),
+            ..bookmark
+        };
+        assert_eq!(
+            own.effective_certificate_file(&profiles).as_deref(),
+            Some("/etc/ssh/user-cert.pub")
+        );
+    }
+
     #[test]
     fn test_dangling_profile_connects_gracefully() {
         // AC-7: dangling profile reference — all fields fall through to bookmark/settings
@@ -1761,6 +2068,7 @@ mod tests {
             }],
             bookmarks: vec![sample_bookmark()],
             groups: vec![sample_group()],
+            ..Default::default()
         };
         let toml_str = toml::to_string_pretty(&config).expect("serialize");
         let deserialized: AppConfig = toml::from_str(&toml_str).expect("deserialize");
@@ -5,11 +5,14 @@ use std::path::{Path, PathBuf};
 use anyhow::{Context, Result};
 
 use crate::config::env::detect_env;
-use crate::config::model::{AppConfig, Bookmark};
+use crate::config::model::{AppConfig, Bookmark, BookmarkGroup, Profile, SavedTunnel};
 
 /// Default SSH port.
 const DEFAULT_SSH_PORT: u16 = 22;
 
+/// Name of the saved tunnel holding a host's LocalForward/RemoteForward/DynamicForward.
+pub(crate) const IMPORTED_TUNNEL_NAME: &str = "ssh-config";
+
 /// Result of merging imported bookmarks into existing config.
 #[derive(Debug)]
 pub struct ImportResult {
@@ -20,6 +23,27 @@ pub struct ImportResult {
     pub already_existed: usize,
 }
 
+/// Profiles and groups from a sshore TOML export, imported alongside its bookmarks.
+#[derive(Debug, Default)]
+pub struct ImportedStructure {
+    pub profiles: Vec<Profile>,
+    pub groups: Vec<BookmarkGroup>,
+}
+
+impl ImportedStructure {
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty() && self.groups.is_empty()
+    }
+}
+
+/// Result of merging imported profiles and groups into existing config.
+#[derive(Debug, Default)]
+pub struct StructureImportResult {
+    pub profiles_imported: usize,
+    pub groups_imported: usize,
+    pub already_existed: usize,
+}
+
 /// Intermediate representation while parsing an SSH config Host block.
 #[derive(Default)]
 struct HostBlock {
@@ -28,10 +52,14 @@ struct HostBlock {
     user: Option<String>,
     port: Option<u16>,
     identity_file: Option<String>,
+    certificate_file: Option<String>,
     proxy_jump: Option<String>,
     on_connect: Option<String>,
     connect_timeout_secs: Option<u64>,
     ssh_options: std::collections::BTreeMap<String, String>,
+    local_forwards: Vec<String>,
+    remote_forwards: Vec<String>,
+    dynamic_forwards: Vec<String>,
 }
 
 impl HostBlock {
@@ -41,6 +69,21 @@ impl HostBlock {
         let host = self.hostname.unwrap_or_else(|| name.clone());
         let env = detect_env(&name, &host);
 
+        let tunnels = if self.local_forwards.is_empty()
+            && self.remote_forwards.is_empty()
+            && self.dynamic_forwards.is_empty()
+        {
+            vec![]
+        } else {
+            vec![SavedTunnel {
+                name: IMPORTED_TUNNEL_NAME.to_string(),
+                local: self.local_forwards,
+                remote: self.remote_forwards,
+                dynamic: self.dynamic_forwards,
+                autostart: false,
+            }]
+        };
+
         Some(Bookmark {
             name,
             host,
@@ -59,6 +102,9 @@ impl HostBlock {
             connect_timeout_secs: self.connect_timeout_secs,
             ssh_options: self.ssh_options,
             profile: None,
+            use_agent: None,
+            certificate_file: self.certificate_file,
+            tunnels,
         })
     }
 }
@@ -77,15 +123,13 @@ pub fn import_from_file(path: &Path) -> Result<Vec<Bookmark>> {
 }
 
 /// Detect whether the file content is a sshore TOML export (vs ssh_config).
-fn is_sshore_toml(content: &str) -> bool {
-    content.contains("[[bookmarks]]")
+pub(crate) fn is_sshore_toml(content: &str) -> bool {
+    content.contains("[[bookmarks]]") || content.contains("[[groups]]")
 }
 
 /// Parse a sshore TOML export file into bookmarks.
 fn import_from_toml(content: &str) -> Result<Vec<Bookmark>> {
-    let config: AppConfig =
-        toml::from_str(content).context("Failed to parse sshore TOML export file")?;
-    Ok(config.bookmarks)
+    Ok(super::parse_sshore_toml(content)?.bookmarks)
 }
 
 /// Parse an SSH config file into a list of bookmarks.
@@ -176,6 +220,11 @@ fn parse_ssh_config_recursive(
                     block.identity_file = Some(value.to_string());
                 }
             }
+            "certificatefile" => {
+                if let Some(ref mut block) = current_block {
+                    block.certificate_file = Some(value.to_string());
+                }
+            }
             "proxyjump" => {
                 if let Some(ref mut block) = current_block {
                     block.proxy_jump = Some(value.to_string());
@@ -192,9 +241,22 @@ fn parse_ssh_config_recursive(
                     block.on_connect = Some(value.to_string());
                 }
             }
-            key @ ("localforward"
-            | "remoteforward"
-            | "serveraliveinterval"
+            "localforward" => {
+                if let Some(ref mut block) = current_block {
+                    block.local_forwards.push(normalize_forward(value));
+                }
+            }
+            "remoteforward" => {
+                if let Some(ref mut block) = current_block {
+                    block.remote_forwards.push(normalize_forward(value));
+                }
+            }
+            "dynamicforward" => {
+                if let Some(ref mut block) = current_block {
+                    block.dynamic_forwards.push(value.to_string());
+                }
+            }
+            key @ ("serveraliveinterval"
             | "serveralivecountmax"
             | "addkeystoagent"
             | "forwardagent"
@@ -231,8 +293,6 @@ fn parse_ssh_config_recursive(
 /// Map a lowercased SSH directive to its canonical PascalCase form.
 fn canonical_ssh_option(key: &str) -> &'static str {
     match key {
-        "localforward" => "LocalForward",
-        "remoteforward" => "RemoteForward",
         "serveraliveinterval" => "ServerAliveInterval",
         "serveralivecountmax" => "ServerAliveCountMax",
         "addkeystoagent" => "AddKeysToAgent",
@@ -244,6 +304,15 @@ fn canonical_ssh_option(key: &str) -> &'static str {
     }
 }
 
+/// Convert an ssh_config forward (`[bind_address:]port host:hostport`) to the
+/// `[bind_address:]port:host:hostport` form used by `tunnel start -L/-R`.
+pub(crate) fn normalize_forward(value: &str) -> String {
+    match value.split_once(char::is_whitespace) {
+        Some((listen, target)) => format!("{listen}:{}", target.trim()),
+        None => value.to_string(),
+    }
+}
+
 /// Split an SSH config line into (directive, value).
 fn split_directive(line: &str) -> Option<(&str, &str)> {
     // SSH config supports both "Directive value" and "Directive=value"
@@ -338,6 +407,46 @@ pub fn merge_imports(
     result
 }
 
+/// Merge imported profiles and groups into `config`, matching by name like
+/// `merge_imports`: existing entries are kept unless `overwrite` is set.
+pub fn merge_structure(
+    config: &mut AppConfig,
+    imported: ImportedStructure,
+    overwrite: bool,
+) -> StructureImportResult {
+    let mut result = StructureImportResult::default();
+
+    for profile in imported.profiles {
+        match config.profiles.iter().position(|p| p.name == profile.name) {
+            Some(i) if overwrite => {
+                config.profiles[i] = profile;
+                result.profiles_imported += 1;
+            }
+            Some(_) => result.already_existed += 1,
+            None => {
+                config.profiles.push(profile);
+                result.profiles_imported += 1;
+            }
+        }
+    }
+
+    for group in imported.groups {
+        match config.groups.iter().position(|g| g.name == group.name) {
+            Some(i) if overwrite => {
+                config.groups[i] = group;
+                result.groups_imported += 1;
+            }
+            Some(_) => result.already_existed += 1,
+            None => {
+                config.groups.push(group);
+                result.groups_imported += 1;
+            }
+        }
+    }
+
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -524,6 +633,25 @@ host myhost
         assert_eq!(bookmarks[0].proxy_jump, Some("bastion".into()));
     }
 
+    #[test]
+    fn test_parse_certificate_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_temp_ssh_config(
+            dir.path(),
+            "config",
+            r#"
+Host ca-host
+    HostName 10.0.0.9
+    CertificateFile ~/.ssh/id_ed25519-cert.pub
+"#,
+        );
+        let bookmarks = parse_ssh_config(&path).unwrap();
+        assert_eq!(
+            bookmarks[0].certificate_file,
+            Some("~/.ssh/id_ed25519-cert.pub".into())
+        );
+    }
+
     #[test]
     fn test_parse_proxy_jump() {
         let dir = tempfile::tempdir().unwrap();
@@ -735,6 +863,9 @@ Host relative-host
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         }];
 
         let imported = vec![
@@ -756,6 +887,9 @@ Host relative-host
                 connect_timeout_secs: None,
                 ssh_options: std::collections::BTreeMap::new(),
                 profile: None,
+                use_agent: None,
+                certificate_file: None,
+                tunnels: vec![],
             },
             Bookmark {
                 name: "server-b".into(),
@@ -775,6 +909,9 @@ Host relative-host
                 connect_timeout_secs: None,
                 ssh_options: std::collections::BTreeMap::new(),
                 profile: None,
+                use_agent: None,
+                certificate_file: None,
+                tunnels: vec![],
             },
         ];
 
@@ -806,6 +943,9 @@ Host relative-host
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         }];
 
         let imported = vec![Bookmark {
@@ -826,6 +966,9 @@ Host relative-host
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         }];
 
         let result = merge_imports(&mut existing, imported, true);
@@ -1020,10 +1163,38 @@ Host myhost
         );
         assert_eq!(opts.get("Compression").map(String::as_str), Some("yes"));
         assert_eq!(opts.get("ForwardAgent").map(String::as_str), Some("yes"));
+        // Forwards become a saved tunnel rather than an ssh option
+        assert!(opts.get("LocalForward").is_none());
+        assert_eq!(bookmarks[0].tunnels[0].local, vec!["5432:localhost:5432"]);
+    }
+
+    #[test]
+    fn test_parse_forwards_into_saved_tunnel() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_temp_ssh_config(
+            dir.path(),
+            "config",
+            r#"
+Host bastion
+    HostName bastion.example.com
+    LocalForward 5432 db.internal:5432
+    LocalForward 127.0.0.1:8080 web.internal:80
+    RemoteForward 9000 localhost:9000
+    DynamicForward 1080
+"#,
+        );
+
+        let bookmarks = parse_ssh_config(&path).unwrap();
+        assert_eq!(bookmarks[0].tunnels.len(), 1);
+        let tunnel = &bookmarks[0].tunnels[0];
+        assert_eq!(tunnel.name, "ssh-config");
         assert_eq!(
-            opts.get("LocalForward").map(String::as_str),
-            Some("5432:localhost:5432")
+            tunnel.local,
+            vec!["5432:db.internal:5432", "127.0.0.1:8080:web.internal:80"]
         );
+        assert_eq!(tunnel.remote, vec!["9000:localhost:9000"]);
+        assert_eq!(tunnel.dynamic, vec!["1080"]);
+        assert!(!tunnel.autostart);
     }
 
     #[test]
@@ -1066,10 +1237,6 @@ Host myhost
             Some("60")
         );
         assert_eq!(opts.get("Compression").map(String::as_str), Some("yes"));
-        assert_eq!(
-            opts.get("LocalForward").map(String::as_str),
-            Some("8080:localhost:80")
-        );
         // Lowercase keys should NOT exist
         assert!(opts.get("serveraliveinterval").is_none());
         assert!(opts.get("compression").is_none());
@@ -1088,4 +1255,46 @@ Host myhost
         // Invalid port should fall back to default 22
         assert_eq!(bookmarks[0].port, 22);
     }
+
+    #[test]
+    fn test_is_sshore_toml_detects_groups_only_export() {
+        assert!(is_sshore_toml("[[groups]]\nname = \"g\"\nhost = \"h\"\n"));
+        assert!(!is_sshore_toml("Host web\n    HostName 10.0.0.1\n"));
+    }
+
+    #[test]
+    fn test_merge_structure_respects_overwrite() {
+        let mut config = AppConfig {
+            profiles: vec![Profile {
+                name: "ops".into(),
+                user: Some("old".into()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let imported = || ImportedStructure {
+            profiles: vec![Profile {
+                name: "ops".into(),
+                user: Some("new".into()),
+                ..Default::default()
+            }],
+            groups: vec![BookmarkGroup {
+                name: "app".into(),
+                host: "10.0.0.1".into(),
+                ..Default::default()
+            }],
+        };
+
+        let result = merge_structure(&mut config, imported(), false);
+        assert_eq!(result.groups_imported, 1);
+        assert_eq!(result.profiles_imported, 0);
+        assert_eq!(result.already_existed, 1);
+        assert_eq!(config.profiles[0].user.as_deref(), Some("old"));
+
+        let result = merge_structure(&mut config, imported(), true);
+        assert_eq!(result.profiles_imported, 1);
+        assert_eq!(result.groups_imported, 1);
+        assert_eq!(config.profiles[0].user.as_deref(), Some("new"));
+        assert_eq!(config.groups.len(), 1);
+    }
 }
@@ -0,0 +1,311 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::config::migrate;
+use crate::config::model::{AppConfig, Settings};
+
+/// Resolve `settings.team_configs` to file paths. `~` is expanded and relative
+/// paths are taken from the directory containing `personal_path`.
+pub fn team_config_paths(settings: &Settings, personal_path: &Path) -> Vec<PathBuf> {
+    let base = personal_path.parent().unwrap_or(Path::new("."));
+    settings
+        .team_configs
+        .iter()
+        .map(|p| {
+            let expanded = PathBuf::from(shellexpand::tilde(p).to_string());
+            if expanded.is_relative() {
+                base.join(expanded)
+            } else {
+                expanded
+            }
+        })
+        .collect()
+}
+
+/// The team source `path` refers to, if it is one of the team configs that
+/// were resolved when `config` was loaded.
+pub fn team_source_for(config: &AppConfig, path: &Path) -> Option<PathBuf> {
+    let target = canonical(path);
+    config
+        .team
+        .sources
+        .iter()
+        .find(|team| **team == target)
+        .cloned()
+}
+
+/// Merge every team source listed in the settings beneath `config`.
+///
+/// Profiles, bookmarks and groups are appended unless an entry with the same
+/// name is already defined — personal entries win, and an earlier team source
+/// wins over a later one. Settings in team files are ignored. A team source
+/// that is missing or invalid is skipped with a warning so a stale checkout
+/// never blocks startup.
+pub fn merge_team_layers(config: &mut AppConfig, personal_path: &Path) {
+    for path in team_config_paths(&config.settings, personal_path) {
+        let source = canonical(&path);
+        if source == canonical(personal_path) {
+            eprintln!(
+                "Warning: team config {} is the personal config itself; skipping",
+                path.display()
+            );
+            continue;
+        }
+        config.team.sources.push(source);
+        match read_team_file(&path, config) {
+            Ok(team) => merge_layer(config, team),
+            Err(e) => eprintln!("Warning: skipping team config {}: {e:#}", path.display()),
+        }
+    }
+}
+
+/// The personal layer of `config`: everything except unmodified team entries.
+/// This is what gets written back to the personal config file.
+pub fn personal_layer(config: &AppConfig) -> Cow<'_, AppConfig> {
+    if config.team.is_empty() {
+        return Cow::Borrowed(config);
+    }
+    let team = &config.team;
+    Cow::Owned(AppConfig {
+        schema_version: config.schema_version,
+        settings: config.settings.clone(),
+        profiles: config
+            .profiles
+            .iter()
+            .filter(|p| !team.owns_profile(p))
+            .cloned()
+            .collect(),
+        bookmarks: config
+            .bookmarks
+            .iter()
+            .filter(|b| !team.owns_bookmark(b))
+            .cloned()
+            .collect(),
+        groups: config
+            .groups
+            .iter()
+            .filter(|g| !team.owns_group(g))
+            .cloned()
+            .collect(),
+        team: Default::default(),
+    })
+}
+
+/// Read and validate one team source. Never creates or modifies the file.
+fn read_team_file(path: &Path, personal: &AppConfig) -> Result<AppConfig> {
+    let content = fs::read_to_string(path).context("Failed to read file")?;
+    let (team, _) = migrate::parse_and_migrate(&content).context("Failed to parse TOML")?;
+
+    let all_profiles: Vec<_> = personal
+        .profiles
+        .iter()
+        .chain(&team.profiles)
+        .cloned()
+        .collect();
+    super::validate_profiles(&team.profiles)?;
+    super::validate_on_connect_fields(&team.profiles, &team.bookmarks)?;
+    super::validate_groups(&team.groups, &all_profiles)?;
+    super::validate_bookmarks(&team.bookmarks);
+
+    Ok(team)
+}
+
+/// Append the entries of `team` that `config` doesn't define yet.
+fn merge_layer(config: &mut AppConfig, team: AppConfig) {
+    let profile_names: HashSet<String> = config.profiles.iter().map(|p| p.name.clone()).collect();
+    let bookmark_names: HashSet<String> = config.bookmarks.iter().map(|b| b.name.clone()).collect();
+    let group_names: HashSet<String> = config.groups.iter().map(|g| g.name.clone()).collect();
+
+    for profile in team.profiles {
+        if !profile_names.contains(&profile.name) {
+            config.team.profiles.push(profile.clone());
+            config.profiles.push(profile);
+        }
+    }
+    for bookmark in team.bookmarks {
+        if !bookmark_names.contains(&bookmark.name) {
+            config.team.bookmarks.push(bookmark.clone());
+            config.bookmarks.push(bookmark);
+        }
+    }
+    for group in team.groups {
+        if !group_names.contains(&group.name) {
+            config.team.groups.push(group.clone());
+            config.groups.push(group);
+        }
+    }
+}
+
+/// Canonicalize when possible so different spellings of one file compare equal.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{load_from, save_to};
+
+    const TEAM: &str = r#"
+[settings]
+default_user = "team-user"
+
+[[profiles]]
+name = "corp"
+user = "ops"
+
+[[bookmarks]]
+name = "shared-db"
+host = "10.0.0.5"
+env = "production"
+profile = "corp"
+
+[[bookmarks]]
+name = "web"
+host = "10.0.0.9"
+
+[[groups]]
+name = "cluster"
+host = "10.0.1.1"
+
+[[groups.sessions]]
+name = "shell"
+"#;
+
+    const PERSONAL: &str = r#"
+[settings]
+team_configs = ["team.toml"]
+
+[[bookmarks]]
+name = "web"
+host = "192.168.1.9"
+"#;
+
+    /// Write a personal config pointing at a team file in the same directory.
+    fn setup() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let team = dir.path().join("team.toml");
+        let personal = dir.path().join("config.toml");
+        fs::write(&team, TEAM).unwrap();
+        fs::write(&personal, PERSONAL).unwrap();
+        (dir, personal, team)
+    }
+
+    #[test]
+    fn test_team_entries_merged_beneath_personal() {
+        let (_dir, personal, _team) = setup();
+        let config = load_from(&personal).unwrap();
+
+        let names: Vec<&str> = config.bookmarks.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["web", "shared-db"]);
+        // Personal "web" wins over the team's
+        assert_eq!(config.bookmarks[0].host, "192.168.1.9");
+        assert!(!config.team.owns_bookmark(&config.bookmarks[0]));
+        assert!(config.team.owns_bookmark(&config.bookmarks[1]));
+        assert_eq!(config.profiles[0].name, "corp");
+        assert_eq!(config.groups[0].name, "cluster");
+        // Team settings are ignored
+        assert_eq!(config.settings.default_user, None);
+    }
+
+    #[test]
+    fn test_save_keeps_team_entries_out_of_personal_file() {
+        let (_dir, personal, team) = setup();
+        let mut config = load_from(&personal).unwrap();
+        config.bookmarks[1].connect_count += 1;
+        save_to(&config, &personal).unwrap();
+
+        let content = fs::read_to_string(&personal).unwrap();
+        assert!(!content.contains("shared-db"));
+        assert!(!content.contains("cluster"));
+        assert!(!content.contains("corp"));
+        assert_eq!(fs::read_to_string(&team).unwrap(), TEAM);
+
+        // Reloading still shows the team entries
+        let reloaded = load_from(&personal).unwrap();
+        assert_eq!(reloaded.bookmarks.len(), 2);
+    }
+
+    #[test]
+    fn test_edited_team_entry_becomes_personal_override() {
+        let (_dir, personal, team) = setup();
+        let mut config = load_from(&personal).unwrap();
+        config.bookmarks[1].port = 2222;
+        save_to(&config, &personal).unwrap();
+
+        let saved: AppConfig = toml::from_str(&fs::read_to_string(&personal).unwrap()).unwrap();
+        assert_eq!(saved.bookmarks.len(), 2);
+        assert_eq!(saved.bookmarks[1].name, "shared-db");
+        assert_eq!(saved.bookmarks[1].port, 2222);
+        assert_eq!(fs::read_to_string(&team).unwrap(), TEAM);
+
+        let reloaded = load_from(&personal).unwrap();
+        assert_eq!(reloaded.bookmarks.len(), 2);
+        assert!(!reloaded.team.owns_bookmark(&reloaded.bookmarks[1]));
+    }
+
+    #[test]
+    fn test_atomic_write_refuses_team_file() {
+        let (_dir, personal, team) = setup();
+        let config = load_from(&personal).unwrap();
+        let err = save_to(&config, &team).unwrap_err();
+        assert!(err.to_string().contains("read-only team config"));
+        assert_eq!(fs::read_to_string(&team).unwrap(), TEAM);
+    }
+
+    #[test]
+    fn test_atomic_write_refuses_team_file_in_subdirectory() {
+        let dir = tempfile::tempdir().unwrap();
+        let personal = dir.path().join("config.toml");
+        let team = dir.path().join("sub").join("team.toml");
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(&team, TEAM).unwrap();
+        fs::write(
+            &personal,
+            "[settings]\nteam_configs = [\"sub/team.toml\"]\n",
+        )
+        .unwrap();
+
+        let config = load_from(&personal).unwrap();
+        assert!(config.team.owns_bookmark(&config.bookmarks[0]));
+        let err = save_to(&config, &team).unwrap_err();
+        assert!(err.to_string().contains("read-only team config"));
+        assert_eq!(fs::read_to_string(&team).unwrap(), TEAM);
+    }
+
+    #[test]
+    fn test_missing_or_invalid_team_source_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let personal = dir.path().join("config.toml");
+        fs::write(dir.path().join("broken.toml"), "[[bookmarks]\n").unwrap();
+        fs::write(
+            &personal,
+            "[settings]\nteam_configs = [\"missing.toml\", \"broken.toml\"]\n",
+        )
+        .unwrap();
+
+        let config = load_from(&personal).unwrap();
+        assert!(config.bookmarks.is_empty());
+        assert!(config.team.is_empty());
+    }
+
+    #[test]
+    fn test_team_config_paths_resolve_relative_to_personal_config() {
+        let settings = Settings {
+            team_configs: vec!["team.toml".into(), "/srv/team/hosts.toml".into()],
+            ..Settings::default()
+        };
+        let paths = team_config_paths(&settings, Path::new("/home/me/.config/sshore/config.toml"));
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/home/me/.config/sshore/team.toml"),
+                PathBuf::from("/srv/team/hosts.toml"),
+            ]
+        );
+    }
+}
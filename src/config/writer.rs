@@ -1,10 +1,11 @@
 use std::fs;
 use std::path::Path;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use tempfile::NamedTempFile;
 
 use crate::config::model::AppConfig;
+use crate::config::{migrate, team};
 
 /// Atomically write config to disk using tempfile-then-rename.
 ///
@@ -13,8 +14,27 @@ use crate::config::model::AppConfig;
 /// 3. Write to a temp file in the same directory (ensures same filesystem)
 /// 4. Set permissions to 0600 on Unix
 /// 5. Atomic rename into place
+///
+/// Only the personal layer is written: unmodified entries from team config
+/// sources are left out, and writing to a team source itself is refused.
 pub fn atomic_write(config: &AppConfig, path: &Path) -> Result<()> {
-    let toml_str = toml::to_string_pretty(config).context("Failed to serialize config to TOML")?;
+    if migrate::is_newer_schema(config) {
+        bail!(
+            "Refusing to save config: it was written by a newer sshore (schema v{}, this build \
+             supports v{}) and saving would drop fields this version doesn't know. Upgrade sshore.",
+            config.schema_version,
+            migrate::CURRENT_SCHEMA_VERSION
+        );
+    }
+    if let Some(team_path) = team::team_source_for(config, path) {
+        bail!(
+            "Refusing to write to {}: it is a read-only team config source",
+            team_path.display()
+        );
+    }
+
+    let toml_str = toml::to_string_pretty(team::personal_layer(config).as_ref())
+        .context("Failed to serialize config to TOML")?;
 
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
@@ -111,6 +131,9 @@ mod tests {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         });
 
         atomic_write(&config, &path).unwrap();
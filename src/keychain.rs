@@ -117,6 +117,9 @@ mod tests {
                 connect_timeout_secs: None,
                 ssh_options: std::collections::BTreeMap::new(),
                 profile: None,
+                use_agent: None,
+                certificate_file: None,
+                tunnels: vec![],
             },
             Bookmark {
                 name: unstored_name.into(),
@@ -136,6 +139,9 @@ mod tests {
                 connect_timeout_secs: None,
                 ssh_options: std::collections::BTreeMap::new(),
                 profile: None,
+                use_agent: None,
+                certificate_file: None,
+                tunnels: vec![],
             },
         ];
 
@@ -21,7 +21,7 @@ pub static malloc_conf: &[u8] = b"dirty_decay_ms:0,muzzy_decay_ms:0,retain:false
 
 mod cli;
 
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result, bail};
@@ -29,10 +29,16 @@ use clap::{CommandFactory, Parser};
 use sshore::{config, keychain, sftp, ssh, storage, tui};
 use zeroize::Zeroizing;
 
-use cli::{Cli, Commands, ImportSource, PasswordAction, TunnelAction};
+use cli::{
+    Cli, Commands, ExecOutputFormat, ImportSource, ListFormat, PasswordAction, TunnelAction,
+};
 use config::ImportSourceKind;
+use config::bundle;
 use config::model::Bookmark;
-use config::ssh_import::merge_imports;
+use config::ssh_import::{ImportedStructure, merge_imports, merge_structure};
+use ssh::exec_guard;
+use ssh::exec_report::{self, ErrorMode, ExecOutput};
+use ssh::rollout::{HostCount, RolloutPlan};
 
 /// Terminate a process by PID. Returns `true` if the signal was sent successfully.
 #[cfg(unix)]
@@ -64,17 +70,12 @@ fn default_ssh_config_path() -> PathBuf {
         .join("config")
 }
 
-/// Format a bookmark row for the list table.
-fn format_bookmark_row(
-    b: &Bookmark,
-    settings: &config::model::Settings,
-    profiles: &[config::model::Profile],
-) -> String {
-    let user = b.effective_user(settings, profiles);
-    let env_display = if b.env.is_empty() { "-" } else { &b.env };
+/// Format a bookmark or group session row for the list table.
+fn format_inventory_row(e: &config::inventory::InventoryEntry) -> String {
+    let env_display = if e.env.is_empty() { "-" } else { &e.env };
     format!(
         "  {:<20} {:<30} {:<12} {:<6} {}",
-        b.name, b.host, user, b.port, env_display
+        e.name, e.host, e.user, e.port, env_display
     )
 }
 
@@ -158,11 +159,28 @@ async fn main() -> Result<()> {
             env,
             tag,
             dry_run,
+            identity,
+            signer,
         }) => {
-            cmd_import(from, file, overwrite, env, tag, dry_run, cfg_override)?;
+            cmd_import(
+                from,
+                file,
+                overwrite,
+                env,
+                tag,
+                dry_run,
+                identity,
+                signer,
+                cfg_override,
+            )?;
         }
-        Some(Commands::List { env, format: _ }) => {
-            cmd_list(env, cfg_override)?;
+        Some(Commands::List {
+            env,
+            tag,
+            name,
+            format,
+        }) => {
+            cmd_list(env, tag, name, format, cfg_override)?;
         }
         Some(Commands::Completions { shell }) => {
             cmd_completions(shell);
@@ -180,8 +198,27 @@ async fn main() -> Result<()> {
             source,
             destination,
             resume,
+            preserve,
         }) => {
-            cmd_scp(&source, &destination, resume, cfg_override).await?;
+            cmd_scp(&source, &destination, resume, preserve, cfg_override).await?;
+        }
+        Some(Commands::Sync {
+            source,
+            destination,
+            dry_run,
+            checksum,
+            delete,
+            include,
+            exclude,
+            yes,
+        }) => {
+            let options = storage::sync::SyncOptions {
+                checksum,
+                delete,
+                include,
+                exclude,
+            };
+            cmd_sync(&source, &destination, &options, dry_run, yes, cfg_override).await?;
         }
         Some(Commands::Browse {
             target,
@@ -198,9 +235,64 @@ async fn main() -> Result<()> {
             command,
             tag,
             env,
+            script,
+            interpreter,
+            stdin,
+            no_stdin,
             concurrency,
+            output,
+            stream,
+            fail_fast,
+            ignore_errors,
+            yes,
+            canary,
+            batch_size,
+            pause,
+            max_failures,
         }) => {
-            cmd_exec(bookmark, command, tag, env, concurrency, cfg_override).await?;
+            let error_mode = if fail_fast {
+                ErrorMode::FailFast
+            } else if ignore_errors {
+                ErrorMode::IgnoreErrors
+            } else {
+                ErrorMode::Continue
+            };
+            // A script is piped over stdin; the trailing arguments become its arguments
+            let (command, input) = match script {
+                Some(path) => {
+                    let body = ssh::script::read_script(Path::new(&path))?;
+                    let command = ssh::script::remote_script_command(&interpreter, &command);
+                    (vec![command], Some(body.into()))
+                }
+                None => (command, None),
+            };
+            let options = ssh::MultiExecOptions {
+                concurrency,
+                output: match output {
+                    ExecOutputFormat::Text => ExecOutput::Text,
+                    ExecOutputFormat::Json => ExecOutput::Json,
+                    ExecOutputFormat::Jsonl => ExecOutput::Jsonl,
+                },
+                error_mode,
+                rollout: rollout_plan(canary, batch_size.as_deref(), pause, &max_failures)?,
+                stream,
+                input,
+            };
+            if stream && options.output != ExecOutput::Text {
+                bail!("--stream cannot be combined with --output json or jsonl");
+            }
+            cmd_exec(
+                bookmark,
+                command,
+                tag,
+                env,
+                options,
+                stdin,
+                no_stdin,
+                yes,
+                cfg_override,
+            )
+            .await?;
         }
         Some(Commands::Export {
             env,
@@ -208,8 +300,21 @@ async fn main() -> Result<()> {
             name,
             output,
             include_settings,
+            encrypt,
+            recipients,
+            sign,
         }) => {
-            cmd_export(env, tag, name, output, include_settings, cfg_override)?;
+            cmd_export(
+                env,
+                tag,
+                name,
+                output,
+                include_settings,
+                encrypt,
+                recipients,
+                sign,
+                cfg_override,
+            )?;
         }
         Some(Commands::ResetTab) => {
             ssh::terminal_theme::reset_theme();
@@ -240,6 +345,7 @@ fn check_bookmark_subcommand_collision(cmd: &Commands, cfg_override: Option<&str
         Commands::Connect { .. } => return, // `connect` is the intended escape hatch
         Commands::Sftp { .. } => "sftp",
         Commands::Scp { .. } => "scp",
+        Commands::Sync { .. } => "sync",
         Commands::Browse { .. } => "browse",
         Commands::Tunnel { .. } => "tunnel",
         Commands::Exec { .. } => "exec",
@@ -264,6 +370,7 @@ fn check_bookmark_subcommand_collision(cmd: &Commands, cfg_override: Option<&str
 }
 
 /// Import bookmarks from various sources.
+#[allow(clippy::too_many_arguments)]
 fn cmd_import(
     from: Option<ImportSource>,
     file: Option<String>,
@@ -271,6 +378,8 @@ fn cmd_import(
     env_override: Option<String>,
     extra_tags: Vec<String>,
     dry_run: bool,
+    identity: Vec<String>,
+    signer: Option<String>,
     cfg_override: Option<&str>,
 ) -> Result<()> {
     // Validate env_override against known tiers (warn only)
@@ -295,6 +404,7 @@ fn cmd_import(
         };
         return finish_import(
             result.bookmarks,
+            ImportedStructure::default(),
             &result.source_label,
             &result.file_path,
             overwrite,
@@ -354,19 +464,58 @@ fn cmd_import(
         source_kind,
         ImportSourceKind::Auto | ImportSourceKind::SshConfig | ImportSourceKind::Sshore
     );
-    let mut imported = config::import_from_source(
-        &import_path,
+
+    // Encrypted or signed sshore exports are opened (and verified) first
+    let opened = if matches!(
         source_kind,
-        env_override.as_deref(),
-        &extra_tags,
-    )
-    .with_context(|| {
-        format!(
-            "Failed to parse {} from {}",
-            source_label,
-            import_path.display()
+        ImportSourceKind::Auto | ImportSourceKind::Sshore
+    ) {
+        let data = std::fs::read(&import_path)
+            .with_context(|| format!("Failed to read import file: {}", import_path.display()))?;
+        if bundle::is_encrypted(&data) || bundle::is_signed(&data) || signer.is_some() {
+            Some(open_export_bundle(&data, &identity, signer.as_deref())?)
+        } else {
+            None
+        }
+    } else {
+        if !identity.is_empty() || signer.is_some() {
+            bail!("--identity and --signer only apply to sshore exports");
+        }
+        None
+    };
+
+    let (mut imported, mut structure) = if let Some(opened) = opened {
+        config::parse_sshore_export(&opened.content).with_context(|| {
+            format!(
+                "Failed to parse {} from {}",
+                source_label,
+                import_path.display()
+            )
+        })?
+    } else {
+        let structure =
+            config::import_structure(&import_path, &source_kind).with_context(|| {
+                format!(
+                    "Failed to parse {} from {}",
+                    source_label,
+                    import_path.display()
+                )
+            })?;
+        let imported = config::import_from_source(
+            &import_path,
+            source_kind,
+            env_override.as_deref(),
+            &extra_tags,
         )
-    })?;
+        .with_context(|| {
+            format!(
+                "Failed to parse {} from {}",
+                source_label,
+                import_path.display()
+            )
+        })?;
+        (imported, structure)
+    };
 
     // Apply environment override for sources that don't handle it internally
     // (ssh_config, auto, sshore TOML don't pass env_override to the parser)
@@ -376,14 +525,21 @@ fn cmd_import(
         for bookmark in &mut imported {
             bookmark.env = env.clone();
         }
+        for group in &mut structure.groups {
+            group.env = env.clone();
+        }
     }
 
     // Apply extra tags for sources that don't handle them internally
     if !extra_tags.is_empty() && is_passthrough {
-        for bookmark in &mut imported {
+        for tags in imported
+            .iter_mut()
+            .map(|b| &mut b.tags)
+            .chain(structure.groups.iter_mut().map(|g| &mut g.tags))
+        {
             for tag in &extra_tags {
-                if !bookmark.tags.contains(tag) {
-                    bookmark.tags.push(tag.clone());
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
                 }
             }
         }
@@ -391,6 +547,7 @@ fn cmd_import(
 
     finish_import(
         imported,
+        structure,
         source_label,
         &import_path,
         overwrite,
@@ -402,6 +559,7 @@ fn cmd_import(
 /// Complete an import: dry-run output or merge + save + summary.
 fn finish_import(
     imported: Vec<Bookmark>,
+    structure: ImportedStructure,
     source_label: &str,
     import_path: &Path,
     overwrite: bool,
@@ -447,6 +605,38 @@ fn finish_import(
             }
         }
 
+        let existing_profiles: Vec<&str> = app_config
+            .profiles
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        let existing_groups: Vec<&str> =
+            app_config.groups.iter().map(|g| g.name.as_str()).collect();
+        let entries = structure
+            .profiles
+            .iter()
+            .map(|p| ("profile", p.name.as_str(), &existing_profiles))
+            .chain(
+                structure
+                    .groups
+                    .iter()
+                    .map(|g| ("group", g.name.as_str(), &existing_groups)),
+            );
+        for (kind, name, existing) in entries {
+            if existing.contains(&name) {
+                if overwrite {
+                    println!("  Would overwrite {kind}: {name}");
+                    overwritten += 1;
+                } else {
+                    println!("  Skipping {kind} (already exists): {name}");
+                    skipped += 1;
+                }
+            } else {
+                println!("  Would import {kind}: {name}");
+                added += 1;
+            }
+        }
+
         println!(
             "\n{} would be added, {} would be overwritten, {} skipped",
             added, overwritten, skipped
@@ -455,6 +645,7 @@ fn finish_import(
     }
 
     let result = merge_imports(&mut app_config.bookmarks, imported, overwrite);
+    let structure_result = merge_structure(&mut app_config, structure, overwrite);
 
     config::save_with_override(&app_config, cfg_override).context("Failed to save config")?;
 
@@ -465,11 +656,18 @@ fn finish_import(
         import_path.display()
     );
     println!("  {} bookmarks imported", result.imported.len());
-    if result.already_existed > 0 {
+    if structure_result.groups_imported > 0 {
+        println!("  {} groups imported", structure_result.groups_imported);
+    }
+    if structure_result.profiles_imported > 0 {
+        println!("  {} profiles imported", structure_result.profiles_imported);
+    }
+    let already_existed = result.already_existed + structure_result.already_existed;
+    if already_existed > 0 {
         if overwrite {
-            println!("  {} overwritten", result.already_existed);
+            println!("  {} overwritten", already_existed);
         } else {
-            println!("  {} skipped (already exist)", result.already_existed);
+            println!("  {} skipped (already exist)", already_existed);
         }
     }
     println!("  {} total parsed from source", total_parsed);
@@ -500,22 +698,39 @@ fn finish_import(
 }
 
 /// List bookmarks in a table format.
-fn cmd_list(env_filter: Option<String>, cfg_override: Option<&str>) -> Result<()> {
+fn cmd_list(
+    env_filter: Option<String>,
+    tag_filters: Vec<String>,
+    name_pattern: Option<String>,
+    format: ListFormat,
+    cfg_override: Option<&str>,
+) -> Result<()> {
+    use config::inventory::{self, InventoryFormat};
+
     let app_config = config::load_with_override(cfg_override).context("Failed to load config")?;
+    let entries = inventory::collect(
+        &app_config,
+        env_filter.as_deref(),
+        &tag_filters,
+        name_pattern.as_deref(),
+    );
 
-    let bookmarks: Vec<&Bookmark> = app_config
-        .bookmarks
-        .iter()
-        .filter(|b| {
-            env_filter
-                .as_ref()
-                .is_none_or(|env| b.env.eq_ignore_ascii_case(env))
-        })
-        .collect();
+    let machine_format = match format {
+        ListFormat::Table => None,
+        ListFormat::Json => Some(InventoryFormat::Json),
+        ListFormat::Yaml => Some(InventoryFormat::Yaml),
+        ListFormat::Csv => Some(InventoryFormat::Csv),
+        ListFormat::Tsv => Some(InventoryFormat::Tsv),
+    };
+    if let Some(machine_format) = machine_format {
+        // Machine-readable output is always emitted, even when empty, so scripts can parse it
+        print!("{}", inventory::render(&entries, machine_format)?);
+        return Ok(());
+    }
 
-    if bookmarks.is_empty() {
-        if env_filter.is_some() {
-            println!("No bookmarks matching environment filter.");
+    if entries.is_empty() {
+        if env_filter.is_some() || !tag_filters.is_empty() || name_pattern.is_some() {
+            println!("No bookmarks matching filters.");
         } else {
             println!(
                 "No bookmarks yet. Run `sshore import` to import from SSH config, PuTTY, Tabby, CSV, and more."
@@ -531,14 +746,17 @@ fn cmd_list(env_filter: Option<String>, cfg_override: Option<&str>) -> Result<()
     );
     println!("  {}", "-".repeat(76));
 
-    for b in &bookmarks {
-        println!(
-            "{}",
-            format_bookmark_row(b, &app_config.settings, &app_config.profiles)
-        );
+    for e in &entries {
+        println!("{}", format_inventory_row(e));
     }
 
-    println!("\n  {} bookmark(s)", bookmarks.len());
+    let sessions = entries.iter().filter(|e| e.group.is_some()).count();
+    let bookmarks = entries.len() - sessions;
+    if sessions == 0 {
+        println!("\n  {bookmarks} bookmark(s)");
+    } else {
+        println!("\n  {bookmarks} bookmark(s), {sessions} group session(s)");
+    }
 
     Ok(())
 }
@@ -582,10 +800,116 @@ async fn cmd_scp(
     source: &str,
     destination: &str,
     resume: bool,
+    preserve: bool,
     cfg_override: Option<&str>,
 ) -> Result<()> {
     let config = config::load_with_override(cfg_override).context("Failed to load config")?;
-    sftp::shortcuts::scp_transfer(&config, source, destination, resume).await
+    sftp::shortcuts::scp_transfer(&config, source, destination, resume, preserve).await
+}
+
+/// Sync a directory tree between a local path and a bookmark.
+/// Deletions on a production host need typed confirmation.
+async fn cmd_sync(
+    source: &str,
+    destination: &str,
+    options: &storage::sync::SyncOptions,
+    dry_run: bool,
+    yes: bool,
+    cfg_override: Option<&str>,
+) -> Result<()> {
+    let config = config::load_with_override(cfg_override).context("Failed to load config")?;
+
+    let src_remote = sftp::shortcuts::parse_remote_spec(source);
+    let dst_remote = sftp::shortcuts::parse_remote_spec(destination);
+    let ((bookmark_name, remote_path), local_path) = match (src_remote, dst_remote) {
+        (Some(_), Some(_)) => {
+            bail!("Both source and destination are remote. Only one can be remote.");
+        }
+        (None, None) => {
+            bail!(
+                "Neither source nor destination is remote. Use `bookmark:path` syntax for the remote side."
+            );
+        }
+        (Some(spec), None) => (spec, destination),
+        (None, Some(spec)) => (spec, source),
+    };
+
+    let index = find_bookmark_index(&config, bookmark_name)?;
+    let bookmark = &config.bookmarks[index];
+    ssh::print_production_banner(bookmark, &config.settings, &config.profiles, "sync");
+
+    let remote_fs = storage::sftp_backend::SftpBackend::new(&config, index).await?;
+    let remote_root = match remote_path.trim_end_matches('/') {
+        "" if remote_path.starts_with('/') => "/".to_string(),
+        "" | "." => remote_fs.cwd()?,
+        p if p.starts_with('/') => p.to_string(),
+        p => format!("{}/{p}", remote_fs.cwd()?.trim_end_matches('/')),
+    };
+    let local_root = shellexpand::tilde(local_path).to_string();
+    let local_root = match local_root.trim_end_matches('/') {
+        "" if local_root.starts_with('/') => "/".to_string(),
+        "" => ".".to_string(),
+        p => p.to_string(),
+    };
+    let remote = storage::Backend::Sftp(remote_fs);
+    let local = storage::Backend::Local(storage::local_backend::LocalBackend::new(".")?);
+
+    let (src, src_root, dst, dst_root) = if src_remote.is_some() {
+        (&remote, remote_root, &local, local_root)
+    } else {
+        (&local, local_root, &remote, remote_root)
+    };
+    let plan = storage::sync::plan(src, &src_root, dst, &dst_root, options).await?;
+
+    for line in plan.preview_lines() {
+        println!("{line}");
+    }
+    println!("{}", plan.summary());
+    if dry_run || plan.is_empty() {
+        return Ok(());
+    }
+
+    if dst_remote.is_some() && exec_guard::is_production(&bookmark.env) {
+        let deletions: Vec<String> = plan
+            .items
+            .iter()
+            .filter(|i| matches!(i.op, storage::sync::SyncOp::Delete { .. }))
+            .map(|i| i.relative.clone())
+            .collect();
+        let confirmation = exec_guard::Confirmation {
+            assume_yes: yes,
+            stdin_is_tty: io::stdin().is_terminal(),
+        };
+        let confirmed = exec_guard::confirm_production_delete(
+            &bookmark.name,
+            &deletions,
+            confirmation,
+            exec_guard::prompt_stdin,
+        )?;
+        if !confirmed {
+            eprintln!("Cancelled.");
+            return Ok(());
+        }
+    }
+
+    let progress = std::sync::atomic::AtomicU64::new(0);
+    let files_done = std::sync::atomic::AtomicU64::new(0);
+    let cancel = std::sync::atomic::AtomicBool::new(false);
+    let report = storage::sync::execute(&plan, src, dst, &progress, &files_done, &cancel).await?;
+    println!(
+        "Synced: {} copied ({}), {} dir(s) created, {} deleted",
+        report.copied,
+        sftp::shortcuts::format_bytes(progress.into_inner()),
+        report.created,
+        report.deleted
+    );
+    if !report.errors.is_empty() {
+        for error in &report.errors {
+            eprintln!("Error: {error}");
+        }
+        bail!("{} sync step(s) failed", report.errors.len());
+    }
+    Ok(())
 }
 
 /// Open the dual-pane file browser.
@@ -667,7 +991,7 @@ fn cmd_password_set(bookmark_name: &str, cfg_override: Option<&str>) -> Result<(
         })?;
 
     // Warn for production environments
-    if bookmark.env.eq_ignore_ascii_case("production") {
+    if exec_guard::is_production(&bookmark.env) {
         eprint!("Warning: storing a password for a PRODUCTION bookmark. Continue? [y/N] ");
         io::stderr().flush()?;
         let mut answer = String::new();
@@ -773,12 +1097,16 @@ fn read_password_from_tty(prompt: &str) -> Result<Zeroizing<String>> {
 }
 
 /// Execute a command on one or more bookmarks.
+#[allow(clippy::too_many_arguments)]
 async fn cmd_exec(
     bookmark: Option<String>,
     command: Vec<String>,
     tag: Vec<String>,
     env: Option<String>,
-    concurrency: usize,
+    mut options: ssh::MultiExecOptions,
+    stdin: bool,
+    no_stdin: bool,
+    yes: bool,
     cfg_override: Option<&str>,
 ) -> Result<()> {
     let config = config::load_with_override(cfg_override).context("Failed to load config")?;
@@ -791,7 +1119,16 @@ async fn cmd_exec(
     if let Some(name) = bookmark {
         // Single-host exec
         let index = find_bookmark_index(&config, &name)?;
-        let result = ssh::exec_command(&config, index, &command_str).await?;
+        // Like ssh: a script body takes stdin; otherwise piped stdin is streamed
+        // unless -n asks to leave it alone
+        let input = match options.input.clone() {
+            Some(bytes) => Some(ssh::ExecInput::Bytes(bytes)),
+            None if stdin || (!no_stdin && !io::stdin().is_terminal()) => {
+                Some(ssh::ExecInput::LocalStdin)
+            }
+            None => None,
+        };
+        let result = ssh::exec_command(&config, index, &command_str, input).await?;
         std::process::exit(result.exit_code as i32);
     } else if !tag.is_empty() || env.is_some() {
         // Multi-host exec
@@ -819,12 +1156,40 @@ async fn cmd_exec(
             bail!("No bookmarks match the given filters");
         }
 
+        // Only a --script body is code worth screening; --stdin is data for the command
+        let script = options.input.clone();
+        if stdin {
+            // Buffered once so every host receives the same input
+            options.input = Some(ssh::script::read_fan_out_input(io::stdin().lock())?.into());
+        }
+
+        let confirmation = exec_guard::Confirmation {
+            assume_yes: yes,
+            stdin_is_tty: io::stdin().is_terminal(),
+        };
+        let confirmed = exec_guard::confirm_multi_exec(
+            &config,
+            &matches,
+            &command_str,
+            script.as_deref(),
+            confirmation,
+            exec_guard::prompt_stdin,
+        )?;
+        if !confirmed {
+            eprintln!("Cancelled.");
+            return Ok(());
+        }
+
         eprintln!(
             "Running on {} bookmark(s) (concurrency: {})...",
             matches.len(),
-            concurrency
+            options.concurrency
         );
-        ssh::exec_multi(&config, &matches, &command_str, concurrency).await?;
+        let results = ssh::exec_multi(&config, &matches, &command_str, &options).await?;
+        let code = exec_report::overall_exit_code(&results, options.error_mode);
+        if code != 0 {
+            std::process::exit(code);
+        }
     } else {
         bail!(
             "Specify a bookmark name or use --tag/--env filters.\n\
@@ -836,13 +1201,44 @@ async fn cmd_exec(
     Ok(())
 }
 
+/// Build the rolling-mode plan from `exec` flags; `None` unless `--canary`
+/// or `--batch-size` was given.
+fn rollout_plan(
+    canary: bool,
+    batch_size: Option<&str>,
+    pause: u64,
+    max_failures: &str,
+) -> Result<Option<RolloutPlan>> {
+    if !canary && batch_size.is_none() {
+        if pause != 0 || max_failures != "0" {
+            bail!("--pause and --max-failures require rolling mode (--batch-size or --canary)");
+        }
+        return Ok(None);
+    }
+
+    Ok(Some(RolloutPlan {
+        canary,
+        // --canary alone: canary, then everyone else in one batch
+        batch_size: batch_size
+            .map(HostCount::parse)
+            .transpose()?
+            .unwrap_or(HostCount::Percent(100)),
+        pause: std::time::Duration::from_secs(pause),
+        max_failures: HostCount::parse(max_failures)?,
+    }))
+}
+
 /// Export bookmarks to a portable TOML file.
+#[allow(clippy::too_many_arguments)]
 fn cmd_export(
     env: Option<String>,
     tag: Vec<String>,
     name: Option<String>,
     output: Option<String>,
     include_settings: bool,
+    encrypt: bool,
+    recipients: Vec<String>,
+    sign: Option<String>,
     cfg_override: Option<&str>,
 ) -> Result<()> {
     let app_config = config::load_with_override(cfg_override).context("Failed to load config")?;
@@ -854,17 +1250,81 @@ fn cmd_export(
         include_settings,
     )?;
 
+    let signing_key = sign
+        .as_deref()
+        .map(|path| bundle::load_signing_key(path, read_password_from_tty))
+        .transpose()?;
+    let encryption = if !recipients.is_empty() {
+        bundle::Encryption::Recipients(recipients)
+    } else if encrypt {
+        bundle::Encryption::Passphrase(read_new_passphrase()?)
+    } else {
+        bundle::Encryption::None
+    };
+    let is_encrypted = !matches!(encryption, bundle::Encryption::None);
+    let export = bundle::seal(&toml_output, signing_key.as_ref(), &encryption)?;
+
     if let Some(path) = output {
-        std::fs::write(&path, &toml_output)
+        std::fs::write(&path, &export)
             .with_context(|| format!("Failed to write export to {path}"))?;
-        eprintln!("Exported to {path}");
+        let mut notes = Vec::new();
+        if is_encrypted {
+            notes.push("encrypted");
+        }
+        if signing_key.is_some() {
+            notes.push("signed");
+        }
+        if notes.is_empty() {
+            eprintln!("Exported to {path}");
+        } else {
+            eprintln!("Exported to {path} ({})", notes.join(", "));
+        }
     } else {
-        print!("{toml_output}");
+        print!("{export}");
     }
 
     Ok(())
 }
 
+/// Ask twice for the passphrase protecting an encrypted export.
+fn read_new_passphrase() -> Result<Zeroizing<String>> {
+    let passphrase = read_password_from_tty("Export passphrase: ")?;
+    if passphrase.is_empty() {
+        bail!("Passphrase must not be empty");
+    }
+    let confirm = read_password_from_tty("Confirm passphrase: ")?;
+    if *passphrase != *confirm {
+        bail!("Passphrases do not match");
+    }
+    Ok(passphrase)
+}
+
+/// Decrypt and verify a sshore export bundle, reporting what was checked.
+fn open_export_bundle(
+    data: &[u8],
+    identity: &[String],
+    signer: Option<&str>,
+) -> Result<bundle::OpenedBundle> {
+    let opened = bundle::open(data, identity, read_password_from_tty)?;
+    if opened.encrypted {
+        eprintln!("Decrypted export bundle");
+    }
+    match &opened.signature {
+        bundle::SignatureStatus::Unsigned => eprintln!("Signature: none (export is unsigned)"),
+        bundle::SignatureStatus::Valid { signer } => eprintln!(
+            "Signature: valid, signed by {} key {}",
+            signer.algorithm(),
+            signer.fingerprint(russh::keys::HashAlg::Sha256)
+        ),
+    }
+    if let Some(spec) = signer {
+        let trusted = bundle::parse_public_keys(spec)?;
+        bundle::require_signer(&opened.signature, &trusted)?;
+        eprintln!("Signer matches {spec}");
+    }
+    Ok(opened)
+}
+
 /// Dispatch tunnel subcommands.
 async fn cmd_tunnel(action: TunnelAction, cfg_override: Option<&str>) -> Result<()> {
     match action {
@@ -872,37 +1332,46 @@ async fn cmd_tunnel(action: TunnelAction, cfg_override: Option<&str>) -> Result<
             bookmark,
             local_forward,
             remote_forward,
+            dynamic_forward,
+            name,
             persist,
             daemon,
         } => {
             cmd_tunnel_start(
                 &bookmark,
+                name.as_deref(),
                 &local_forward,
                 &remote_forward,
+                &dynamic_forward,
                 persist,
                 daemon,
                 cfg_override,
             )
             .await
         }
-        TunnelAction::Stop { bookmark } => cmd_tunnel_stop(&bookmark),
+        TunnelAction::Stop { target } => cmd_tunnel_stop(&target),
+        TunnelAction::Up { target } => cmd_tunnel_up(target.as_deref(), cfg_override),
+        TunnelAction::Down { target } => cmd_tunnel_down(target.as_deref(), cfg_override),
         TunnelAction::Status => cmd_tunnel_status(),
     }
 }
 
 /// Start a tunnel to a bookmark.
+#[allow(clippy::too_many_arguments)]
 async fn cmd_tunnel_start(
     bookmark_name: &str,
+    name: Option<&str>,
     local_specs: &[String],
     remote_specs: &[String],
+    dynamic_specs: &[String],
     persist: bool,
     daemon: bool,
     cfg_override: Option<&str>,
 ) -> Result<()> {
-    use ssh::tunnel::{ForwardDirection, ForwardSpec, parse_forward_spec};
+    use ssh::tunnel::{ForwardDirection, ForwardSpec, parse_dynamic_spec, parse_forward_spec};
 
-    if local_specs.is_empty() && remote_specs.is_empty() {
-        bail!("No forward specs provided. Use -L or -R to specify port forwards.");
+    if local_specs.is_empty() && remote_specs.is_empty() && dynamic_specs.is_empty() {
+        bail!("No forward specs provided. Use -L, -R or -D to specify port forwards.");
     }
 
     let config = config::load_with_override(cfg_override).context("Failed to load config")?;
@@ -916,86 +1385,224 @@ async fn cmd_tunnel_start(
     for spec in remote_specs {
         forwards.push(parse_forward_spec(spec, ForwardDirection::Remote)?);
     }
+    for spec in dynamic_specs {
+        forwards.push(parse_dynamic_spec(spec)?);
+    }
 
-    if persist && !daemon {
-        // Re-exec as daemon: detach from terminal and run in background
-        let exe = std::env::current_exe().context("Failed to get current executable path")?;
-
-        let mut args = Vec::new();
-        if let Some(cfg) = cfg_override {
-            args.push("--config".to_string());
-            args.push(cfg.to_string());
-        }
-        args.extend([
-            "tunnel".to_string(),
-            "start".to_string(),
-            bookmark_name.to_string(),
-            "--persist".to_string(),
-            "--daemon".to_string(),
-        ]);
-        for spec in local_specs {
-            args.push("-L".to_string());
-            args.push(spec.clone());
-        }
-        for spec in remote_specs {
-            args.push("-R".to_string());
-            args.push(spec.clone());
+    // The daemon re-exec was already checked by its parent
+    if let Some(name) = name
+        && !daemon
+    {
+        let mut state = ssh::tunnel::load_tunnel_state().context("Failed to load tunnel state")?;
+        ssh::tunnel::cleanup_stale_tunnels(&mut state);
+        if let Some(existing) = ssh::tunnel::find_named_tunnel(&state, bookmark_name, name) {
+            bail!(
+                "A tunnel named '{name}' is already running for '{bookmark_name}' (ID {}).",
+                existing.id
+            );
         }
+    }
 
-        let child = std::process::Command::new(exe)
-            .args(&args)
-            .stdin(std::process::Stdio::null())
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .spawn()
-            .context("Failed to spawn daemon process")?;
-
-        println!(
-            "Persistent tunnel started for '{}' (PID {})",
+    if persist && !daemon {
+        let pid = spawn_tunnel_daemon(
             bookmark_name,
-            child.id()
-        );
+            name,
+            local_specs,
+            remote_specs,
+            dynamic_specs,
+            cfg_override,
+        )?;
+        println!("Persistent tunnel started for '{bookmark_name}' (PID {pid})");
         return Ok(());
     }
 
     if daemon {
         // Running as daemon process
-        ssh::tunnel::run_daemon_loop(&config, index, &forwards).await
+        ssh::tunnel::run_daemon_loop(&config, index, name, &forwards).await
     } else {
         // Foreground mode
-        ssh::tunnel::run_foreground(&config, index, &forwards).await
+        ssh::tunnel::run_foreground(&config, index, name, &forwards).await
+    }
+}
+
+/// Re-exec as a tunnel daemon: detach from the terminal and run in the
+/// background. Returns the daemon's PID.
+fn spawn_tunnel_daemon(
+    bookmark_name: &str,
+    name: Option<&str>,
+    local_specs: &[String],
+    remote_specs: &[String],
+    dynamic_specs: &[String],
+    cfg_override: Option<&str>,
+) -> Result<u32> {
+    let exe = std::env::current_exe().context("Failed to get current executable path")?;
+
+    let mut args = Vec::new();
+    if let Some(cfg) = cfg_override {
+        args.push("--config".to_string());
+        args.push(cfg.to_string());
+    }
+    args.extend([
+        "tunnel".to_string(),
+        "start".to_string(),
+        bookmark_name.to_string(),
+        "--persist".to_string(),
+        "--daemon".to_string(),
+    ]);
+    if let Some(name) = name {
+        args.push("--name".to_string());
+        args.push(name.to_string());
+    }
+    for spec in local_specs {
+        args.push("-L".to_string());
+        args.push(spec.clone());
+    }
+    for spec in remote_specs {
+        args.push("-R".to_string());
+        args.push(spec.clone());
     }
+    for spec in dynamic_specs {
+        args.push("-D".to_string());
+        args.push(spec.clone());
+    }
+
+    let child = std::process::Command::new(exe)
+        .args(&args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .context("Failed to spawn daemon process")?;
+
+    Ok(child.id())
 }
 
-/// Stop a tunnel for a bookmark.
-fn cmd_tunnel_stop(bookmark_name: &str) -> Result<()> {
-    use ssh::tunnel::{cleanup_stale_tunnels, load_tunnel_state, save_tunnel_state};
+/// Start saved tunnels from the config as persistent daemons.
+/// Without a target, only tunnels marked `autostart` are started.
+fn cmd_tunnel_up(target: Option<&str>, cfg_override: Option<&str>) -> Result<()> {
+    use ssh::tunnel::{
+        cleanup_stale_tunnels, declared_tunnels, find_named_tunnel, load_tunnel_state,
+        saved_tunnel_forwards, select_declared_tunnels,
+    };
+
+    let config = config::load_with_override(cfg_override).context("Failed to load config")?;
+    let mut selected = select_declared_tunnels(declared_tunnels(&config), target)?;
+    if target.is_none() {
+        selected.retain(|d| d.tunnel.autostart);
+    }
+
+    if selected.is_empty() {
+        println!("No saved tunnels to start (mark tunnels with autostart = true, or name one).");
+        return Ok(());
+    }
 
     let mut state = load_tunnel_state().context("Failed to load tunnel state")?;
     cleanup_stale_tunnels(&mut state);
 
-    let entry = state
-        .tunnels
+    for declared in &selected {
+        let tunnel = &declared.tunnel;
+        if let Some(existing) = find_named_tunnel(&state, &declared.bookmark, &tunnel.name) {
+            println!(
+                "Tunnel '{}' for '{}' is already running (ID {}).",
+                tunnel.name, declared.bookmark, existing.id
+            );
+            continue;
+        }
+
+        // Validate before detaching, where errors would go unseen
+        saved_tunnel_forwards(tunnel)
+            .with_context(|| format!("Invalid saved tunnel for '{}'", declared.bookmark))?;
+
+        let pid = spawn_tunnel_daemon(
+            &declared.bookmark,
+            Some(&tunnel.name),
+            &tunnel.local,
+            &tunnel.remote,
+            &tunnel.dynamic,
+            cfg_override,
+        )?;
+        println!(
+            "Started tunnel '{}' for '{}' (PID {pid})",
+            tunnel.name, declared.bookmark
+        );
+    }
+
+    Ok(())
+}
+
+/// Stop running tunnels that were declared in the config.
+fn cmd_tunnel_down(target: Option<&str>, cfg_override: Option<&str>) -> Result<()> {
+    use ssh::tunnel::{
+        cleanup_stale_tunnels, declared_tunnels, find_named_tunnel, load_tunnel_state,
+        select_declared_tunnels,
+    };
+
+    let config = config::load_with_override(cfg_override).context("Failed to load config")?;
+    let selected = select_declared_tunnels(declared_tunnels(&config), target)?;
+
+    let mut state = load_tunnel_state().context("Failed to load tunnel state")?;
+    cleanup_stale_tunnels(&mut state);
+
+    let running: Vec<ssh::tunnel::TunnelEntry> = selected
         .iter()
-        .find(|t| t.bookmark.eq_ignore_ascii_case(bookmark_name));
+        .filter_map(|d| find_named_tunnel(&state, &d.bookmark, &d.tunnel.name))
+        .cloned()
+        .collect();
 
-    let Some(entry) = entry else {
-        println!("No active tunnel for '{bookmark_name}'.");
+    if running.is_empty() {
+        println!("No saved tunnels are running.");
         return Ok(());
-    };
+    }
+
+    for entry in &running {
+        stop_tunnel_entry(&mut state, entry)?;
+    }
+
+    Ok(())
+}
+
+/// Stop tunnels matching a tunnel ID, tunnel name, or bookmark name.
+fn cmd_tunnel_stop(target: &str) -> Result<()> {
+    use ssh::tunnel::{cleanup_stale_tunnels, load_tunnel_state, resolve_tunnel_target};
+
+    let mut state = load_tunnel_state().context("Failed to load tunnel state")?;
+    cleanup_stale_tunnels(&mut state);
+
+    let entries: Vec<ssh::tunnel::TunnelEntry> = resolve_tunnel_target(&state, target)?
+        .into_iter()
+        .cloned()
+        .collect();
+
+    if entries.is_empty() {
+        println!("No active tunnel matches '{target}'.");
+        return Ok(());
+    }
+
+    for entry in &entries {
+        stop_tunnel_entry(&mut state, entry)?;
+    }
+
+    Ok(())
+}
+
+/// Terminate one tunnel process and remove its state entry.
+fn stop_tunnel_entry(
+    state: &mut ssh::tunnel::TunnelState,
+    entry: &ssh::tunnel::TunnelEntry,
+) -> Result<()> {
+    use ssh::tunnel::save_tunnel_state;
 
     let pid = entry.pid;
+    let description = format!("tunnel {} for '{}'", entry.label(), entry.bookmark);
 
     if !ssh::tunnel::pid_matches_tunnel_entry(pid, entry.started_at) {
         eprintln!(
             "Warning: PID {pid} no longer matches the original tunnel process start time. \
              Refusing to kill to avoid terminating an unrelated process."
         );
-        eprintln!("Removing stale tunnel entry for '{bookmark_name}'.");
-        state
-            .tunnels
-            .retain(|t| !t.bookmark.eq_ignore_ascii_case(bookmark_name));
-        save_tunnel_state(&state).context("Failed to update tunnel state")?;
+        eprintln!("Removing stale entry for {description}.");
+        state.tunnels.retain(|t| t.id != entry.id);
+        save_tunnel_state(state).context("Failed to update tunnel state")?;
         return Ok(());
     }
 
@@ -1013,7 +1620,7 @@ fn cmd_tunnel_stop(bookmark_name: &str) -> Result<()> {
             );
             return Ok(());
         }
-        println!("Stopped tunnel for '{bookmark_name}' (PID {pid}).");
+        println!("Stopped {description} (PID {pid}).");
     } else {
         if ssh::tunnel::is_process_alive(pid) {
             eprintln!("Warning: failed to signal PID {pid}; tunnel is still running.");
@@ -1023,10 +1630,8 @@ fn cmd_tunnel_stop(bookmark_name: &str) -> Result<()> {
     }
 
     // Remove from state file
-    state
-        .tunnels
-        .retain(|t| !t.bookmark.eq_ignore_ascii_case(bookmark_name));
-    save_tunnel_state(&state).context("Failed to update tunnel state")?;
+    state.tunnels.retain(|t| t.id != entry.id);
+    save_tunnel_state(state).context("Failed to update tunnel state")?;
 
     Ok(())
 }
@@ -1045,10 +1650,10 @@ fn cmd_tunnel_status() -> Result<()> {
     }
 
     println!(
-        "  {:<20} {:<30} {:<14} {:<10} RECONNECTS",
-        "BOOKMARK", "FORWARDS", "STATUS", "UPTIME"
+        "  {:<10} {:<14} {:<20} {:<30} {:<14} {:<10} RECONNECTS",
+        "ID", "NAME", "BOOKMARK", "FORWARDS", "STATUS", "UPTIME"
     );
-    println!("  {}", "-".repeat(86));
+    println!("  {}", "-".repeat(112));
 
     for entry in &state.tunnels {
         let forwards_str: Vec<String> = entry.forwards.iter().map(|f| f.to_string()).collect();
@@ -1066,8 +1671,14 @@ fn cmd_tunnel_status() -> Result<()> {
         let uptime_display = format_uptime(uptime);
 
         println!(
-            "  {:<20} {:<30} {:<14} {:<10} {}",
-            entry.bookmark, forwards_display, status_display, uptime_display, entry.reconnect_count
+            "  {:<10} {:<14} {:<20} {:<30} {:<14} {:<10} {}",
+            entry.id,
+            entry.name.as_deref().unwrap_or("-"),
+            entry.bookmark,
+            forwards_display,
+            status_display,
+            uptime_display,
+            entry.reconnect_count
         );
     }
 
@@ -1,4 +1,5 @@
 pub mod pipeline;
+pub mod preserve;
 pub mod shortcuts;
 
 use std::io::{self, BufRead, BufReader, BufWriter, Write};
@@ -9,6 +10,7 @@ use russh_sftp::client::SftpSession;
 use crate::config::model::AppConfig;
 use crate::ssh;
 use crate::ssh::client::SshoreHandler;
+use crate::ssh::exec_guard;
 use crate::ssh::terminal_theme;
 
 use self::shortcuts::ProgressBar;
@@ -26,7 +28,8 @@ pub async fn open_session(config: &AppConfig, bookmark_index: usize) -> Result<(
     );
     terminal_theme::apply_theme_with_title(bookmark, settings, &title);
     ssh::print_production_banner(bookmark, settings, &config.profiles, "SFTP session");
-    let is_production = bookmark.env.eq_ignore_ascii_case("production");
+    let production_host =
+        exec_guard::is_production(&bookmark.env).then_some(bookmark.name.as_str());
 
     // Open a session channel and request SFTP subsystem
     let channel = session
@@ -53,7 +56,7 @@ pub async fn open_session(config: &AppConfig, bookmark_index: usize) -> Result<(
     eprintln!("Type 'help' for available commands.");
 
     // Run the interactive command loop (pass SSH handle for pipelined transfers)
-    let result = run_command_loop(&sftp, &session, cwd, is_production).await;
+    let result = run_command_loop(&sftp, &session, cwd, production_host).await;
 
     // Always reset theme, even on error
     terminal_theme::reset_theme();
@@ -66,7 +69,7 @@ async fn run_command_loop(
     sftp: &SftpSession,
     session: &russh::client::Handle<SshoreHandler>,
     initial_cwd: String,
-    is_production: bool,
+    production_host: Option<&str>,
 ) -> Result<()> {
     let mut cwd = initial_cwd;
     let stdin = io::stdin();
@@ -163,7 +166,9 @@ async fn run_command_loop(
                     eprintln!("rm: missing path argument");
                 } else {
                     let path = resolve_path(&cwd, args);
-                    if is_production && !confirm_production_delete("rm", &path)? {
+                    if let Some(host) = production_host
+                        && !confirm_delete(host, &path)?
+                    {
                         eprintln!("rm: cancelled");
                         continue;
                     }
@@ -177,7 +182,9 @@ async fn run_command_loop(
                     eprintln!("rmdir: missing path argument");
                 } else {
                     let path = resolve_path(&cwd, args);
-                    if is_production && !confirm_production_delete("rmdir", &path)? {
+                    if let Some(host) = production_host
+                        && !confirm_delete(host, &path)?
+                    {
                         eprintln!("rmdir: cancelled");
                         continue;
                     }
@@ -213,13 +220,20 @@ async fn run_command_loop(
     Ok(())
 }
 
-/// Ask for explicit confirmation before destructive actions on production hosts.
-fn confirm_production_delete(action: &str, path: &str) -> Result<bool> {
-    eprint!("\x1b[1;37;41m PROD \x1b[0m Confirm {action} {path}? Type 'yes' to proceed: ");
-    io::stderr().flush()?;
-    let mut answer = String::new();
-    io::stdin().read_line(&mut answer)?;
-    Ok(answer.trim().eq_ignore_ascii_case("yes"))
+/// Ask for explicit confirmation before deleting `path` on a production host.
+/// The answer comes from the same stdin as the shell's commands, so there is
+/// no `--yes` to consider.
+fn confirm_delete(host: &str, path: &str) -> Result<bool> {
+    let confirmation = exec_guard::Confirmation {
+        assume_yes: false,
+        stdin_is_tty: true,
+    };
+    exec_guard::confirm_production_delete(
+        host,
+        &[path.to_string()],
+        confirmation,
+        exec_guard::prompt_stdin,
+    )
 }
 
 /// Parse a command line into (command, args).
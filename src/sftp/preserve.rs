@@ -0,0 +1,166 @@
+use std::fs::{self, FileTimes};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use russh_sftp::client::{RawSftpSession, SftpSession};
+use russh_sftp::protocol::FileAttributes;
+
+/// Permission bits that can be carried across (no file type bits).
+const MODE_MASK: u32 = 0o7777;
+
+/// File attributes carried across a transfer when preserving (like `scp -p`):
+/// permission bits plus access and modification times.
+///
+/// Ownership is deliberately left alone, as with `scp -p` — uids rarely
+/// match between hosts and only root may give files away.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PreservedAttrs {
+    pub mode: Option<u32>,
+    /// Unix seconds.
+    pub atime: Option<u32>,
+    /// Unix seconds.
+    pub mtime: Option<u32>,
+}
+
+impl PreservedAttrs {
+    /// Attributes of a local file.
+    pub fn from_local(meta: &fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(meta.permissions().mode() & MODE_MASK)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        Self {
+            mode,
+            atime: meta.accessed().ok().and_then(unix_secs),
+            mtime: meta.modified().ok().and_then(unix_secs),
+        }
+    }
+
+    /// Attributes reported by an SFTP server.
+    pub fn from_sftp(attrs: &FileAttributes) -> Self {
+        Self {
+            mode: attrs.permissions.map(|m| m & MODE_MASK),
+            atime: attrs.atime,
+            mtime: attrs.mtime,
+        }
+    }
+
+    /// Attributes for SETSTAT/FSETSTAT. Only the carried fields are set;
+    /// SFTP sends both times together, so a missing atime falls back to mtime.
+    pub fn to_sftp(self) -> FileAttributes {
+        let mut attrs = FileAttributes::empty();
+        attrs.permissions = self.mode;
+        if self.mtime.is_some() {
+            attrs.atime = self.atime.or(self.mtime);
+            attrs.mtime = self.mtime;
+        }
+        attrs
+    }
+
+    /// Apply to a local file. Times are set before the mode so a read-only
+    /// mode can't get in the way.
+    pub fn apply_local(&self, path: &Path) -> Result<()> {
+        if let Some(mtime) = self.mtime {
+            let mtime = from_unix_secs(mtime);
+            let atime = self.atime.map(from_unix_secs).unwrap_or(mtime);
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .open(path)
+                .with_context(|| format!("Failed to open {} to set times", path.display()))?;
+            file.set_times(FileTimes::new().set_accessed(atime).set_modified(mtime))
+                .with_context(|| format!("Failed to set times on {}", path.display()))?;
+        }
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))
+                .with_context(|| format!("Failed to set mode on {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Apply to a remote path with SETSTAT.
+    pub async fn apply_remote(&self, sftp: &SftpSession, path: &str) -> Result<()> {
+        sftp.set_metadata(path, self.to_sftp())
+            .await
+            .with_context(|| format!("Failed to set attributes on {path}"))
+    }
+
+    /// Apply to an open remote file with FSETSTAT.
+    pub async fn apply_remote_handle(&self, raw: &RawSftpSession, handle: &str) -> Result<()> {
+        raw.fsetstat(handle, self.to_sftp())
+            .await
+            .map(|_| ())
+            .map_err(|e| anyhow::anyhow!("Failed to set attributes: {e}"))
+    }
+}
+
+fn unix_secs(time: SystemTime) -> Option<u32> {
+    let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    u32::try_from(secs).ok()
+}
+
+fn from_unix_secs(secs: u32) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(u64::from(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_sftp_sets_only_carried_fields() {
+        let attrs = PreservedAttrs {
+            mode: Some(0o755),
+            atime: None,
+            mtime: Some(1_700_000_000),
+        }
+        .to_sftp();
+        assert_eq!(attrs.permissions, Some(0o755));
+        assert_eq!(attrs.atime, Some(1_700_000_000));
+        assert_eq!(attrs.mtime, Some(1_700_000_000));
+        assert_eq!(attrs.size, None);
+        assert_eq!(attrs.uid, None);
+    }
+
+    #[test]
+    fn test_from_sftp_strips_file_type_bits() {
+        let mut attrs = FileAttributes::empty();
+        attrs.permissions = Some(0o100_640);
+        attrs.mtime = Some(42);
+        let preserved = PreservedAttrs::from_sftp(&attrs);
+        assert_eq!(preserved.mode, Some(0o640));
+        assert_eq!(preserved.mtime, Some(42));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_local_round_trip() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src.sh");
+        let dst = dir.path().join("dst.sh");
+        fs::write(&src, "#!/bin/sh\n").unwrap();
+        fs::write(&dst, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&src, fs::Permissions::from_mode(0o750)).unwrap();
+        let old = fs::File::options().write(true).open(&src).unwrap();
+        old.set_modified(from_unix_secs(1_600_000_000)).unwrap();
+
+        let preserved = PreservedAttrs::from_local(&fs::metadata(&src).unwrap());
+        preserved.apply_local(&dst).unwrap();
+
+        let meta = fs::metadata(&dst).unwrap();
+        assert_eq!(meta.permissions().mode() & MODE_MASK, 0o750);
+        assert_eq!(
+            meta.modified().unwrap(),
+            from_unix_secs(1_600_000_000),
+            "mtime should be carried over"
+        );
+    }
+}
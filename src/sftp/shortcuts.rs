@@ -1,4 +1,5 @@
 use std::io::{BufWriter, Write};
+use std::path::Path;
 use std::time::Instant;
 
 use anyhow::{Context, Result, bail};
@@ -10,6 +11,7 @@ use crate::ssh::client::SshoreHandler;
 use crate::ssh::terminal_theme;
 
 use super::pipeline;
+use super::preserve::PreservedAttrs;
 
 /// Minimum interval between progress bar redraws (100ms).
 const PROGRESS_THROTTLE_MS: u128 = 100;
@@ -40,12 +42,14 @@ pub fn parse_remote_spec(spec: &str) -> Option<(&str, &str)> {
     Some((bookmark, path))
 }
 
-/// Execute an SCP-style file transfer.
+/// Execute an SCP-style file transfer. With `preserve`, the source's mode and
+/// times are applied to the destination, like `scp -p`.
 pub async fn scp_transfer(
     config: &AppConfig,
     source: &str,
     destination: &str,
     resume: bool,
+    preserve: bool,
 ) -> Result<()> {
     let src_remote = parse_remote_spec(source);
     let dst_remote = parse_remote_spec(destination);
@@ -62,7 +66,15 @@ pub async fn scp_transfer(
         (Some((bookmark_name, remote_path)), None) => {
             // Download: remote -> local
             let local_path = destination;
-            download(config, bookmark_name, remote_path, local_path, resume).await
+            download(
+                config,
+                bookmark_name,
+                remote_path,
+                local_path,
+                resume,
+                preserve,
+            )
+            .await
         }
         (None, Some((bookmark_name, remote_path))) => {
             // Upload: local -> remote
@@ -70,7 +82,7 @@ pub async fn scp_transfer(
                 eprintln!("Warning: --resume is only supported for downloads, ignoring.");
             }
             let local_path = source;
-            upload(config, bookmark_name, local_path, remote_path).await
+            upload(config, bookmark_name, local_path, remote_path, preserve).await
         }
     }
 }
@@ -134,6 +146,7 @@ async fn download(
     remote_path: &str,
     local_path: &str,
     resume: bool,
+    preserve: bool,
 ) -> Result<()> {
     let (session, sftp, index) = open_ssh_and_sftp(config, bookmark_name).await?;
     let display_name = &config.bookmarks[index].name;
@@ -157,6 +170,9 @@ async fn download(
             "Local file is already complete ({}).",
             format_bytes(local_meta.len())
         );
+        if preserve {
+            PreservedAttrs::from_sftp(&meta).apply_local(Path::new(local_path))?;
+        }
         terminal_theme::reset_theme();
         return Ok(());
     }
@@ -210,10 +226,17 @@ async fn download(
         None,
     )
     .await?;
+    local_file
+        .flush()
+        .with_context(|| format!("Failed to write {part_path}"))?;
+    drop(local_file);
 
     // Rename .part to final path on success.
     std::fs::rename(&part_path, local_path)
         .with_context(|| format!("Failed to rename {part_path} to {local_path}"))?;
+    if preserve {
+        PreservedAttrs::from_sftp(&meta).apply_local(Path::new(local_path))?;
+    }
 
     progress.finish();
     terminal_theme::reset_theme();
@@ -226,8 +249,9 @@ async fn upload(
     bookmark_name: &str,
     local_path: &str,
     remote_path: &str,
+    preserve: bool,
 ) -> Result<()> {
-    let (session, _sftp, index) = open_ssh_and_sftp(config, bookmark_name).await?;
+    let (session, sftp, index) = open_ssh_and_sftp(config, bookmark_name).await?;
     let display_name = &config.bookmarks[index].name;
 
     let local_meta =
@@ -264,6 +288,11 @@ async fn upload(
         None,
     )
     .await?;
+    if preserve {
+        PreservedAttrs::from_local(&local_meta)
+            .apply_remote(&sftp, remote_path)
+            .await?;
+    }
 
     progress.finish();
     terminal_theme::reset_theme();
@@ -0,0 +1,264 @@
+use anyhow::Result;
+use russh::MethodSet;
+use russh::client::AuthResult;
+use russh::keys::agent::client::AgentClient;
+use russh::keys::{Certificate, HashAlg, PublicKey};
+
+use super::certificate;
+use super::client::SshoreHandler;
+
+/// Agent client over the platform's agent transport.
+#[cfg(unix)]
+pub type LocalAgentClient = AgentClient<tokio::net::UnixStream>;
+/// Agent client over the platform's agent transport.
+#[cfg(windows)]
+pub type LocalAgentClient = AgentClient<tokio::net::windows::named_pipe::NamedPipeClient>;
+
+/// Named pipe used by the Windows OpenSSH agent service.
+#[cfg(windows)]
+const WINDOWS_AGENT_PIPE: &str = r"\\.\pipe\openssh-ssh-agent";
+
+/// Connect to the local ssh-agent.
+/// On Unix this uses `SSH_AUTH_SOCK`; on Windows the OpenSSH agent pipe.
+/// Returns None when no agent is reachable (not an error — the agent is optional).
+pub async fn connect() -> Option<LocalAgentClient> {
+    #[cfg(unix)]
+    let result = AgentClient::connect_env().await;
+    #[cfg(windows)]
+    let result = AgentClient::connect_named_pipe(WINDOWS_AGENT_PIPE).await;
+
+    match result {
+        Ok(client) => Some(client),
+        Err(e) => {
+            tracing::debug!(error = %e, "ssh-agent not available");
+            None
+        }
+    }
+}
+
+/// Open a raw stream to the local ssh-agent, for bridging forwarded agent channels.
+#[cfg(unix)]
+pub async fn connect_stream() -> std::io::Result<tokio::net::UnixStream> {
+    let path = std::env::var_os("SSH_AUTH_SOCK").ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "SSH_AUTH_SOCK is not set")
+    })?;
+    tokio::net::UnixStream::connect(path).await
+}
+
+/// Open a raw stream to the local ssh-agent, for bridging forwarded agent channels.
+#[cfg(windows)]
+pub async fn connect_stream() -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    tokio::net::windows::named_pipe::ClientOptions::new().open(WINDOWS_AGENT_PIPE)
+}
+
+/// Whether a local agent is configured (without connecting to it).
+pub fn is_available() -> bool {
+    #[cfg(unix)]
+    {
+        std::env::var_os("SSH_AUTH_SOCK").is_some_and(|s| !s.is_empty())
+    }
+    #[cfg(windows)]
+    {
+        std::path::Path::new(WINDOWS_AGENT_PIPE).exists()
+    }
+}
+
+/// Hash algorithms to try when signing with an agent-held key.
+/// Mirrors `wrap_key`: RSA keys try `rsa-sha2-256` then `rsa-sha2-512`,
+/// other key types need no hash override.
+fn signing_hash_algs(key: &PublicKey) -> Vec<Option<HashAlg>> {
+    if key.algorithm().is_rsa() {
+        vec![Some(HashAlg::Sha256), Some(HashAlg::Sha512)]
+    } else {
+        vec![None]
+    }
+}
+
+/// Failure result for "no agent key was accepted".
+fn rejected() -> AuthResult {
+    AuthResult::Failure {
+        remaining_methods: MethodSet::empty(),
+        partial_success: false,
+    }
+}
+
+/// Agent identities to offer, in order. With a configured `identity_file`
+/// only the agent key matching it is offered, like OpenSSH's `IdentitiesOnly`:
+/// offering every agent key first can exhaust the server's `MaxAuthTries`
+/// before the configured key is tried.
+fn identities_to_offer(identities: Vec<PublicKey>, identity: Option<&PublicKey>) -> Vec<PublicKey> {
+    match identity {
+        Some(identity) => identities
+            .into_iter()
+            .filter(|key| key.key_data() == identity.key_data())
+            .collect(),
+        None => identities,
+    }
+}
+
+/// Try public key authentication with the identities held by the local ssh-agent.
+/// `identity` is the public key of the bookmark's `identity_file`, if any; see
+/// [`identities_to_offer`].
+/// Returns a failure if no agent is running, it holds no usable keys, or all keys are
+/// rejected. A key that yields partial success (the server wants a second factor)
+/// stops the search and its failure result is returned, so the caller can continue
+/// with keyboard-interactive authentication.
+pub async fn authenticate(
+    session: &mut russh::client::Handle<SshoreHandler>,
+    user: &str,
+    identity: Option<&PublicKey>,
+) -> Result<AuthResult> {
+    let Some(mut agent) = connect().await else {
+        return Ok(rejected());
+    };
+
+    let identities = match agent.request_identities().await {
+        Ok(keys) => keys,
+        Err(e) => {
+            tracing::debug!(error = %e, "failed to list ssh-agent identities");
+            return Ok(rejected());
+        }
+    };
+    tracing::debug!(key_count = identities.len(), "loaded ssh-agent identities");
+
+    for key in identities_to_offer(identities, identity) {
+        for hash_alg in signing_hash_algs(&key) {
+            tracing::debug!(comment = key.comment(), ?hash_alg, "trying ssh-agent key");
+            match session
+                .authenticate_publickey_with(user, key.clone(), hash_alg, &mut agent)
+                .await
+            {
+                Ok(AuthResult::Success) => {
+                    tracing::debug!(comment = key.comment(), "ssh-agent auth succeeded");
+                    return Ok(AuthResult::Success);
+                }
+                Ok(
+                    failure @ AuthResult::Failure {
+                        partial_success: true,
+                        ..
+                    },
+                ) => {
+                    tracing::debug!(comment = key.comment(), "ssh-agent key partially accepted");
+                    return Ok(failure);
+                }
+                Ok(AuthResult::Failure { .. }) => {
+                    tracing::debug!(comment = key.comment(), "ssh-agent key rejected");
+                }
+                Err(e) => {
+                    tracing::debug!(error = %e, "ssh-agent signing error");
+                }
+            }
+        }
+    }
+
+    Ok(rejected())
+}
+
+/// Whether the local ssh-agent holds the private key `cert` certifies.
+pub async fn holds_certificate_key(cert: &Certificate) -> bool {
+    let Some(mut agent) = connect().await else {
+        return false;
+    };
+    match agent.request_identities().await {
+        Ok(keys) => keys
+            .iter()
+            .any(|key| certificate::matches_public_key(cert, key)),
+        Err(e) => {
+            tracing::debug!(error = %e, "failed to list ssh-agent identities");
+            false
+        }
+    }
+}
+
+/// Present an OpenSSH certificate whose private key is held by the local
+/// ssh-agent; the agent signs with the certified key.
+/// Partial success is returned as-is, like [`authenticate`].
+pub async fn authenticate_certificate(
+    session: &mut russh::client::Handle<SshoreHandler>,
+    user: &str,
+    cert: &Certificate,
+) -> Result<AuthResult> {
+    let Some(mut agent) = connect().await else {
+        return Ok(rejected());
+    };
+
+    let key = PublicKey::from(cert.public_key().clone());
+    for hash_alg in signing_hash_algs(&key) {
+        tracing::debug!(
+            key_id = cert.key_id(),
+            ?hash_alg,
+            "trying ssh-agent certificate"
+        );
+        match session
+            .authenticate_certificate_with(user, cert.clone(), hash_alg, &mut agent)
+            .await
+        {
+            Ok(AuthResult::Success) => return Ok(AuthResult::Success),
+            Ok(
+                failure @ AuthResult::Failure {
+                    partial_success: true,
+                    ..
+                },
+            ) => return Ok(failure),
+            Ok(AuthResult::Failure { .. }) => {
+                tracing::debug!(key_id = cert.key_id(), "ssh-agent certificate rejected");
+            }
+            Err(e) => {
+                tracing::debug!(error = %e, "ssh-agent certificate signing error");
+            }
+        }
+    }
+
+    Ok(rejected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_hash_algs_ed25519_no_override() {
+        let key = PublicKey::from_openssh(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl test",
+        )
+        .unwrap();
+        assert_eq!(signing_hash_algs(&key), vec![None]);
+    }
+
+    fn public_key(openssh: &str) -> PublicKey {
+        PublicKey::from_openssh(openssh).unwrap()
+    }
+
+    #[test]
+    fn test_identities_to_offer_only_configured_identity() {
+        let other = public_key(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl other",
+        );
+        let configured = public_key(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAICee1HwKwgazS6pYO9Tj60qI2ihiWyVnkv9kM5LtQMSh agent-comment",
+        );
+        let agent_keys = vec![other.clone(), other.clone(), configured.clone()];
+
+        // The identity_file's .pub may carry a different comment than the agent
+        let from_file = public_key(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAICee1HwKwgazS6pYO9Tj60qI2ihiWyVnkv9kM5LtQMSh file-comment",
+        );
+        let offered = identities_to_offer(agent_keys.clone(), Some(&from_file));
+        assert_eq!(offered, vec![configured]);
+
+        // Without an identity_file every agent key is offered, in agent order
+        assert_eq!(identities_to_offer(agent_keys.clone(), None), agent_keys);
+    }
+
+    #[test]
+    fn test_identities_to_offer_identity_not_in_agent() {
+        let agent_keys = vec![public_key(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl other",
+        )];
+        let configured = public_key(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAICee1HwKwgazS6pYO9Tj60qI2ihiWyVnkv9kM5LtQMSh",
+        );
+        // Nothing is offered, so the on-disk identity_file is the next key tried
+        assert!(identities_to_offer(agent_keys, Some(&configured)).is_empty());
+    }
+}
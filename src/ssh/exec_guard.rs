@@ -0,0 +1,433 @@
+use std::io::{self, Write};
+
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+
+use crate::config::model::AppConfig;
+
+/// Environment tier that requires typed confirmation before multi-host exec
+/// (the user types this name) or deleting files.
+pub const PRODUCTION_ENV: &str = "production";
+
+/// Whether `env` is the production tier.
+pub fn is_production(env: &str) -> bool {
+    env.eq_ignore_ascii_case(PRODUCTION_ENV)
+}
+
+/// How the caller confirms a guarded multi-host exec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Confirmation {
+    /// `--yes` was passed.
+    pub assume_yes: bool,
+    /// Whether stdin is an interactive terminal.
+    pub stdin_is_tty: bool,
+}
+
+/// Return the first `exec_denylist` pattern matching `command`, if any.
+pub fn matching_denylist_pattern<'a>(
+    command: &str,
+    patterns: &'a [String],
+) -> Result<Option<&'a str>> {
+    for pattern in patterns {
+        let re = Regex::new(pattern)
+            .with_context(|| format!("Invalid exec_denylist pattern '{pattern}'"))?;
+        if re.is_match(command) {
+            return Ok(Some(pattern));
+        }
+    }
+    Ok(None)
+}
+
+/// Names of the targeted bookmarks in the production tier.
+pub fn production_targets(config: &AppConfig, indices: &[usize]) -> Vec<String> {
+    indices
+        .iter()
+        .map(|&i| &config.bookmarks[i])
+        .filter(|b| is_production(&b.env))
+        .map(|b| b.name.clone())
+        .collect()
+}
+
+/// Confirm a multi-host exec before anything connects.
+///
+/// Production targets require typing the environment name, and commands
+/// matching `settings.exec_denylist` require typing "yes" on top. `script`
+/// (a `--script` body) is checked against the denylist too; piped `--stdin`
+/// data is not, since it is input to the command rather than code. `--yes`
+/// skips both prompts but is only accepted when stdin is not a terminal,
+/// so an interactive user can't bypass the check out of habit.
+///
+/// `prompt` shows a message and returns the line the user typed. Returns
+/// `Ok(false)` if the user declined.
+pub fn confirm_multi_exec(
+    config: &AppConfig,
+    indices: &[usize],
+    command: &str,
+    script: Option<&[u8]>,
+    confirmation: Confirmation,
+    mut prompt: impl FnMut(&str) -> Result<String>,
+) -> Result<bool> {
+    let production = production_targets(config, indices);
+    let patterns = &config.settings.exec_denylist;
+    let mut denylisted = matching_denylist_pattern(command, patterns)?;
+    if denylisted.is_none()
+        && let Some(script) = script
+    {
+        denylisted = matching_denylist_pattern(&String::from_utf8_lossy(script), patterns)?;
+    }
+
+    if production.is_empty() && denylisted.is_none() {
+        return Ok(true);
+    }
+
+    if skip_prompts(
+        confirmation,
+        "to run against production hosts or destructive commands",
+    )? {
+        return Ok(true);
+    }
+
+    if !production.is_empty() {
+        let mut message = format!(
+            "\x1b[1;37;41m PROD \x1b[0m About to run `{command}` on {} production host(s):\n",
+            production.len()
+        );
+        for name in &production {
+            message.push_str(&format!("  - {name}\n"));
+        }
+        message.push_str(&format!("Type '{PRODUCTION_ENV}' to proceed: "));
+        if !prompt(&message)?
+            .trim()
+            .eq_ignore_ascii_case(PRODUCTION_ENV)
+        {
+            return Ok(false);
+        }
+    }
+
+    if let Some(pattern) = denylisted {
+        let message = format!(
+            "\x1b[1;31mDestructive command:\x1b[0m `{command}` matches exec_denylist pattern `{pattern}`.\n\
+             Type 'yes' to run it on {} host(s): ",
+            indices.len()
+        );
+        if !prompt(&message)?.trim().eq_ignore_ascii_case("yes") {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Confirm deleting `paths` on the production host `bookmark` (sftp `rm`,
+/// `sync --delete`).
+///
+/// The user types "yes"; `--yes` follows the same rules as in
+/// [`confirm_multi_exec`].
+pub fn confirm_production_delete(
+    bookmark: &str,
+    paths: &[String],
+    confirmation: Confirmation,
+    mut prompt: impl FnMut(&str) -> Result<String>,
+) -> Result<bool> {
+    if paths.is_empty() || skip_prompts(confirmation, "to delete files on production hosts")? {
+        return Ok(true);
+    }
+
+    const SHOWN: usize = 10;
+    let mut message = format!(
+        "\x1b[1;37;41m PROD \x1b[0m About to delete {} path(s) on {bookmark}:\n",
+        paths.len()
+    );
+    for path in paths.iter().take(SHOWN) {
+        message.push_str(&format!("  - {path}\n"));
+    }
+    if paths.len() > SHOWN {
+        message.push_str(&format!("  ...and {} more\n", paths.len() - SHOWN));
+    }
+    message.push_str("Type 'yes' to proceed: ");
+    Ok(prompt(&message)?.trim().eq_ignore_ascii_case("yes"))
+}
+
+/// Show `message` on stderr and read the answer from stdin.
+pub fn prompt_stdin(message: &str) -> Result<String> {
+    eprint!("{message}");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer)
+}
+
+/// Apply the `--yes` rules. Returns `Ok(true)` when prompts are skipped and
+/// `Ok(false)` when the user must be asked; `purpose` completes the hint
+/// "Pass --yes ... from a script".
+fn skip_prompts(confirmation: Confirmation, purpose: &str) -> Result<bool> {
+    if confirmation.assume_yes {
+        if confirmation.stdin_is_tty {
+            bail!(
+                "--yes is only accepted for scripted use (stdin is not a terminal). \
+                 Confirm interactively instead."
+            );
+        }
+        return Ok(true);
+    }
+    if !confirmation.stdin_is_tty {
+        bail!(
+            "Confirmation required but stdin is not a terminal. \
+             Pass --yes {purpose} from a script."
+        );
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[[bookmarks]]
+name = "prod-web"
+host = "10.0.0.1"
+env = "production"
+
+[[bookmarks]]
+name = "stg-web"
+host = "10.0.1.1"
+env = "staging"
+"#;
+
+    const TTY: Confirmation = Confirmation {
+        assume_yes: false,
+        stdin_is_tty: true,
+    };
+
+    fn config() -> AppConfig {
+        toml::from_str(CONFIG).unwrap()
+    }
+
+    /// Prompt that replays `answers` in order and records the messages shown.
+    fn scripted<'a>(
+        answers: &'a [&'a str],
+        shown: &'a mut Vec<String>,
+    ) -> impl FnMut(&str) -> Result<String> + 'a {
+        let mut answers = answers.iter();
+        move |message| {
+            shown.push(message.to_string());
+            Ok(answers.next().expect("unexpected prompt").to_string())
+        }
+    }
+
+    #[test]
+    fn test_default_denylist_matches_destructive_commands() {
+        let patterns = crate::config::model::Settings::default().exec_denylist;
+        for command in [
+            "rm -rf /var/www",
+            "sudo rm -f /etc/nginx/sites-enabled/default",
+            "sudo reboot",
+            "mkfs.ext4 /dev/sdb1",
+            "dd if=/dev/zero of=/dev/sda",
+            "systemctl stop nginx",
+            "psql -c 'DROP TABLE users'",
+        ] {
+            assert!(
+                matching_denylist_pattern(command, &patterns)
+                    .unwrap()
+                    .is_some(),
+                "{command} should match"
+            );
+        }
+        for command in [
+            "uptime",
+            "df -h",
+            "systemctl status nginx",
+            "systemctl restart nginx",
+            "rmdir /tmp/x",
+        ] {
+            assert!(
+                matching_denylist_pattern(command, &patterns)
+                    .unwrap()
+                    .is_none(),
+                "{command} should not match"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_denylist_pattern_is_an_error() {
+        assert!(matching_denylist_pattern("ls", &["(".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_non_production_safe_command_needs_no_prompt() {
+        let mut shown = Vec::new();
+        let ok = confirm_multi_exec(
+            &config(),
+            &[1],
+            "uptime",
+            None,
+            TTY,
+            scripted(&[], &mut shown),
+        );
+        assert!(ok.unwrap());
+        assert!(shown.is_empty());
+    }
+
+    #[test]
+    fn test_production_requires_typing_env_name() {
+        let mut shown = Vec::new();
+        let ok = confirm_multi_exec(
+            &config(),
+            &[0, 1],
+            "uptime",
+            None,
+            TTY,
+            scripted(&["production"], &mut shown),
+        );
+        assert!(ok.unwrap());
+        assert!(shown[0].contains("prod-web"));
+        assert!(!shown[0].contains("stg-web"));
+
+        let mut shown = Vec::new();
+        let ok = confirm_multi_exec(
+            &config(),
+            &[0],
+            "uptime",
+            None,
+            TTY,
+            scripted(&["y"], &mut shown),
+        );
+        assert!(!ok.unwrap());
+    }
+
+    #[test]
+    fn test_denylisted_command_needs_extra_confirmation() {
+        let mut shown = Vec::new();
+        let ok = confirm_multi_exec(
+            &config(),
+            &[0],
+            "sudo reboot",
+            None,
+            TTY,
+            scripted(&["production", "no"], &mut shown),
+        );
+        assert!(!ok.unwrap());
+        assert_eq!(shown.len(), 2);
+        assert!(shown[1].contains("exec_denylist"));
+
+        // Denylist applies outside production too
+        let mut shown = Vec::new();
+        let ok = confirm_multi_exec(
+            &config(),
+            &[1],
+            "sudo reboot",
+            None,
+            TTY,
+            scripted(&["yes"], &mut shown),
+        );
+        assert!(ok.unwrap());
+        assert_eq!(shown.len(), 1);
+    }
+
+    #[test]
+    fn test_denylist_checks_script_body() {
+        let mut shown = Vec::new();
+        let script = b"#!/bin/sh\nset -e\nrm -rf /srv/app/releases/old\n";
+        let ok = confirm_multi_exec(
+            &config(),
+            &[1],
+            "sh -s --",
+            Some(script),
+            TTY,
+            scripted(&["no"], &mut shown),
+        );
+        assert!(!ok.unwrap());
+        assert!(shown[0].contains("exec_denylist"));
+    }
+
+    #[test]
+    fn test_yes_flag_only_accepted_without_tty() {
+        let scripted_run = Confirmation {
+            assume_yes: true,
+            stdin_is_tty: false,
+        };
+        let mut shown = Vec::new();
+        let ok = confirm_multi_exec(
+            &config(),
+            &[0],
+            "sudo reboot",
+            None,
+            scripted_run,
+            scripted(&[], &mut shown),
+        );
+        assert!(ok.unwrap());
+
+        let interactive_yes = Confirmation {
+            assume_yes: true,
+            stdin_is_tty: true,
+        };
+        let mut shown = Vec::new();
+        let result = confirm_multi_exec(
+            &config(),
+            &[0],
+            "uptime",
+            None,
+            interactive_yes,
+            scripted(&[], &mut shown),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_no_tty_without_yes_is_refused() {
+        let piped = Confirmation {
+            assume_yes: false,
+            stdin_is_tty: false,
+        };
+        let mut shown = Vec::new();
+        let result = confirm_multi_exec(
+            &config(),
+            &[0],
+            "uptime",
+            None,
+            piped,
+            scripted(&[], &mut shown),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_is_production_ignores_case() {
+        assert!(is_production("production"));
+        assert!(is_production("Production"));
+        assert!(!is_production("staging"));
+        assert!(!is_production(""));
+    }
+
+    #[test]
+    fn test_production_delete_requires_typing_yes() {
+        let paths = vec!["old.txt".to_string(), "stale/".to_string()];
+        let mut shown = Vec::new();
+        let ok = confirm_production_delete("prod-web", &paths, TTY, scripted(&["yes"], &mut shown));
+        assert!(ok.unwrap());
+        assert!(shown[0].contains("2 path(s) on prod-web"));
+        assert!(shown[0].contains("stale/"));
+
+        let mut shown = Vec::new();
+        let ok = confirm_production_delete("prod-web", &paths, TTY, scripted(&["y"], &mut shown));
+        assert!(!ok.unwrap());
+
+        // Nothing to delete, nothing to ask
+        let mut shown = Vec::new();
+        let ok = confirm_production_delete("prod-web", &[], TTY, scripted(&[], &mut shown));
+        assert!(ok.unwrap());
+        assert!(shown.is_empty());
+
+        let piped = Confirmation {
+            assume_yes: false,
+            stdin_is_tty: false,
+        };
+        let mut shown = Vec::new();
+        let result =
+            confirm_production_delete("prod-web", &paths, piped, scripted(&[], &mut shown));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,324 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Process exit code when at least one host ran the command and it failed.
+pub const EXIT_HOST_FAILED: i32 = 1;
+
+/// Process exit code when at least one host could not be reached.
+/// Matches `ssh`, which exits 255 on connection errors.
+pub const EXIT_HOST_UNREACHABLE: i32 = 255;
+
+/// How multi-host exec reports results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecOutput {
+    /// Colored per-host blocks followed by a summary table.
+    #[default]
+    Text,
+    /// A single JSON array printed once every host has finished.
+    Json,
+    /// One JSON object per line, printed as each host finishes.
+    Jsonl,
+}
+
+/// How multi-host exec reacts to failing hosts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorMode {
+    /// Run on every host; exit non-zero if any failed.
+    #[default]
+    Continue,
+    /// Stop starting new hosts after the first failure; exit non-zero.
+    FailFast,
+    /// Run on every host and always exit 0.
+    IgnoreErrors,
+}
+
+/// Outcome of running a command on one host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostStatus {
+    /// Command exited 0.
+    Ok,
+    /// Command ran but exited non-zero.
+    Failed,
+    /// Connection, authentication or channel setup failed.
+    Unreachable,
+    /// Not attempted because an earlier host failed under `--fail-fast`.
+    Skipped,
+}
+
+impl HostStatus {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Failed => "failed",
+            Self::Unreachable => "unreachable",
+            Self::Skipped => "skipped",
+        }
+    }
+}
+
+/// Per-host result of a multi-host exec, as emitted by `--output json|jsonl`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HostExecResult {
+    /// Bookmark name.
+    pub host: String,
+    pub status: HostStatus,
+    /// Remote exit code; `None` when the command never ran.
+    pub exit_code: Option<u32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration_ms: u64,
+    /// Connection error message for unreachable hosts.
+    pub error: Option<String>,
+}
+
+impl HostExecResult {
+    /// Result for a host where the command ran to completion.
+    pub fn completed(
+        host: &str,
+        exit_code: u32,
+        stdout: String,
+        stderr: String,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            host: host.to_string(),
+            status: if exit_code == 0 {
+                HostStatus::Ok
+            } else {
+                HostStatus::Failed
+            },
+            exit_code: Some(exit_code),
+            stdout,
+            stderr,
+            duration_ms: duration.as_millis() as u64,
+            error: None,
+        }
+    }
+
+    /// Result for a host that could not be connected to.
+    pub fn unreachable(host: &str, error: &anyhow::Error, duration: Duration) -> Self {
+        Self {
+            host: host.to_string(),
+            status: HostStatus::Unreachable,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            duration_ms: duration.as_millis() as u64,
+            error: Some(format!("{error:#}")),
+        }
+    }
+
+    /// Result for a host that was never attempted.
+    pub fn skipped(host: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            status: HostStatus::Skipped,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            duration_ms: 0,
+            error: None,
+        }
+    }
+
+    /// Whether this host counts as a failure for exit code purposes.
+    pub fn is_failure(&self) -> bool {
+        matches!(self.status, HostStatus::Failed | HostStatus::Unreachable)
+    }
+}
+
+/// Overall process exit code for a multi-host exec.
+///
+/// Unreachable hosts take precedence over failed commands so CI can tell
+/// "the check failed" apart from "the check could not run".
+pub fn overall_exit_code(results: &[HostExecResult], mode: ErrorMode) -> i32 {
+    if mode == ErrorMode::IgnoreErrors {
+        return 0;
+    }
+    if results.iter().any(|r| r.status == HostStatus::Unreachable) {
+        EXIT_HOST_UNREACHABLE
+    } else if results.iter().any(|r| r.status == HostStatus::Failed) {
+        EXIT_HOST_FAILED
+    } else {
+        0
+    }
+}
+
+/// Render the colored text block shown for one host in text mode.
+/// Skipped hosts never ran, so they get a single dimmed line instead.
+pub fn format_host_block(result: &HostExecResult) -> String {
+    if result.status == HostStatus::Skipped {
+        return format!("\x1b[2m── {} ── skipped\x1b[0m\n", result.host);
+    }
+    let mut output = format!("\x1b[1m── {} ──\x1b[0m\n{}", result.host, result.stdout);
+    if !result.stderr.is_empty() {
+        output.push_str(&format!("\x1b[31m{}\x1b[0m", result.stderr));
+    }
+    match result.status {
+        HostStatus::Failed => {
+            if let Some(code) = result.exit_code {
+                output.push_str(&format!("\x1b[31m(exit code: {code})\x1b[0m\n"));
+            }
+        }
+        HostStatus::Unreachable => {
+            let error = result.error.as_deref().unwrap_or("unknown error");
+            output.push_str(&format!("\x1b[31mError: {error}\x1b[0m\n"));
+        }
+        HostStatus::Ok | HostStatus::Skipped => {}
+    }
+    output
+}
+
+/// Render the summary table printed after all hosts finish in text mode.
+pub fn format_summary(results: &[HostExecResult]) -> String {
+    let mut out = String::from("\n\x1b[1m── Summary ──\x1b[0m\n");
+    out.push_str(&format!(
+        "  {:<24} {:<12} {:<6} DURATION\n",
+        "HOST", "STATUS", "EXIT"
+    ));
+
+    for r in results {
+        let color = match r.status {
+            HostStatus::Ok => "\x1b[32m",
+            HostStatus::Failed | HostStatus::Unreachable => "\x1b[31m",
+            HostStatus::Skipped => "\x1b[2m",
+        };
+        let exit = r
+            .exit_code
+            .map_or_else(|| "-".to_string(), |c| c.to_string());
+        let duration = if r.status == HostStatus::Skipped {
+            "-".to_string()
+        } else {
+            format!("{:.1}s", r.duration_ms as f64 / 1000.0)
+        };
+        out.push_str(&format!(
+            "  {:<24} {color}{:<12}\x1b[0m {:<6} {}\n",
+            r.host,
+            r.status.label(),
+            exit,
+            duration
+        ));
+    }
+
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    out.push_str(&format!(
+        "\n  {} ok, {} failed, {} unreachable, {} skipped\n",
+        count(HostStatus::Ok),
+        count(HostStatus::Failed),
+        count(HostStatus::Unreachable),
+        count(HostStatus::Skipped)
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(host: &str) -> HostExecResult {
+        HostExecResult::completed(
+            host,
+            0,
+            "up\n".into(),
+            String::new(),
+            Duration::from_millis(1200),
+        )
+    }
+
+    fn failed(host: &str) -> HostExecResult {
+        HostExecResult::completed(
+            host,
+            3,
+            String::new(),
+            "boom\n".into(),
+            Duration::from_millis(50),
+        )
+    }
+
+    fn unreachable(host: &str) -> HostExecResult {
+        HostExecResult::unreachable(
+            host,
+            &anyhow::anyhow!("Connection refused"),
+            Duration::from_millis(10),
+        )
+    }
+
+    #[test]
+    fn test_completed_status_from_exit_code() {
+        assert_eq!(ok("a").status, HostStatus::Ok);
+        assert_eq!(failed("a").status, HostStatus::Failed);
+        assert_eq!(failed("a").exit_code, Some(3));
+    }
+
+    #[test]
+    fn test_overall_exit_code() {
+        assert_eq!(
+            overall_exit_code(&[ok("a"), ok("b")], ErrorMode::Continue),
+            0
+        );
+        assert_eq!(
+            overall_exit_code(&[ok("a"), failed("b")], ErrorMode::Continue),
+            EXIT_HOST_FAILED
+        );
+        assert_eq!(
+            overall_exit_code(&[failed("a"), unreachable("b")], ErrorMode::FailFast),
+            EXIT_HOST_UNREACHABLE
+        );
+        assert_eq!(
+            overall_exit_code(&[failed("a"), unreachable("b")], ErrorMode::IgnoreErrors),
+            0
+        );
+    }
+
+    #[test]
+    fn test_skipped_is_not_a_failure() {
+        let skipped = HostExecResult::skipped("c");
+        assert!(!skipped.is_failure());
+        assert_eq!(overall_exit_code(&[skipped], ErrorMode::FailFast), 0);
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let value = serde_json::to_value(unreachable("db-1")).unwrap();
+        assert_eq!(value["host"], "db-1");
+        assert_eq!(value["status"], "unreachable");
+        assert!(value["exit_code"].is_null());
+        assert_eq!(value["error"], "Connection refused");
+        assert_eq!(value["duration_ms"], 10);
+
+        let value = serde_json::to_value(failed("web-1")).unwrap();
+        assert_eq!(value["status"], "failed");
+        assert_eq!(value["exit_code"], 3);
+        assert_eq!(value["stderr"], "boom\n");
+    }
+
+    #[test]
+    fn test_format_summary_counts() {
+        let summary = format_summary(&[
+            ok("web-1"),
+            failed("web-2"),
+            unreachable("web-3"),
+            HostExecResult::skipped("web-4"),
+        ]);
+        assert!(summary.contains("web-1"));
+        assert!(summary.contains("1.2s"));
+        assert!(summary.contains("1 ok, 1 failed, 1 unreachable, 1 skipped"));
+    }
+
+    #[test]
+    fn test_format_host_block() {
+        let block = format_host_block(&failed("web-2"));
+        assert!(block.contains("── web-2 ──"));
+        assert!(block.contains("boom"));
+        assert!(block.contains("(exit code: 3)"));
+
+        let block = format_host_block(&unreachable("web-3"));
+        assert!(block.contains("Error: Connection refused"));
+
+        let block = format_host_block(&HostExecResult::skipped("web-4"));
+        assert!(block.contains("── web-4 ── skipped"));
+        assert_eq!(block.lines().count(), 1);
+    }
+}
@@ -0,0 +1,152 @@
+use std::io::Write;
+
+use crate::config::model::Settings;
+
+/// Splits a byte stream into complete lines, holding back a trailing partial
+/// line until the rest of it arrives.
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Append `data` and return every line it completed, without the
+    /// trailing `\n` (or `\r\n`).
+    pub fn push(&mut self, data: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(data);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let mut line: Vec<u8> = self.pending.drain(..=pos).collect();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            lines.push(String::from_utf8_lossy(&line).into_owned());
+        }
+        lines
+    }
+
+    /// Return the unterminated remainder, if any (called once the stream ends).
+    pub fn finish(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let rest = std::mem::take(&mut self.pending);
+        Some(String::from_utf8_lossy(&rest).into_owned())
+    }
+}
+
+/// Live, line-prefixed output for one host during streaming multi-host exec.
+///
+/// Remote stdout lines go to local stdout as `name │ line`; remote stderr
+/// lines go to local stderr as `name ! line` in red. Each line is written
+/// with a single locked write so lines from different hosts never interleave.
+pub struct LiveOutput {
+    prefix: String,
+    stdout: LineBuffer,
+    stderr: LineBuffer,
+}
+
+impl LiveOutput {
+    /// `width` pads the name so prefixes line up across hosts.
+    pub fn new(name: &str, env: &str, settings: &Settings, width: usize) -> Self {
+        Self {
+            prefix: format_prefix(name, env, settings, width),
+            stdout: LineBuffer::default(),
+            stderr: LineBuffer::default(),
+        }
+    }
+
+    pub fn stdout(&mut self, data: &[u8]) {
+        for line in self.stdout.push(data) {
+            self.emit_stdout(&line);
+        }
+    }
+
+    pub fn stderr(&mut self, data: &[u8]) {
+        for line in self.stderr.push(data) {
+            self.emit_stderr(&line);
+        }
+    }
+
+    /// Report a connection error in the host's stderr stream.
+    pub fn error(&mut self, message: &str) {
+        self.emit_stderr(&format!("Error: {message}"));
+    }
+
+    /// Flush partial lines left when the channel closed.
+    pub fn finish(&mut self) {
+        if let Some(line) = self.stdout.finish() {
+            self.emit_stdout(&line);
+        }
+        if let Some(line) = self.stderr.finish() {
+            self.emit_stderr(&line);
+        }
+    }
+
+    fn emit_stdout(&self, line: &str) {
+        let mut out = std::io::stdout().lock();
+        let _ = writeln!(out, "{} │ {line}", self.prefix);
+        let _ = out.flush();
+    }
+
+    fn emit_stderr(&self, line: &str) {
+        let mut err = std::io::stderr().lock();
+        let _ = writeln!(err, "{} \x1b[31m! {line}\x1b[0m", self.prefix);
+        let _ = err.flush();
+    }
+}
+
+/// Bookmark name padded to `width` and colored with the environment's
+/// `env_colors` background (as a foreground color), bold and uncolored for
+/// environments without one.
+pub fn format_prefix(name: &str, env: &str, settings: &Settings, width: usize) -> String {
+    let color = settings
+        .env_colors
+        .get(env)
+        .and_then(|c| super::terminal_theme::parse_hex_rgb(&c.bg));
+    match color {
+        Some((r, g, b)) => format!("\x1b[1;38;2;{r};{g};{b}m{name:<width$}\x1b[0m"),
+        None => format!("\x1b[1m{name:<width$}\x1b[0m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_buffer_holds_partial_lines() {
+        let mut buf = LineBuffer::default();
+        assert!(buf.push(b"hel").is_empty());
+        assert_eq!(buf.push(b"lo\nwor"), vec!["hello"]);
+        assert_eq!(buf.push(b"ld\n\nnext"), vec!["world", ""]);
+        assert_eq!(buf.finish(), Some("next".to_string()));
+        assert_eq!(buf.finish(), None);
+    }
+
+    #[test]
+    fn test_line_buffer_strips_crlf() {
+        let mut buf = LineBuffer::default();
+        assert_eq!(buf.push(b"a\r\nb\r\n"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_line_buffer_split_utf8() {
+        // "é" is 0xC3 0xA9; splitting it across chunks must not corrupt it
+        let mut buf = LineBuffer::default();
+        assert!(buf.push(&[b'c', b'a', b'f', 0xC3]).is_empty());
+        assert_eq!(buf.push(&[0xA9, b'\n']), vec!["café"]);
+    }
+
+    #[test]
+    fn test_format_prefix_uses_env_color() {
+        let settings = Settings::default();
+        // Default production bg is #CC0000
+        let prefix = format_prefix("web-1", "production", &settings, 8);
+        assert_eq!(prefix, "\x1b[1;38;2;204;0;0mweb-1   \x1b[0m");
+
+        let prefix = format_prefix("web-1", "custom", &settings, 5);
+        assert_eq!(prefix, "\x1b[1mweb-1\x1b[0m");
+    }
+}
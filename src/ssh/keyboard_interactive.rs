@@ -0,0 +1,158 @@
+use anyhow::Result;
+use russh::client::KeyboardInteractiveAuthResponse;
+use zeroize::Zeroizing;
+
+use super::client::SshoreHandler;
+
+/// Upper bound on info-request rounds, so a misbehaving server can't loop forever.
+const MAX_ROUNDS: usize = 10;
+
+/// Outcome of a keyboard-interactive attempt.
+pub struct KbdInteractiveOutcome {
+    /// Whether the server accepted the responses.
+    pub success: bool,
+    /// Password typed by the user at a password-like prompt, so the caller
+    /// can offer to save it to the keychain after a successful login.
+    pub typed_password: Option<Zeroizing<String>>,
+}
+
+/// Whether a server prompt asks for the account password (as opposed to an
+/// OTP, a Duo passcode or a password change). Only such prompts are answered
+/// from the keychain.
+pub fn is_password_prompt(prompt: &str) -> bool {
+    let p = prompt.to_ascii_lowercase();
+    // OpenSSH prefixes prompts with "(user@host) "; don't match on the host name
+    let p = p.rsplit_once(')').map_or(p.as_str(), |(_, rest)| rest);
+    if !p.contains("password") {
+        return false;
+    }
+    const NOT_LOGIN_PASSWORD: &[&str] = &[
+        "one-time", "otp", "token", "code", "new ", "retype", "confirm", "again",
+    ];
+    !NOT_LOGIN_PASSWORD.iter().any(|w| p.contains(w))
+}
+
+/// Run keyboard-interactive authentication (RFC 4256), as used by PAM-based
+/// 2FA such as Duo or Google Authenticator.
+///
+/// Each server prompt is shown on stderr and echoes input only when the server
+/// allows it. A password-like prompt is answered once from `stored_password`
+/// when available. In `non_interactive` mode there is no terminal to ask on,
+/// so any other prompt ends the attempt unsuccessfully and the caller moves
+/// on to the next method (the keychain password).
+pub async fn authenticate(
+    session: &mut russh::client::Handle<SshoreHandler>,
+    user: &str,
+    stored_password: Option<&str>,
+    non_interactive: bool,
+) -> Result<KbdInteractiveOutcome> {
+    let mut outcome = KbdInteractiveOutcome {
+        success: false,
+        typed_password: None,
+    };
+    let mut stored_password = stored_password;
+
+    let mut response = match session
+        .authenticate_keyboard_interactive_start(user, None)
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::debug!(error = %e, "keyboard-interactive start error");
+            return Ok(outcome);
+        }
+    };
+
+    for round in 0..MAX_ROUNDS {
+        let (name, instructions, prompts) = match response {
+            KeyboardInteractiveAuthResponse::Success => {
+                tracing::debug!(round, "keyboard-interactive auth succeeded");
+                outcome.success = true;
+                return Ok(outcome);
+            }
+            KeyboardInteractiveAuthResponse::Failure { .. } => {
+                tracing::debug!(round, "keyboard-interactive auth rejected");
+                return Ok(outcome);
+            }
+            KeyboardInteractiveAuthResponse::InfoRequest {
+                name,
+                instructions,
+                prompts,
+            } => (name, instructions, prompts),
+        };
+        tracing::debug!(
+            round,
+            prompt_count = prompts.len(),
+            "keyboard-interactive info request"
+        );
+
+        let mut shown_header = false;
+        let mut responses = Vec::with_capacity(prompts.len());
+        for prompt in &prompts {
+            if !prompt.echo
+                && is_password_prompt(&prompt.prompt)
+                && let Some(stored) = stored_password.take()
+            {
+                tracing::debug!("answering keyboard-interactive prompt from keychain");
+                responses.push(stored.to_string());
+                continue;
+            }
+
+            if non_interactive {
+                tracing::debug!(
+                    prompt = prompt.prompt.trim(),
+                    "keyboard-interactive prompt can't be answered in non-interactive mode"
+                );
+                return Ok(outcome);
+            }
+
+            if !shown_header {
+                for line in [name.trim(), instructions.trim()] {
+                    if !line.is_empty() {
+                        eprintln!("{line}");
+                    }
+                }
+                shown_header = true;
+            }
+
+            let answer = super::read_prompt(&prompt.prompt, prompt.echo)?;
+            if !prompt.echo && is_password_prompt(&prompt.prompt) {
+                outcome.typed_password = Some(answer.clone());
+            }
+            responses.push(answer.to_string());
+        }
+
+        response = session
+            .authenticate_keyboard_interactive_respond(responses)
+            .await?;
+    }
+
+    tracing::debug!("keyboard-interactive gave up after {MAX_ROUNDS} rounds");
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_password_prompt_plain() {
+        assert!(is_password_prompt("Password: "));
+        assert!(is_password_prompt("(alice@codeserver) Password:"));
+    }
+
+    #[test]
+    fn test_is_password_prompt_rejects_otp() {
+        assert!(!is_password_prompt("Verification code: "));
+        assert!(!is_password_prompt("Passcode or option (1-3): "));
+        assert!(!is_password_prompt(
+            "One-time password (OATH) for `alice': "
+        ));
+    }
+
+    #[test]
+    fn test_is_password_prompt_rejects_password_change() {
+        assert!(!is_password_prompt("New password: "));
+        assert!(!is_password_prompt("Retype new password: "));
+    }
+}
@@ -1,10 +1,10 @@
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use base64::Engine;
 use hmac::{Hmac, Mac};
-use russh::keys::PublicKey;
+use russh::keys::{Certificate, HashAlg, PublicKey};
 use sha2::{Digest, Sha256};
 
 /// Result of checking a server's host key against known_hosts.
@@ -29,18 +29,37 @@ pub enum HostKeyStatus {
     },
 }
 
+/// Result of checking a server's host certificate against `@cert-authority` entries.
+#[derive(Debug)]
+pub enum HostCertStatus {
+    /// Signed by a trusted CA, currently valid and issued for this host.
+    Trusted,
+    /// No `@cert-authority` entry for this host lists the signing CA.
+    UntrustedCa,
+    /// Signed by a trusted CA but not acceptable for this connection.
+    Invalid { reason: String },
+    /// The signing CA key or the certified host key is marked as revoked.
+    Revoked {
+        fingerprint: String,
+        known_hosts_line: usize,
+    },
+}
+
 /// Path to the user's known_hosts file.
-fn known_hosts_path() -> PathBuf {
+pub fn default_path() -> PathBuf {
     dirs::home_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join(".ssh")
         .join("known_hosts")
 }
 
-/// Check a server's host key against ~/.ssh/known_hosts.
-pub fn check_host_key(hostname: &str, port: u16, server_key: &PublicKey) -> Result<HostKeyStatus> {
-    let known_hosts_file = known_hosts_path();
-
+/// Check a server's host key against a known_hosts file.
+pub fn check_host_key(
+    known_hosts_file: &Path,
+    hostname: &str,
+    port: u16,
+    server_key: &PublicKey,
+) -> Result<HostKeyStatus> {
     if !known_hosts_file.exists() {
         let fingerprint = format_fingerprint(server_key);
         let key_type = format_key_type(server_key);
@@ -51,7 +70,7 @@ pub fn check_host_key(hostname: &str, port: u16, server_key: &PublicKey) -> Resu
     }
 
     let content =
-        std::fs::read_to_string(&known_hosts_file).context("Failed to read known_hosts")?;
+        std::fs::read_to_string(known_hosts_file).context("Failed to read known_hosts")?;
 
     let server_key_data = server_key.to_bytes().unwrap_or_default();
     let fingerprint = format_fingerprint(server_key);
@@ -137,10 +156,153 @@ fn check_host_key_in_content(
     }
 }
 
-/// Append a new host key entry to ~/.ssh/known_hosts.
-pub fn add_host_key(hostname: &str, port: u16, server_key: &PublicKey) -> Result<()> {
-    let known_hosts_file = known_hosts_path();
+/// Check a server's host certificate against the `@cert-authority` and
+/// `@revoked` entries in a known_hosts file.
+pub fn check_host_certificate(
+    known_hosts_file: &Path,
+    hostname: &str,
+    port: u16,
+    cert: &Certificate,
+) -> Result<HostCertStatus> {
+    if !known_hosts_file.exists() {
+        return Ok(HostCertStatus::UntrustedCa);
+    }
+
+    let content =
+        std::fs::read_to_string(known_hosts_file).context("Failed to read known_hosts")?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    Ok(check_host_certificate_in_content(
+        hostname, port, cert, now, &content,
+    ))
+}
 
+/// Core host certificate logic, separated from filesystem access and the clock
+/// for testability.
+///
+/// Like `check_host_key_in_content`, scans all entries first: an `@revoked`
+/// entry for either the CA key or the certified key wins over any trust.
+/// Only then is the certificate itself checked — host type, validity window
+/// at `now`, CA signature, and a principal matching the host name.
+fn check_host_certificate_in_content(
+    hostname: &str,
+    port: u16,
+    cert: &Certificate,
+    now: u64,
+    content: &str,
+) -> HostCertStatus {
+    let host_pattern = if port == 22 {
+        hostname.to_string()
+    } else {
+        format!("[{}]:{}", hostname, port)
+    };
+
+    let ca_key = PublicKey::from(cert.signature_key().clone());
+    let host_key = PublicKey::from(cert.public_key().clone());
+    let ca_key_data = ca_key.to_bytes().unwrap_or_default();
+    let host_key_data = host_key.to_bytes().unwrap_or_default();
+
+    let mut ca_trusted = false;
+
+    for (line_num, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(entry) = parse_known_hosts_line(line)
+            && entry.is_revoked
+            && entry.matches_host(&host_pattern)
+        {
+            let revoked_key = if entry.key_matches(&ca_key_data) {
+                Some(&ca_key)
+            } else if entry.key_matches(&host_key_data) {
+                Some(&host_key)
+            } else {
+                None
+            };
+            if let Some(key) = revoked_key {
+                return HostCertStatus::Revoked {
+                    fingerprint: format_fingerprint(key),
+                    known_hosts_line: line_num + 1,
+                };
+            }
+        }
+
+        if let Some(entry) = parse_cert_authority_line(line)
+            && entry.matches_host(&host_pattern)
+            && entry.key_matches(&ca_key_data)
+        {
+            ca_trusted = true;
+        }
+    }
+
+    if !ca_trusted {
+        return HostCertStatus::UntrustedCa;
+    }
+
+    let invalid = |reason: &str| HostCertStatus::Invalid {
+        reason: reason.to_string(),
+    };
+
+    if !cert.cert_type().is_host() {
+        return invalid("certificate is not a host certificate");
+    }
+    if now < cert.valid_after() {
+        return invalid("certificate is not yet valid");
+    }
+    if now >= cert.valid_before() {
+        return invalid("certificate has expired");
+    }
+    let ca_fingerprint = cert.signature_key().fingerprint(HashAlg::Sha256);
+    if cert.validate_at(now, [&ca_fingerprint]).is_err() {
+        return invalid("certificate signature does not verify against the CA key");
+    }
+    // OpenSSH requires host certificates to name the host explicitly
+    if !cert
+        .valid_principals()
+        .iter()
+        .any(|p| wildcard_match(p, hostname))
+    {
+        return HostCertStatus::Invalid {
+            reason: format!("certificate is not valid for host '{hostname}'"),
+        };
+    }
+
+    HostCertStatus::Trusted
+}
+
+/// Whether any `@cert-authority` entry in a known_hosts file covers this host.
+/// Used to decide whether to ask the server for its host certificate.
+pub fn has_cert_authority(known_hosts_file: &Path, hostname: &str, port: u16) -> bool {
+    std::fs::read_to_string(known_hosts_file)
+        .map(|content| has_cert_authority_in_content(hostname, port, &content))
+        .unwrap_or(false)
+}
+
+fn has_cert_authority_in_content(hostname: &str, port: u16, content: &str) -> bool {
+    let host_pattern = if port == 22 {
+        hostname.to_string()
+    } else {
+        format!("[{}]:{}", hostname, port)
+    };
+
+    content
+        .lines()
+        .filter_map(|line| parse_cert_authority_line(line.trim()))
+        .any(|entry| entry.matches_host(&host_pattern))
+}
+
+/// Append a new host key entry to a known_hosts file.
+pub fn add_host_key(
+    known_hosts_file: &Path,
+    hostname: &str,
+    port: u16,
+    server_key: &PublicKey,
+) -> Result<()> {
     // Ensure ~/.ssh directory exists
     if let Some(parent) = known_hosts_file.parent() {
         std::fs::create_dir_all(parent).context("Failed to create ~/.ssh directory")?;
@@ -158,7 +320,7 @@ pub fn add_host_key(hostname: &str, port: u16, server_key: &PublicKey) -> Result
     let mut file = std::fs::OpenOptions::new()
         .create(true)
         .append(true)
-        .open(&known_hosts_file)
+        .open(known_hosts_file)
         .context("Failed to open known_hosts for writing")?;
 
     writeln!(file, "{}", entry)?;
@@ -167,7 +329,7 @@ pub fn add_host_key(hostname: &str, port: u16, server_key: &PublicKey) -> Result
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        std::fs::set_permissions(&known_hosts_file, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::set_permissions(known_hosts_file, std::fs::Permissions::from_mode(0o600))?;
     }
 
     Ok(())
@@ -188,17 +350,27 @@ struct KnownHostEntry {
 impl KnownHostEntry {
     /// Check if this entry matches the given host pattern.
     /// `host_pattern` is "hostname" for port 22, or "[hostname]:port" for other ports.
+    ///
+    /// Patterns may use `*` and `?` wildcards; a matching `!pattern` excludes
+    /// the host even if another pattern on the line matches.
     fn matches_host(&self, host_pattern: &str) -> bool {
+        let mut matched = false;
         for pattern in &self.host_patterns {
-            if pattern == host_pattern {
-                return true;
+            if let Some(negated) = pattern.strip_prefix('!') {
+                if wildcard_match(negated, host_pattern) {
+                    return false;
+                }
+                continue;
             }
             // Hashed hostnames: |1|base64(salt)|base64(HMAC-SHA1(salt, hostname))
-            if check_hashed_host(pattern, host_pattern) == Some(true) {
-                return true;
+            if pattern == host_pattern
+                || wildcard_match(pattern, host_pattern)
+                || check_hashed_host(pattern, host_pattern) == Some(true)
+            {
+                matched = true;
             }
         }
-        false
+        matched
     }
 
     /// Check if the stored key matches the server's key data.
@@ -212,6 +384,34 @@ impl KnownHostEntry {
     }
 }
 
+/// Match `text` against an OpenSSH-style pattern where `*` matches any run of
+/// characters and `?` matches exactly one.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen and the text index it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            // Let the last `*` swallow one more character and retry
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 /// Check if a hashed known_hosts pattern matches the given hostname.
 ///
 /// OpenSSH hashed format: `|1|base64(salt)|base64(HMAC-SHA1(salt, hostname))`
@@ -280,6 +480,16 @@ fn parse_known_hosts_line(line: &str) -> Option<KnownHostEntry> {
     })
 }
 
+/// Parse a `@cert-authority` line. The returned entry's key is the CA key
+/// trusted to sign host certificates for the line's host patterns.
+fn parse_cert_authority_line(line: &str) -> Option<KnownHostEntry> {
+    let (marker, rest) = line.split_once(char::is_whitespace)?;
+    if !marker.eq_ignore_ascii_case("@cert-authority") {
+        return None;
+    }
+    parse_known_hosts_line(rest.trim_start())
+}
+
 /// Format a public key's fingerprint as SHA256 hash.
 fn format_fingerprint(key: &PublicKey) -> String {
     let key_bytes = key.to_bytes().unwrap_or_default();
@@ -388,8 +598,8 @@ mod tests {
     }
 
     #[test]
-    fn test_known_hosts_path() {
-        let path = known_hosts_path();
+    fn test_default_path() {
+        let path = default_path();
         assert!(path.ends_with(".ssh/known_hosts"));
     }
 
@@ -403,7 +613,7 @@ mod tests {
         let hash = mac.finalize().into_bytes();
 
         let salt_b64 = base64::engine::general_purpose::STANDARD.encode(salt);
-        let hash_b64 = base64::engine::general_purpose::STANDARD.encode(&hash);
+        let hash_b64 = base64::engine::general_purpose::STANDARD.encode(hash);
         let hashed_pattern = format!("|1|{salt_b64}|{hash_b64}");
 
         let entry = KnownHostEntry {
@@ -427,7 +637,7 @@ mod tests {
         let hash = mac.finalize().into_bytes();
 
         let salt_b64 = base64::engine::general_purpose::STANDARD.encode(salt);
-        let hash_b64 = base64::engine::general_purpose::STANDARD.encode(&hash);
+        let hash_b64 = base64::engine::general_purpose::STANDARD.encode(hash);
         let hashed_pattern = format!("|1|{salt_b64}|{hash_b64}");
 
         let entry = KnownHostEntry {
@@ -752,4 +962,180 @@ mod tests {
             "expected Revoked even when a non-revoked entry also matches, got: {status:?}"
         );
     }
+
+    // --- Host certificates (@cert-authority) ---
+
+    /// ed25519 CA that signed `HOST_CERT` and `USER_CERT`.
+    const CA_PUBKEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAICPwDp6Qsu8i9mIm/IaMMflRei/piTEQjEwOAtRpffkX";
+
+    /// The host key certified by `HOST_CERT`.
+    const HOST_PUBKEY: &str =
+        "AAAAC3NzaC1lZDI1NTE5AAAAIO0LGrW2zC2Vu2MbJjnkrVF4bC1o8Wu3ESh/kWE+AfSV";
+
+    /// Host certificate: key ID "web-host", principals web1.example.com and
+    /// 10.0.0.5, valid 2026-01-01 to 2026-02-01 (UTC).
+    const HOST_CERT: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIFl1a3x8Km2FFegw4nzI8yXFa3lJKqAZxKvvGA8qxLDzAAAAIO0LGrW2zC2Vu2MbJjnkrVF4bC1o8Wu3ESh/kWE+AfSVAAAAAAAAAAAAAAACAAAACHdlYi1ob3N0AAAAIAAAABB3ZWIxLmV4YW1wbGUuY29tAAAACDEwLjAuMC41AAAAAGlVuQAAAAAAaX6XgAAAAAAAAAAAAAAAAAAAADMAAAALc3NoLWVkMjU1MTkAAAAgI/AOnpCy7yL2Yib8howx+VF6L+mJMRCMTA4C1Gl9+RcAAABTAAAAC3NzaC1lZDI1NTE5AAAAQNeROzfSfprubo8y3ThxLsjh2zjpGGHx4TxseE65dB3fdKEzh8V0p1I+bneMD7zD32Omd7uONIFcn+QDoWX3BQg= host";
+
+    /// Same key and validity as `HOST_CERT`, but issued as a user certificate.
+    const USER_CERT: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIJd57LipOt2OSgOc1tJk0NMxptcxsm4kMqUf8uKu3Om3AAAAIO0LGrW2zC2Vu2MbJjnkrVF4bC1o8Wu3ESh/kWE+AfSVAAAAAAAAAAAAAAABAAAACXVzZXItY2VydAAAABQAAAAQd2ViMS5leGFtcGxlLmNvbQAAAABpVbkAAAAAAGl+l4AAAAAAAAAAggAAABVwZXJtaXQtWDExLWZvcndhcmRpbmcAAAAAAAAAF3Blcm1pdC1hZ2VudC1mb3J3YXJkaW5nAAAAAAAAABZwZXJtaXQtcG9ydC1mb3J3YXJkaW5nAAAAAAAAAApwZXJtaXQtcHR5AAAAAAAAAA5wZXJtaXQtdXNlci1yYwAAAAAAAAAAAAAAMwAAAAtzc2gtZWQyNTUxOQAAACAj8A6ekLLvIvZiJvyGjDH5UXov6YkxEIxMDgLUaX35FwAAAFMAAAALc3NoLWVkMjU1MTkAAABAqG8FBIat5nQbFFvTVlrgbseuAvdZGAXk/2q7TJAWwAvUAT/1PKSthAFXEW/ktAyrwWMiW0xmHyINAh4FisfNCQ== host";
+
+    /// 2026-01-15 00:00 UTC, inside the test certificates' validity window.
+    const MID_JANUARY_2026: u64 = 1_768_435_200;
+
+    fn check_cert(hostname: &str, cert: &str, now: u64, content: &str) -> HostCertStatus {
+        let cert = Certificate::from_openssh(cert).unwrap();
+        check_host_certificate_in_content(hostname, 22, &cert, now, content)
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.example.com", "web1.example.com"));
+        assert!(wildcard_match("web?.example.com", "web1.example.com"));
+        assert!(wildcard_match("*", "anything"));
+        assert!(!wildcard_match("*.example.com", "example.com"));
+        assert!(!wildcard_match("web?.example.com", "web10.example.com"));
+    }
+
+    #[test]
+    fn test_host_pattern_negation() {
+        let entry = KnownHostEntry {
+            host_patterns: vec!["*.example.com".into(), "!db.example.com".into()],
+            _key_type: "ssh-ed25519".into(),
+            key_base64: "AAAA".into(),
+            is_revoked: false,
+        };
+        assert!(entry.matches_host("web1.example.com"));
+        assert!(!entry.matches_host("db.example.com"));
+    }
+
+    #[test]
+    fn test_parse_cert_authority_line() {
+        let entry =
+            parse_cert_authority_line("@cert-authority *.example.com ssh-ed25519 AAAA ca").unwrap();
+        assert_eq!(entry.host_patterns, vec!["*.example.com"]);
+        assert!(!entry.is_revoked);
+        assert!(parse_cert_authority_line("example.com ssh-ed25519 AAAA").is_none());
+        assert!(parse_cert_authority_line("@revoked example.com ssh-ed25519 AAAA").is_none());
+    }
+
+    #[test]
+    fn test_has_cert_authority_in_content() {
+        let content = format!(
+            "web9.example.com ssh-ed25519 {HOST_PUBKEY}\n@cert-authority *.example.com,!db.example.com ssh-ed25519 {CA_PUBKEY}"
+        );
+        assert!(has_cert_authority_in_content(
+            "web1.example.com",
+            22,
+            &content
+        ));
+        assert!(!has_cert_authority_in_content(
+            "db.example.com",
+            22,
+            &content
+        ));
+        assert!(!has_cert_authority_in_content(
+            "web1.other.org",
+            22,
+            &content
+        ));
+        // Non-default ports are matched as [host]:port, like plain entries
+        assert!(!has_cert_authority_in_content(
+            "web1.example.com",
+            2222,
+            &content
+        ));
+        assert!(!has_cert_authority_in_content("web9.example.com", 2222, ""));
+    }
+
+    #[test]
+    fn test_check_host_certificate_trusted() {
+        let content = format!("@cert-authority *.example.com ssh-ed25519 {CA_PUBKEY}");
+        let status = check_cert("web1.example.com", HOST_CERT, MID_JANUARY_2026, &content);
+        assert!(
+            matches!(status, HostCertStatus::Trusted),
+            "expected Trusted, got: {status:?}"
+        );
+    }
+
+    #[test]
+    fn test_check_host_certificate_untrusted_ca() {
+        // No CA lines at all, and a CA line for a different domain
+        let status = check_cert("web1.example.com", HOST_CERT, MID_JANUARY_2026, "");
+        assert!(matches!(status, HostCertStatus::UntrustedCa));
+
+        let content = format!("@cert-authority *.other.org ssh-ed25519 {CA_PUBKEY}");
+        let status = check_cert("web1.example.com", HOST_CERT, MID_JANUARY_2026, &content);
+        assert!(matches!(status, HostCertStatus::UntrustedCa));
+
+        // A plain (non-CA) entry with the CA key does not make it a CA
+        let content = format!("web1.example.com ssh-ed25519 {CA_PUBKEY}");
+        let status = check_cert("web1.example.com", HOST_CERT, MID_JANUARY_2026, &content);
+        assert!(matches!(status, HostCertStatus::UntrustedCa));
+    }
+
+    #[test]
+    fn test_check_host_certificate_wrong_principal() {
+        let content = format!("@cert-authority *.example.com ssh-ed25519 {CA_PUBKEY}");
+        let status = check_cert("web2.example.com", HOST_CERT, MID_JANUARY_2026, &content);
+        assert!(
+            matches!(&status, HostCertStatus::Invalid { reason } if reason.contains("web2.example.com")),
+            "expected Invalid for wrong principal, got: {status:?}"
+        );
+    }
+
+    #[test]
+    fn test_check_host_certificate_outside_validity() {
+        let content = format!("@cert-authority *.example.com ssh-ed25519 {CA_PUBKEY}");
+        // 2026-03-01 and 2025-12-01
+        let expired = check_cert("web1.example.com", HOST_CERT, 1_772_323_200, &content);
+        assert!(
+            matches!(&expired, HostCertStatus::Invalid { reason } if reason.contains("expired"))
+        );
+        let early = check_cert("web1.example.com", HOST_CERT, 1_764_547_200, &content);
+        assert!(
+            matches!(&early, HostCertStatus::Invalid { reason } if reason.contains("not yet valid"))
+        );
+    }
+
+    #[test]
+    fn test_check_host_certificate_user_cert_rejected() {
+        let content = format!("@cert-authority *.example.com ssh-ed25519 {CA_PUBKEY}");
+        let status = check_cert("web1.example.com", USER_CERT, MID_JANUARY_2026, &content);
+        assert!(
+            matches!(&status, HostCertStatus::Invalid { reason } if reason.contains("not a host certificate"))
+        );
+    }
+
+    #[test]
+    fn test_check_host_certificate_revoked_ca() {
+        let content = format!(
+            "@cert-authority *.example.com ssh-ed25519 {CA_PUBKEY}\n@revoked * ssh-ed25519 {CA_PUBKEY}"
+        );
+        let status = check_cert("web1.example.com", HOST_CERT, MID_JANUARY_2026, &content);
+        assert!(
+            matches!(
+                status,
+                HostCertStatus::Revoked {
+                    known_hosts_line: 2,
+                    ..
+                }
+            ),
+            "expected Revoked for a revoked CA, got: {status:?}"
+        );
+    }
+
+    #[test]
+    fn test_check_host_certificate_revoked_host_key() {
+        let content = format!(
+            "@revoked web1.example.com ssh-ed25519 {HOST_PUBKEY}\n@cert-authority *.example.com ssh-ed25519 {CA_PUBKEY}"
+        );
+        let status = check_cert("web1.example.com", HOST_CERT, MID_JANUARY_2026, &content);
+        assert!(matches!(
+            status,
+            HostCertStatus::Revoked {
+                known_hosts_line: 1,
+                ..
+            }
+        ));
+    }
 }
@@ -1,8 +1,18 @@
+pub mod agent;
+pub mod certificate;
 pub mod client;
+pub mod exec_guard;
+pub mod exec_report;
+pub mod exec_stream;
+pub mod keyboard_interactive;
 pub mod known_hosts;
 pub mod mux;
+pub mod options;
 pub mod password;
+pub mod rollout;
+pub mod script;
 pub mod snippet;
+pub mod socks;
 pub mod stdin_reader;
 pub mod terminal_theme;
 pub mod tunnel;
@@ -10,6 +20,7 @@ pub mod tunnel;
 use std::io::Write;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use anyhow::{Context, Result, bail};
 use chrono::Utc;
@@ -22,8 +33,13 @@ use crate::config;
 use crate::config::model::{AppConfig, Bookmark};
 use crate::keychain;
 
+use self::certificate::KeyCertificate;
 use self::client::{HostKeyCheckMode, SshoreHandler};
+use self::exec_report::{ErrorMode, ExecOutput, HostExecResult};
+use self::exec_stream::LiveOutput;
+use self::options::ConnectionOptions;
 use self::password::{PasswordDetector, PromptKind};
+use self::rollout::RolloutPlan;
 
 /// Result of executing a single command on a remote host.
 pub struct ExecResult {
@@ -37,7 +53,7 @@ const DEFAULT_KEY_NAMES: &[&str] = &["id_ed25519", "id_rsa", "id_ecdsa"];
 
 /// Default SSH connection timeout in seconds.
 /// Can be overridden per-settings or per-bookmark.
-const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 15;
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 15;
 
 /// SSH keepalive interval for interactive/SFTP sessions (seconds).
 /// Sends a keepalive packet if no data is exchanged within this period.
@@ -72,7 +88,7 @@ pub fn print_production_banner(
     profiles: &[crate::config::model::Profile],
     context: &str,
 ) {
-    if bookmark.env.eq_ignore_ascii_case("production") {
+    if exec_guard::is_production(&bookmark.env) {
         let user: String = bookmark
             .effective_user(settings, profiles)
             .chars()
@@ -289,6 +305,9 @@ pub async fn connect_adhoc(
         connect_timeout_secs: None,
         ssh_options: std::collections::BTreeMap::new(),
         profile: None,
+        use_agent: None,
+        certificate_file: None,
+        tunnels: vec![],
     };
 
     // Temporarily add the bookmark for connection, then remove it
@@ -305,22 +324,14 @@ pub async fn connect_adhoc(
     result
 }
 
-/// Build the shared russh client config used for interactive and proxy sessions.
-fn build_ssh_config() -> Arc<russh::client::Config> {
-    let preferred = {
-        use russh::cipher;
-        let mut p = russh::Preferred::DEFAULT;
-        p.cipher = std::borrow::Cow::Borrowed(&[
-            cipher::AES_256_GCM,
-            cipher::AES_128_GCM,
-            cipher::CHACHA20_POLY1305,
-            cipher::AES_256_CTR,
-            cipher::AES_192_CTR,
-            cipher::AES_128_CTR,
-        ]);
-        p
-    };
-    Arc::new(russh::client::Config {
+/// Build the shared russh client config used for interactive and proxy sessions,
+/// with the bookmark's `ssh_options` (keepalive, algorithms) applied on top and
+/// host certificates requested where known_hosts trusts a CA for the host.
+fn build_ssh_config(
+    options: &ConnectionOptions,
+    handler: &SshoreHandler,
+) -> Arc<russh::client::Config> {
+    let mut config = russh::client::Config {
         inactivity_timeout: None,
         keepalive_interval: Some(std::time::Duration::from_secs(KEEPALIVE_INTERVAL_SECS)),
         keepalive_max: KEEPALIVE_MAX,
@@ -328,9 +339,11 @@ fn build_ssh_config() -> Arc<russh::client::Config> {
         maximum_packet_size: SSH_MAX_PACKET_SIZE,
         channel_buffer_size: SSH_CHANNEL_BUFFER_SIZE,
         nodelay: true,
-        preferred,
         ..<_>::default()
-    })
+    };
+    options.apply(&mut config);
+    handler.prefer_host_certificates(&mut config);
+    Arc::new(config)
 }
 
 /// Establish an authenticated SSH session to a bookmark.
@@ -355,8 +368,15 @@ pub async fn establish_session(
     tracing::debug!(host, port, user, "establishing SSH session");
 
     // Load SSH keys (using profile-aware identity file resolution)
-    let keys = load_keys(bookmark, profiles)?;
-    tracing::debug!(key_count = keys.len(), "loaded SSH keys");
+    let mut creds = load_keys(bookmark, profiles)?;
+    creds
+        .pair_pending_with_agent(bookmark.effective_use_agent(profiles))
+        .await;
+    tracing::debug!(
+        key_count = creds.keys.len(),
+        cert_count = creds.certs.len(),
+        "loaded SSH keys"
+    );
 
     // Configurable connection timeout
     let timeout_secs = effective_timeout(bookmark, settings, profiles);
@@ -364,7 +384,15 @@ pub async fn establish_session(
     // Check for proxy jump
     let proxy_jump = bookmark.effective_proxy_jump(profiles);
 
-    let check_mode = HostKeyCheckMode::from_str_setting(&settings.host_key_checking);
+    // Per-connection behavior from the merged ssh_options
+    let options = ConnectionOptions::from_ssh_options(&bookmark.effective_ssh_options(profiles));
+    // `connect` prints these once; mux, browser and reconnect sessions only log them
+    options.log_warnings();
+
+    let check_mode = options.host_key_check_mode(HostKeyCheckMode::from_str_setting(
+        &settings.host_key_checking,
+    ));
+    let use_agent = bookmark.effective_use_agent(profiles);
 
     let mut session = if let Some(ref proxy_jump_str) = proxy_jump {
         // Connect through proxy jump host
@@ -372,10 +400,13 @@ pub async fn establish_session(
             proxy_jump_str,
             host,
             port,
-            &keys,
+            &creds,
             settings,
             timeout_secs,
             check_mode,
+            use_agent,
+            non_interactive,
+            &options,
         )
         .await
         .with_context(|| {
@@ -386,7 +417,7 @@ pub async fn establish_session(
         })?
     } else {
         // Direct connection
-        connect_direct(host, port, timeout_secs, check_mode).await?
+        connect_direct(host, port, timeout_secs, check_mode, &options).await?
     };
 
     // Authenticate
@@ -395,10 +426,12 @@ pub async fn establish_session(
         env: Some(&bookmark.env),
         has_identity_file: bookmark.effective_identity_file(profiles).is_some(),
         non_interactive,
+        use_agent,
     };
-    let authenticated = authenticate(&mut session, &user, &keys, &ctx).await?;
+    let authenticated = authenticate(&mut session, &user, &creds, &ctx).await?;
     if !authenticated {
         tracing::debug!("authentication failed");
+        creds.report_certificates();
         bail!("Authentication failed for {user}@{host}:{port}");
     }
 
@@ -412,9 +445,11 @@ async fn connect_direct(
     port: u16,
     timeout_secs: u64,
     check_mode: HostKeyCheckMode,
+    options: &ConnectionOptions,
 ) -> Result<russh::client::Handle<SshoreHandler>> {
-    let handler = SshoreHandler::for_host(host, port, check_mode);
-    let ssh_config = build_ssh_config();
+    let handler = SshoreHandler::for_host(host, port, check_mode)
+        .with_agent_forwarding(options.forward_agent);
+    let ssh_config = build_ssh_config(options, &handler);
 
     let connect_future = russh::client::connect(ssh_config, (host, port), handler);
 
@@ -439,14 +474,23 @@ async fn connect_direct(
 /// 2. Connect to the jump host and authenticate
 /// 3. Open a `direct-tcpip` channel through the jump to the target host:port
 /// 4. Use `russh::client::connect_stream` over that channel to establish SSH to the target
+///
+/// `use_agent` follows the target bookmark's setting, so an agent opt-out
+/// also applies to its jump host. `options` and `check_mode` apply to the
+/// target only — the jump host uses defaults and the global host key setting,
+/// and the agent is never forwarded to it.
+#[allow(clippy::too_many_arguments)]
 async fn connect_through_proxy(
     proxy_jump_str: &str,
     target_host: &str,
     target_port: u16,
-    keys: &[PrivateKeyWithHashAlg],
+    creds: &Credentials,
     settings: &crate::config::model::Settings,
     timeout_secs: u64,
     check_mode: HostKeyCheckMode,
+    use_agent: bool,
+    non_interactive: bool,
+    options: &ConnectionOptions,
 ) -> Result<russh::client::Handle<SshoreHandler>> {
     let jump_target = parse_proxy_jump(proxy_jump_str)
         .with_context(|| format!("Invalid proxy jump format: {proxy_jump_str}"))?;
@@ -471,9 +515,10 @@ async fn connect_through_proxy(
     );
 
     // Step 1: Connect to the jump host
+    let jump_check_mode = HostKeyCheckMode::from_str_setting(&settings.host_key_checking);
     let jump_handler =
-        SshoreHandler::for_host(&jump_target.host, jump_target.port, check_mode.clone());
-    let jump_config = build_ssh_config();
+        SshoreHandler::for_host(&jump_target.host, jump_target.port, jump_check_mode);
+    let jump_config = build_ssh_config(&ConnectionOptions::default(), &jump_handler);
 
     let mut jump_session = match tokio::time::timeout(
         std::time::Duration::from_secs(timeout_secs),
@@ -501,18 +546,19 @@ async fn connect_through_proxy(
     };
 
     // Step 2: Authenticate to the jump host
-    // Use default keys (matching native ssh -J behavior) + keychain password
+    // Use ssh-agent + default keys (matching native ssh -J behavior) + keychain password
     let jump_ctx = AuthContext {
         bookmark_name: None, // Don't use bookmark keychain for jump host
         env: None,
         has_identity_file: false, // Jump hosts use default keys; always allow password fallback
-        non_interactive: false,
+        non_interactive,
+        use_agent,
     };
     let jump_authenticated = authenticate_with_jump_keychain(
         &mut jump_session,
         &jump_user,
         &jump_target.host,
-        keys,
+        creds,
         &jump_ctx,
     )
     .await?;
@@ -538,9 +584,9 @@ async fn connect_through_proxy(
     let stream = channel.into_stream();
 
     // Step 4: Connect SSH session over the tunneled stream
-    let target_check_mode = HostKeyCheckMode::from_str_setting(&settings.host_key_checking);
-    let target_handler = SshoreHandler::for_host(target_host, target_port, target_check_mode);
-    let target_config = build_ssh_config();
+    let target_handler = SshoreHandler::for_host(target_host, target_port, check_mode)
+        .with_agent_forwarding(options.forward_agent);
+    let target_config = build_ssh_config(options, &target_handler);
 
     let target_session = match tokio::time::timeout(
         std::time::Duration::from_secs(timeout_secs),
@@ -575,38 +621,43 @@ async fn authenticate_with_jump_keychain(
     session: &mut russh::client::Handle<SshoreHandler>,
     user: &str,
     jump_host: &str,
-    keys: &[PrivateKeyWithHashAlg],
+    creds: &Credentials,
     ctx: &AuthContext<'_>,
 ) -> Result<bool> {
-    // 1. Try public key auth with each available key
-    for (i, key) in keys.iter().enumerate() {
-        tracing::debug!(key_index = i, "trying public key auth for jump host");
-        match session.authenticate_publickey(user, key.clone()).await {
-            Ok(AuthResult::Success) => {
-                tracing::debug!(key_index = i, "public key auth succeeded for jump host");
-                return Ok(true);
-            }
-            Ok(AuthResult::Failure { .. }) => {
-                tracing::debug!(key_index = i, "public key rejected for jump host");
-                continue;
-            }
-            Err(e) => {
-                tracing::debug!(key_index = i, error = %e, "public key auth error for jump host");
-                continue;
-            }
+    // 0-1. Try ssh-agent keys, then on-disk keys
+    let partial = match try_public_keys(session, user, creds, ctx).await {
+        PublicKeyOutcome::Success => {
+            tracing::debug!("public key auth succeeded for jump host");
+            return Ok(true);
         }
-    }
+        PublicKeyOutcome::PartialSuccess => true,
+        PublicKeyOutcome::Rejected => false,
+    };
 
-    if ctx.has_identity_file && !keys.is_empty() {
+    if ctx.has_identity_file && !creds.is_empty() && !partial {
         tracing::debug!(
             "identity_file configured and keys were tried for jump host, skipping password fallback"
         );
         return Ok(false);
     }
 
-    // 2. Try keychain password keyed by __jump:<host>
     let jump_keychain_name = format!("__jump:{}", jump_host);
-    if let Ok(Some(stored)) = keychain::get_password(&jump_keychain_name) {
+    let stored = keychain::get_password(&jump_keychain_name).ok().flatten();
+
+    // 2. Try keyboard-interactive (PAM prompts, 2FA/OTP)
+    let kbd =
+        keyboard_interactive::authenticate(session, user, stored.as_deref(), ctx.non_interactive)
+            .await?;
+    if kbd.success {
+        tracing::debug!("keyboard-interactive auth succeeded for jump host");
+        if let Some(password) = kbd.typed_password {
+            offer_save_jump_password(&jump_keychain_name, &password);
+        }
+        return Ok(true);
+    }
+
+    // 3. Try keychain password keyed by __jump:<host>
+    if let Some(stored) = stored {
         tracing::debug!(jump_host, "trying keychain password for jump host");
         match session.authenticate_password(user, &stored).await {
             Ok(AuthResult::Success) => {
@@ -627,7 +678,11 @@ async fn authenticate_with_jump_keychain(
         }
     }
 
-    // 3. Prompt user for password
+    // 4. Prompt user for password (skip in non-interactive mode)
+    if ctx.non_interactive {
+        tracing::debug!("non-interactive mode, skipping password prompt for jump host");
+        return Ok(false);
+    }
     tracing::debug!("prompting for password for jump host");
     let password = prompt_password(&format!("{}@{} (jump host)", user, jump_host))?;
     match session.authenticate_password(user, password.as_str()).await {
@@ -678,9 +733,13 @@ fn offer_save_jump_password(jump_keychain_name: &str, password: &str) {
 
 /// Establish an SSH session configured for tunnel keepalives.
 /// Returns the session handle and the remote forward map for -R support.
+///
+/// If `non_interactive` is true, skip prompts (keychain-only auth).
+/// Used for daemonized tunnels, which have no terminal.
 pub async fn establish_tunnel_session(
     config: &AppConfig,
     bookmark_index: usize,
+    non_interactive: bool,
 ) -> Result<(
     russh::client::Handle<SshoreHandler>,
     client::RemoteForwardMap,
@@ -695,13 +754,21 @@ pub async fn establish_tunnel_session(
 
     eprintln!("Connecting tunnel to {user}@{host}:{port}...");
 
-    let keys = load_keys(bookmark, profiles)?;
+    let mut creds = load_keys(bookmark, profiles)?;
+    creds
+        .pair_pending_with_agent(bookmark.effective_use_agent(profiles))
+        .await;
+
+    let options = ConnectionOptions::from_ssh_options(&bookmark.effective_ssh_options(profiles));
+    options.log_warnings();
 
-    let check_mode = HostKeyCheckMode::from_str_setting(&settings.host_key_checking);
+    let check_mode = options.host_key_check_mode(HostKeyCheckMode::from_str_setting(
+        &settings.host_key_checking,
+    ));
     let handler = SshoreHandler::for_host(host, port, check_mode);
     let remote_map = handler.remote_forwards.clone();
 
-    let ssh_config = russh::client::Config {
+    let mut ssh_config = russh::client::Config {
         inactivity_timeout: None, // Tunnels stay open indefinitely
         keepalive_interval: Some(std::time::Duration::from_secs(
             tunnel::TUNNEL_KEEPALIVE_INTERVAL_SECS,
@@ -713,6 +780,9 @@ pub async fn establish_tunnel_session(
         nodelay: true,
         ..<_>::default()
     };
+    // ServerAlive* options override the tunnel keepalive defaults
+    options.apply(&mut ssh_config);
+    handler.prefer_host_certificates(&mut ssh_config);
 
     let timeout_secs = effective_timeout(bookmark, settings, profiles);
     let connect_future =
@@ -732,10 +802,12 @@ pub async fn establish_tunnel_session(
         bookmark_name: Some(&bookmark.name),
         env: Some(&bookmark.env),
         has_identity_file: bookmark.effective_identity_file(profiles).is_some(),
-        non_interactive: false,
+        non_interactive,
+        use_agent: bookmark.effective_use_agent(profiles),
     };
-    let authenticated = authenticate(&mut session, &user, &keys, &ctx).await?;
+    let authenticated = authenticate(&mut session, &user, &creds, &ctx).await?;
     if !authenticated {
+        creds.report_certificates();
         bail!("Authentication failed for {user}@{host}:{port}");
     }
 
@@ -749,6 +821,11 @@ pub async fn connect(
     bookmark_index: usize,
     cfg_override: Option<&str>,
 ) -> Result<()> {
+    let bookmark = &config.bookmarks[bookmark_index];
+    let options =
+        ConnectionOptions::from_ssh_options(&bookmark.effective_ssh_options(&config.profiles));
+    options.print_warnings();
+
     let session = Arc::new(establish_session(config, bookmark_index, false).await?);
 
     // Apply terminal theming
@@ -767,13 +844,22 @@ pub async fn connect(
         .await
         .context("Failed to open SSH session channel")?;
 
-    // Request PTY with current terminal size
-    let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
-    tracing::debug!(cols, rows, term = "xterm-256color", "requesting PTY");
-    channel
-        .request_pty(true, "xterm-256color", cols as u32, rows as u32, 0, 0, &[])
-        .await
-        .context("Failed to request PTY")?;
+    let bookmark = &config.bookmarks[bookmark_index];
+
+    // Request agent forwarding (ForwardAgent) before the shell starts
+    if options.forward_agent {
+        request_agent_forwarding(&channel, bookmark).await;
+    }
+
+    // Request PTY with current terminal size (unless RequestTTY no)
+    if options.wants_pty(true) {
+        let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+        tracing::debug!(cols, rows, term = "xterm-256color", "requesting PTY");
+        channel
+            .request_pty(true, "xterm-256color", cols as u32, rows as u32, 0, 0, &[])
+            .await
+            .context("Failed to request PTY")?;
+    }
 
     // Request shell
     tracing::debug!("requesting shell");
@@ -860,6 +946,34 @@ pub async fn connect(
     Ok(())
 }
 
+/// Ask the server to forward agent connections back to us (`auth-agent-req@openssh.com`).
+/// Warns loudly on production hosts: anyone with root there can use the agent
+/// for as long as the session is open.
+async fn request_agent_forwarding(
+    channel: &russh::Channel<russh::client::Msg>,
+    bookmark: &Bookmark,
+) {
+    if !agent::is_available() {
+        eprintln!(
+            "Warning: ForwardAgent is set but no local ssh-agent is running (SSH_AUTH_SOCK)."
+        );
+        return;
+    }
+
+    if exec_guard::is_production(&bookmark.env) {
+        eprintln!(
+            "\x1b[1;37;41m PROD \x1b[0m \x1b[33mForwarding your ssh-agent to '{}'. \
+             Root on that host can use your keys while this session is open.\x1b[0m",
+            bookmark.name
+        );
+    }
+
+    tracing::debug!("requesting agent forwarding");
+    if let Err(e) = channel.agent_forward(true).await {
+        eprintln!("Warning: failed to request agent forwarding: {e}");
+    }
+}
+
 /// Connect to a session within a bookmark group.
 ///
 /// The session index is encoded as: group_idx * 10000 + session_idx.
@@ -917,6 +1031,9 @@ pub async fn connect_session(
         connect_timeout_secs,
         ssh_options,
         profile: group.profile.clone(),
+        use_agent: None,
+        certificate_file: None,
+        tunnels: vec![],
     };
 
     // Temporarily add to bookmarks for the existing connect() to work
@@ -934,12 +1051,23 @@ pub async fn connect_session(
     result
 }
 
+/// What to send to a remote command's stdin before EOF.
+#[derive(Debug, Clone)]
+pub enum ExecInput {
+    /// Fixed bytes, e.g. a `--script` body or `--stdin` input buffered for fan-out.
+    Bytes(Arc<[u8]>),
+    /// Local stdin, streamed until it closes.
+    LocalStdin,
+}
+
 /// Execute a single command on a bookmark and return the result.
 /// Does NOT allocate a PTY — runs as an exec channel.
+/// `input`, if given, is sent to the command's stdin followed by EOF.
 pub async fn exec_command(
     config: &AppConfig,
     bookmark_index: usize,
     command: &str,
+    input: Option<ExecInput>,
 ) -> Result<ExecResult> {
     let session = establish_session(config, bookmark_index, false).await?;
 
@@ -948,6 +1076,18 @@ pub async fn exec_command(
         .await
         .context("Failed to open exec channel")?;
 
+    // RequestTTY yes/force allocates a PTY for exec (e.g. for sudo prompts)
+    let bookmark = &config.bookmarks[bookmark_index];
+    let options =
+        ConnectionOptions::from_ssh_options(&bookmark.effective_ssh_options(&config.profiles));
+    if options.wants_pty(false) {
+        let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+        channel
+            .request_pty(true, "xterm-256color", cols as u32, rows as u32, 0, 0, &[])
+            .await
+            .context("Failed to request PTY")?;
+    }
+
     channel
         .exec(true, command)
         .await
@@ -957,7 +1097,8 @@ pub async fn exec_command(
     let mut stderr_buf = Vec::new();
     let mut exit_code: Option<u32> = None;
 
-    let (mut channel_rx, _channel_tx) = channel.split();
+    let (mut channel_rx, channel_tx) = channel.split();
+    let _stdin_writer = input.map(|input| spawn_stdin_writer(channel_tx, input));
 
     loop {
         match channel_rx.wait().await {
@@ -994,72 +1135,236 @@ pub async fn exec_command(
     })
 }
 
+/// Options for multi-host exec.
+#[derive(Debug, Clone)]
+pub struct MultiExecOptions {
+    /// Maximum concurrent SSH connections.
+    pub concurrency: usize,
+    pub output: ExecOutput,
+    pub error_mode: ErrorMode,
+    /// Staged rollout; `None` runs every host at once (bounded by `concurrency`).
+    pub rollout: Option<RolloutPlan>,
+    /// Print output line by line as it arrives, prefixed with the host name,
+    /// instead of one block per host when it finishes. Text output only;
+    /// streamed output is not kept in the per-host results.
+    pub stream: bool,
+    /// Sent to every host's stdin, followed by EOF (a `--script` body or
+    /// buffered `--stdin` input).
+    pub input: Option<Arc<[u8]>>,
+}
+
 /// Execute a command on multiple bookmarks concurrently.
-/// Output is printed with per-host headers, interleaved as results arrive.
+///
+/// In text mode each host's output is printed as a block as soon as it
+/// finishes (or line by line with `stream`), followed by a summary table; `jsonl` prints one object per host
+/// as it finishes and `json` prints a single array at the end. Returns the
+/// per-host results in `indices` order.
 pub async fn exec_multi(
     config: &AppConfig,
     indices: &[usize],
     command: &str,
-    concurrency: usize,
-) -> Result<()> {
+    options: &MultiExecOptions,
+) -> Result<Vec<HostExecResult>> {
+    // Set on the first failure under --fail-fast; hosts not yet started are skipped
+    let halted = Arc::new(AtomicBool::new(false));
+    // Pad streamed line prefixes to the longest name so output lines up
+    let prefix_width = indices
+        .iter()
+        .map(|&i| config.bookmarks[i].name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let results = match &options.rollout {
+        Some(plan) => {
+            exec_rollout(
+                config,
+                indices,
+                command,
+                options,
+                plan,
+                &halted,
+                prefix_width,
+            )
+            .await?
+        }
+        None => exec_batch(config, indices, command, options, &halted, prefix_width).await?,
+    };
+
+    match options.output {
+        ExecOutput::Text => print!("{}", exec_report::format_summary(&results)),
+        ExecOutput::Json => println!("{}", serde_json::to_string_pretty(&results)?),
+        ExecOutput::Jsonl => {}
+    }
+
+    Ok(results)
+}
+
+/// Run a staged rollout: batches run one after another, with a pause in
+/// between, and the rollout halts once a batch's failures cross the
+/// threshold. Hosts in batches that never ran are reported as skipped.
+/// Progress goes to stderr so it never mixes with JSON output.
+async fn exec_rollout(
+    config: &AppConfig,
+    indices: &[usize],
+    command: &str,
+    options: &MultiExecOptions,
+    plan: &RolloutPlan,
+    halted: &Arc<AtomicBool>,
+    prefix_width: usize,
+) -> Result<Vec<HostExecResult>> {
+    let batches = plan.batches(indices);
+    let mut results = Vec::with_capacity(indices.len());
+    let mut stop_reason: Option<String> = None;
+
+    for (n, batch) in batches.iter().enumerate() {
+        if stop_reason.is_some() || halted.load(Ordering::SeqCst) {
+            results.extend(
+                batch
+                    .iter()
+                    .map(|&i| HostExecResult::skipped(&config.bookmarks[i].name)),
+            );
+            continue;
+        }
+
+        if n > 0 && !plan.pause.is_zero() {
+            eprintln!(
+                "\x1b[2mPausing {}s before next batch...\x1b[0m",
+                plan.pause.as_secs()
+            );
+            tokio::time::sleep(plan.pause).await;
+        }
+
+        let names: Vec<&str> = batch
+            .iter()
+            .map(|&i| config.bookmarks[i].name.as_str())
+            .collect();
+        let label = if plan.canary && n == 0 {
+            "Canary".to_string()
+        } else {
+            format!("Batch {}/{}", n + 1, batches.len())
+        };
+        eprintln!(
+            "\x1b[1m▶ {label}\x1b[0m ({}): {}",
+            batch.len(),
+            names.join(", ")
+        );
+
+        let batch_results =
+            exec_batch(config, batch, command, options, halted, prefix_width).await?;
+        let failures = batch_results.iter().filter(|r| r.is_failure()).count();
+        results.extend(batch_results);
+
+        if plan.exceeds_threshold(failures, batch.len()) {
+            stop_reason = Some(format!(
+                "{label} had {failures} failure(s) out of {}",
+                batch.len()
+            ));
+        }
+    }
+
+    if let Some(reason) = stop_reason {
+        eprintln!("\x1b[31mRollout halted: {reason}; remaining hosts skipped.\x1b[0m");
+    }
+
+    Ok(results)
+}
+
+/// Run a command on `indices` concurrently (bounded by `options.concurrency`),
+/// printing each host's result as it finishes. Returns results in `indices` order.
+async fn exec_batch(
+    config: &AppConfig,
+    indices: &[usize],
+    command: &str,
+    options: &MultiExecOptions,
+    halted: &Arc<AtomicBool>,
+    prefix_width: usize,
+) -> Result<Vec<HostExecResult>> {
+    use std::time::Instant;
+
     use tokio::sync::Semaphore;
 
-    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let semaphore = Arc::new(Semaphore::new(options.concurrency));
     let mut handles = Vec::new();
 
     for &idx in indices {
         let sem = semaphore.clone();
+        let halted = halted.clone();
         let config = config.clone();
         let command = command.to_string();
+        let output = options.output;
+        let error_mode = options.error_mode;
+        let stream = options.stream;
+        let input = options.input.clone();
 
         let handle = tokio::spawn(async move {
-            let _permit = match sem.acquire().await {
-                Ok(permit) => permit,
-                Err(e) => {
-                    eprintln!("\x1b[31mError: failed to acquire semaphore permit: {e}\x1b[0m");
-                    return;
-                }
-            };
+            let name = config.bookmarks[idx].name.clone();
+            let _permit = sem
+                .acquire()
+                .await
+                .context("Failed to acquire semaphore permit")?;
 
-            let bookmark = &config.bookmarks[idx];
-            let header = format!("\x1b[1m── {} ──\x1b[0m", bookmark.name);
+            if halted.load(Ordering::SeqCst) {
+                return Ok::<_, anyhow::Error>(HostExecResult::skipped(&name));
+            }
 
-            match exec_command_quiet(&config, idx, &command).await {
-                Ok(result) => {
-                    let mut output = format!("{header}\n{}", result.stdout);
-                    if !result.stderr.is_empty() {
-                        output.push_str(&format!("\x1b[31m{}\x1b[0m", result.stderr));
-                    }
-                    if result.exit_code != 0 {
-                        output.push_str(&format!(
-                            "\x1b[31m(exit code: {})\x1b[0m\n",
-                            result.exit_code
-                        ));
+            let mut live = stream.then(|| {
+                let bookmark = &config.bookmarks[idx];
+                LiveOutput::new(&name, &bookmark.env, &config.settings, prefix_width)
+            });
+
+            let started = Instant::now();
+            let result =
+                match exec_command_quiet(&config, idx, &command, input, live.as_mut()).await {
+                    Ok(r) => HostExecResult::completed(
+                        &name,
+                        r.exit_code,
+                        r.stdout,
+                        r.stderr,
+                        started.elapsed(),
+                    ),
+                    Err(e) => {
+                        if let Some(live) = live.as_mut() {
+                            live.error(&format!("{e:#}"));
+                        }
+                        HostExecResult::unreachable(&name, &e, started.elapsed())
                     }
-                    print!("{output}");
-                }
-                Err(e) => {
-                    eprintln!("{header}\n\x1b[31mError: {e}\x1b[0m\n");
-                }
+                };
+
+            if error_mode == ErrorMode::FailFast && result.is_failure() {
+                halted.store(true, Ordering::SeqCst);
             }
+
+            match output {
+                // Streamed output has already been shown line by line
+                ExecOutput::Text if stream => {}
+                ExecOutput::Text => print!("{}", exec_report::format_host_block(&result)),
+                ExecOutput::Jsonl => println!("{}", serde_json::to_string(&result)?),
+                ExecOutput::Json => {}
+            }
+
+            Ok(result)
         });
 
         handles.push(handle);
     }
 
+    let mut results = Vec::with_capacity(handles.len());
     for handle in handles {
-        handle.await?;
+        results.push(handle.await??);
     }
-
-    Ok(())
+    Ok(results)
 }
 
 /// Execute a single command on a bookmark without streaming to stdout.
-/// Used by `exec_multi` to collect output atomically per host.
+/// Used by `exec_multi` to collect output atomically per host; with `live`,
+/// output is echoed line by line as it arrives instead and is not kept, so
+/// long-running streamed commands don't grow memory without bound.
 async fn exec_command_quiet(
     config: &AppConfig,
     bookmark_index: usize,
     command: &str,
+    input: Option<Arc<[u8]>>,
+    mut live: Option<&mut LiveOutput>,
 ) -> Result<ExecResult> {
     let session = establish_session(config, bookmark_index, false).await?;
 
@@ -1077,16 +1382,19 @@ async fn exec_command_quiet(
     let mut stderr_buf = Vec::new();
     let mut exit_code: Option<u32> = None;
 
-    let (mut channel_rx, _channel_tx) = channel.split();
+    let (mut channel_rx, channel_tx) = channel.split();
+    let _stdin_writer = input.map(|input| spawn_stdin_writer(channel_tx, ExecInput::Bytes(input)));
 
     loop {
         match channel_rx.wait().await {
-            Some(ChannelMsg::Data { ref data }) => {
-                stdout_buf.extend_from_slice(data);
-            }
-            Some(ChannelMsg::ExtendedData { data, ext: 1 }) => {
-                stderr_buf.extend_from_slice(&data);
-            }
+            Some(ChannelMsg::Data { ref data }) => match live.as_deref_mut() {
+                Some(live) => live.stdout(data),
+                None => stdout_buf.extend_from_slice(data),
+            },
+            Some(ChannelMsg::ExtendedData { data, ext: 1 }) => match live.as_deref_mut() {
+                Some(live) => live.stderr(&data),
+                None => stderr_buf.extend_from_slice(&data),
+            },
             Some(ChannelMsg::ExitStatus { exit_status }) => {
                 exit_code = Some(exit_status);
             }
@@ -1103,6 +1411,10 @@ async fn exec_command_quiet(
         }
     }
 
+    if let Some(live) = live {
+        live.finish();
+    }
+
     Ok(ExecResult {
         stdout: String::from_utf8_lossy(&stdout_buf).to_string(),
         stderr: String::from_utf8_lossy(&stderr_buf).to_string(),
@@ -1110,31 +1422,163 @@ async fn exec_command_quiet(
     })
 }
 
-/// Load SSH private keys for authentication.
+/// Send `input` to a remote command's stdin, then EOF.
+///
+/// Runs in its own task so a command that writes output before it has read
+/// all of its input can't deadlock against our read loop. `data()` waits for
+/// the remote window, so a fast local producer is throttled to what the
+/// remote command consumes.
+fn spawn_stdin_writer(
+    channel_tx: russh::ChannelWriteHalf<russh::client::Msg>,
+    input: ExecInput,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let written = match input {
+            ExecInput::Bytes(bytes) => channel_tx.data(&bytes[..]).await,
+            ExecInput::LocalStdin => channel_tx.data(tokio::io::stdin()).await,
+        };
+        if let Err(e) = written {
+            tracing::debug!("exec: failed to write stdin: {e}");
+            return;
+        }
+        if let Err(e) = channel_tx.eof().await {
+            tracing::debug!("exec: failed to send EOF: {e}");
+        }
+    })
+}
+
+/// Keys and certificates available for public key authentication.
+#[derive(Default)]
+struct Credentials {
+    keys: Vec<PrivateKeyWithHashAlg>,
+    /// OpenSSH user certificates, tried before the plain keys.
+    certs: Vec<KeyCertificate>,
+    /// Explicit `certificate_file` not yet paired with a loaded key.
+    pending_cert: Option<(String, russh::keys::Certificate)>,
+    /// Explicit `certificate_file` whose key is held by the ssh-agent rather
+    /// than loaded from disk. Signed through the agent.
+    agent_cert: Option<(String, russh::keys::Certificate)>,
+    /// Public key of the configured `identity_file`, from the loaded key or its
+    /// `.pub`. Selects the ssh-agent key to offer.
+    identity: Option<russh::keys::PublicKey>,
+}
+
+impl Credentials {
+    fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.certs.is_empty() && self.agent_cert.is_none()
+    }
+
+    /// Pair a `certificate_file` that matched no on-disk key with an ssh-agent
+    /// identity, for keys that only live in the agent (hardware-backed or
+    /// passphrase-protected keys added with ssh-add).
+    async fn pair_pending_with_agent(&mut self, use_agent: bool) {
+        let Some((path, cert)) = self.pending_cert.take() else {
+            return;
+        };
+        if use_agent && agent::holds_certificate_key(&cert).await {
+            tracing::debug!(path, "certificate key is held by ssh-agent");
+            self.agent_cert = Some((path, cert));
+        } else {
+            eprintln!(
+                "Warning: certificate {path} does not match any loaded key or ssh-agent identity, ignored"
+            );
+        }
+    }
+
+    /// Add a private key loaded from `path`. Pairs it with the configured
+    /// `certificate_file` if that certifies this key, and with `<path>-cert.pub`
+    /// if such a file exists.
+    fn add_key(&mut self, key: russh::keys::PrivateKey, path: &str) {
+        let key = Arc::new(key);
+
+        if let Some((cert_path, cert)) = self.pending_cert.take() {
+            if certificate::matches_key(&cert, &key) {
+                self.certs.push(KeyCertificate {
+                    key: Arc::clone(&key),
+                    cert,
+                    path: cert_path,
+                });
+            } else {
+                self.pending_cert = Some((cert_path, cert));
+            }
+        }
+
+        let cert_path = certificate::default_cert_path(std::path::Path::new(path));
+        let cert_path = cert_path.to_string_lossy().to_string();
+        if std::path::Path::new(&cert_path).exists()
+            && !self.certs.iter().any(|c| c.path == cert_path)
+        {
+            match certificate::load(&cert_path) {
+                Ok(cert) if certificate::matches_key(&cert, &key) => {
+                    tracing::debug!(path = cert_path, "loaded SSH certificate");
+                    self.certs.push(KeyCertificate {
+                        key: Arc::clone(&key),
+                        cert,
+                        path: cert_path,
+                    });
+                }
+                Ok(_) => eprintln!("Warning: certificate {cert_path} does not match key {path}"),
+                Err(e) => eprintln!("Warning: {e:#}"),
+            }
+        }
+
+        self.keys.extend(wrap_key(key));
+    }
+
+    /// Print each certificate's validity window and principals, so an expired
+    /// or mis-scoped certificate is obvious after a failed login.
+    fn report_certificates(&self) {
+        let now = Utc::now();
+        for cert in &self.certs {
+            eprintln!(
+                "Certificate {}: {}",
+                cert.path,
+                certificate::describe(&cert.cert, now)
+            );
+        }
+        if let Some((path, cert)) = &self.agent_cert {
+            eprintln!("Certificate {path}: {}", certificate::describe(cert, now));
+        }
+    }
+}
+
+/// Load SSH private keys and certificates for authentication.
 /// If bookmark (or its profile) has identity_file, load that (with env var expansion).
 /// Also always loads default keys (~/.ssh/id_*) so jump hosts can authenticate with
-/// the same keys that native `ssh -J` would use.
+/// the same keys that native `ssh -J` would use. Each key's `-cert.pub` is picked up
+/// automatically; `certificate_file` names a certificate explicitly.
 fn load_keys(
     bookmark: &Bookmark,
     profiles: &[crate::config::model::Profile],
-) -> Result<Vec<PrivateKeyWithHashAlg>> {
-    let mut keys = Vec::new();
+) -> Result<Credentials> {
+    let mut creds = Credentials::default();
+
+    match bookmark.resolved_effective_certificate_file(profiles) {
+        Some(Ok(path)) => match certificate::load(&path) {
+            Ok(cert) => creds.pending_cert = Some((path, cert)),
+            Err(e) => eprintln!("Warning: {e:#}"),
+        },
+        Some(Err(e)) => eprintln!("Warning: {e}"),
+        None => {}
+    }
 
     if bookmark.effective_identity_file(profiles).is_some() {
         match bookmark.resolved_effective_identity_file(profiles) {
             Some(Ok(path)) => {
                 let expanded = PathBuf::from(&path);
+                creds.identity = russh::keys::load_public_key(format!("{path}.pub")).ok();
                 if expanded.exists() {
-                    match load_key_from_path(&path) {
-                        Ok(loaded) => keys.extend(loaded),
-                        Err(_) => {
-                            // Key failed to load without passphrase — prompt for one
-                            match load_key_with_passphrase_prompt(&path) {
-                                Ok(loaded) => keys.extend(loaded),
-                                Err(e) => {
-                                    eprintln!("Warning: failed to load key {path}: {e}");
-                                }
-                            }
+                    let loaded = load_key_from_path(&path).or_else(|_| {
+                        // Key failed to load without passphrase — prompt for one
+                        load_key_with_passphrase_prompt(&path)
+                    });
+                    match loaded {
+                        Ok(key) => {
+                            creds.identity = Some(key.public_key().clone());
+                            creds.add_key(key, &path);
+                        }
+                        Err(e) => {
+                            eprintln!("Warning: failed to load key {path}: {e}");
                         }
                     }
                 } else {
@@ -1158,16 +1602,15 @@ fn load_keys(
     // Always load default keys as well. This matches native ssh -J behavior:
     // jump hosts authenticate with default keys, and having extra keys
     // doesn't hurt the target auth (rejected keys are simply skipped).
-    keys.extend(load_default_keys());
+    load_default_keys(&mut creds);
 
-    Ok(keys)
+    Ok(creds)
 }
 
 /// Load default SSH keys from ~/.ssh/ (id_ed25519, id_rsa, id_ecdsa).
 /// Used for jump host authentication (matching native ssh -J behavior) and
 /// as a fallback for direct connections.
-fn load_default_keys() -> Vec<PrivateKeyWithHashAlg> {
-    let mut keys = Vec::new();
+fn load_default_keys(creds: &mut Credentials) {
     let ssh_dir = dirs::home_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join(".ssh");
@@ -1175,44 +1618,41 @@ fn load_default_keys() -> Vec<PrivateKeyWithHashAlg> {
     for name in DEFAULT_KEY_NAMES {
         let path = ssh_dir.join(name);
         if path.exists() {
-            match load_key_from_path(&path.to_string_lossy()) {
-                Ok(loaded) => keys.extend(loaded),
+            let path = path.to_string_lossy();
+            match load_key_from_path(&path) {
+                Ok(key) => creds.add_key(key, &path),
                 Err(_) => continue, // Silently skip default keys that fail to load
             }
         }
     }
-
-    keys
 }
 
 /// Wrap a loaded private key into one or more `PrivateKeyWithHashAlg` entries.
 /// For RSA keys, returns SHA-256 and SHA-512 variants (modern algorithms first)
 /// so the auth loop tries `rsa-sha2-256` before the deprecated `ssh-rsa` (SHA-1).
 /// For non-RSA keys (Ed25519, ECDSA), returns a single entry with no hash override.
-fn wrap_key(key: russh::keys::PrivateKey) -> Vec<PrivateKeyWithHashAlg> {
+fn wrap_key(key: Arc<russh::keys::PrivateKey>) -> Vec<PrivateKeyWithHashAlg> {
     use russh::keys::HashAlg;
 
-    let arc = Arc::new(key);
-    if arc.algorithm().is_rsa() {
+    if key.algorithm().is_rsa() {
         // Try SHA-256 first (most widely accepted), then SHA-512
         vec![
-            PrivateKeyWithHashAlg::new(Arc::clone(&arc), Some(HashAlg::Sha256)),
-            PrivateKeyWithHashAlg::new(Arc::clone(&arc), Some(HashAlg::Sha512)),
+            PrivateKeyWithHashAlg::new(Arc::clone(&key), Some(HashAlg::Sha256)),
+            PrivateKeyWithHashAlg::new(Arc::clone(&key), Some(HashAlg::Sha512)),
         ]
     } else {
-        vec![PrivateKeyWithHashAlg::new(arc, None)]
+        vec![PrivateKeyWithHashAlg::new(key, None)]
     }
 }
 
 /// Load a single private key from a file path (no passphrase).
-fn load_key_from_path(path: &str) -> Result<Vec<PrivateKeyWithHashAlg>> {
-    let key = russh::keys::load_secret_key(path, None)
-        .with_context(|| format!("Failed to load SSH key: {path}"))?;
-    Ok(wrap_key(key))
+fn load_key_from_path(path: &str) -> Result<russh::keys::PrivateKey> {
+    russh::keys::load_secret_key(path, None)
+        .with_context(|| format!("Failed to load SSH key: {path}"))
 }
 
 /// Prompt for a passphrase and load an encrypted private key.
-fn load_key_with_passphrase_prompt(path: &str) -> Result<Vec<PrivateKeyWithHashAlg>> {
+fn load_key_with_passphrase_prompt(path: &str) -> Result<russh::keys::PrivateKey> {
     eprintln!("Key {path} requires a passphrase.");
     eprint!("Passphrase: ");
     std::io::stderr().flush()?;
@@ -1239,9 +1679,8 @@ fn load_key_with_passphrase_prompt(path: &str) -> Result<Vec<PrivateKeyWithHashA
     crossterm::terminal::disable_raw_mode()?;
     eprintln!(); // newline after hidden input
 
-    let key = russh::keys::load_secret_key(path, Some(passphrase.as_str()))
-        .with_context(|| format!("Failed to decrypt SSH key: {path}"))?;
-    Ok(wrap_key(key))
+    russh::keys::load_secret_key(path, Some(passphrase.as_str()))
+        .with_context(|| format!("Failed to decrypt SSH key: {path}"))
 }
 
 /// Context passed to `authenticate()` for keychain-aware auth.
@@ -1254,46 +1693,158 @@ struct AuthContext<'a> {
     /// When true, skip interactive password prompt (keychain-only auth).
     /// Used for mux mode where the TUI owns the terminal.
     non_interactive: bool,
+    /// When true, try keys held by the local ssh-agent before on-disk keys.
+    use_agent: bool,
 }
 
-/// Try to authenticate using available keys, then keychain password, then user prompt.
-async fn authenticate(
+/// Outcome of the public key phase of authentication.
+enum PublicKeyOutcome {
+    /// A key was accepted and the session is authenticated.
+    Success,
+    /// A key was accepted but the server requires another method (e.g. 2FA).
+    PartialSuccess,
+    /// No key was accepted.
+    Rejected,
+}
+
+/// Try a certificate whose key the ssh-agent holds and then the agent's keys
+/// (when `use_agent`), then on-disk certificates, then plain keys in order.
+/// With an `identity_file`, only the matching agent key is offered.
+/// Stops at the first key the server accepts, fully or partially.
+async fn try_public_keys(
     session: &mut russh::client::Handle<SshoreHandler>,
     user: &str,
-    keys: &[PrivateKeyWithHashAlg],
+    creds: &Credentials,
     ctx: &AuthContext<'_>,
-) -> Result<bool> {
-    // 1. Try public key auth with each available key
-    for (i, key) in keys.iter().enumerate() {
+) -> PublicKeyOutcome {
+    if ctx.use_agent {
+        if let Some((path, cert)) = &creds.agent_cert {
+            tracing::debug!(path, "trying ssh-agent certificate auth");
+            let result = agent::authenticate_certificate(session, user, cert).await;
+            if let Some(outcome) = agent_outcome(result) {
+                return outcome;
+            }
+        }
+        let identity = creds.identity.as_ref().filter(|_| ctx.has_identity_file);
+        if let Some(outcome) = agent_outcome(agent::authenticate(session, user, identity).await) {
+            return outcome;
+        }
+    }
+
+    for cert in &creds.certs {
+        tracing::debug!(path = cert.path, "trying certificate auth");
+        match session
+            .authenticate_openssh_cert(user, Arc::clone(&cert.key), cert.cert.clone())
+            .await
+        {
+            Ok(AuthResult::Success) => {
+                tracing::debug!(path = cert.path, "certificate auth succeeded");
+                return PublicKeyOutcome::Success;
+            }
+            Ok(AuthResult::Failure {
+                partial_success: true,
+                ..
+            }) => {
+                tracing::debug!(
+                    path = cert.path,
+                    "certificate accepted, further auth required"
+                );
+                return PublicKeyOutcome::PartialSuccess;
+            }
+            Ok(AuthResult::Failure { .. }) => {
+                tracing::debug!(path = cert.path, "certificate rejected");
+            }
+            Err(e) => {
+                tracing::debug!(path = cert.path, error = %e, "certificate auth error");
+            }
+        }
+    }
+
+    for (i, key) in creds.keys.iter().enumerate() {
         tracing::debug!(key_index = i, "trying public key auth");
         match session.authenticate_publickey(user, key.clone()).await {
             Ok(AuthResult::Success) => {
                 tracing::debug!(key_index = i, "public key auth succeeded");
-                return Ok(true);
+                return PublicKeyOutcome::Success;
+            }
+            Ok(AuthResult::Failure {
+                partial_success: true,
+                ..
+            }) => {
+                tracing::debug!(key_index = i, "public key accepted, further auth required");
+                return PublicKeyOutcome::PartialSuccess;
             }
             Ok(AuthResult::Failure { .. }) => {
                 tracing::debug!(key_index = i, "public key rejected");
-                continue;
             }
             Err(e) => {
                 tracing::debug!(key_index = i, error = %e, "public key auth error");
-                continue;
             }
         }
     }
 
+    PublicKeyOutcome::Rejected
+}
+
+/// Map an ssh-agent auth attempt to an outcome, or `None` to keep trying.
+fn agent_outcome(result: Result<AuthResult>) -> Option<PublicKeyOutcome> {
+    match result {
+        Ok(AuthResult::Success) => Some(PublicKeyOutcome::Success),
+        Ok(AuthResult::Failure {
+            partial_success: true,
+            ..
+        }) => Some(PublicKeyOutcome::PartialSuccess),
+        Ok(AuthResult::Failure { .. }) => None,
+        Err(e) => {
+            tracing::debug!(error = %e, "ssh-agent auth error");
+            None
+        }
+    }
+}
+
+/// Try to authenticate using the ssh-agent, then available keys, then
+/// keyboard-interactive, then keychain password, then user prompt.
+async fn authenticate(
+    session: &mut russh::client::Handle<SshoreHandler>,
+    user: &str,
+    creds: &Credentials,
+    ctx: &AuthContext<'_>,
+) -> Result<bool> {
+    // 0-1. Try ssh-agent keys, then on-disk keys
+    let partial = match try_public_keys(session, user, creds, ctx).await {
+        PublicKeyOutcome::Success => return Ok(true),
+        PublicKeyOutcome::PartialSuccess => true,
+        PublicKeyOutcome::Rejected => false,
+    };
+
     // If the bookmark has an explicit identity_file AND we actually tried
     // keys, don't fall through to password auth — the user intended key-based
     // authentication. But if no keys were loaded (file missing, wrong format,
-    // passphrase cancelled), still allow password fallback.
-    if ctx.has_identity_file && !keys.is_empty() {
+    // passphrase cancelled), still allow password fallback. A partially
+    // accepted key means the server wants a second factor, so keep going.
+    if ctx.has_identity_file && !creds.is_empty() && !partial {
         tracing::debug!("identity_file configured and keys were tried, skipping password fallback");
         return Ok(false);
     }
 
-    // 2. Try keychain password (if bookmark name is available)
+    let stored = ctx
+        .bookmark_name
+        .and_then(|name| keychain::get_password(name).ok().flatten());
+
+    // 2. Try keyboard-interactive (PAM prompts, 2FA/OTP)
+    let kbd =
+        keyboard_interactive::authenticate(session, user, stored.as_deref(), ctx.non_interactive)
+            .await?;
+    if kbd.success {
+        if let Some(password) = kbd.typed_password {
+            offer_save_password(ctx, &password);
+        }
+        return Ok(true);
+    }
+
+    // 3. Try keychain password (if bookmark name is available)
     if let Some(name) = ctx.bookmark_name
-        && let Ok(Some(stored)) = keychain::get_password(name)
+        && let Some(stored) = stored
     {
         tracing::debug!("trying keychain password");
         match session.authenticate_password(user, &stored).await {
@@ -1313,7 +1864,7 @@ async fn authenticate(
         }
     }
 
-    // 3. Prompt user for password (skip in non-interactive mode)
+    // 4. Prompt user for password (skip in non-interactive mode)
     if ctx.non_interactive {
         tracing::debug!("non-interactive mode, skipping password prompt");
         return Ok(false);
@@ -1349,7 +1900,7 @@ fn offer_save_password(ctx: &AuthContext<'_>, password: &str) {
 
     let env_label = ctx
         .env
-        .filter(|e| e.eq_ignore_ascii_case("production"))
+        .filter(|e| exec_guard::is_production(e))
         .map(|_| " \x1b[31m(PRODUCTION)\x1b[0m")
         .unwrap_or("");
 
@@ -1373,6 +1924,12 @@ fn offer_save_password(ctx: &AuthContext<'_>, password: &str) {
 
 /// Prompt the user for a password on stderr (so it doesn't interfere with SSH I/O).
 fn prompt_password(user: &str) -> Result<Zeroizing<String>> {
+    read_prompt(&format!("{user}'s password: "), false)
+}
+
+/// Show `prompt` on stderr and read one line of input in raw mode.
+/// Typed characters are echoed only when `echo` is true.
+fn read_prompt(prompt: &str, echo: bool) -> Result<Zeroizing<String>> {
     struct RawModeGuard {
         disable_on_drop: bool,
     }
@@ -1385,10 +1942,9 @@ fn prompt_password(user: &str) -> Result<Zeroizing<String>> {
         }
     }
 
-    eprint!("{user}'s password: ");
+    eprint!("{prompt}");
     std::io::stderr().flush()?;
 
-    // Read password without echo
     let was_raw = crossterm::terminal::is_raw_mode_enabled().unwrap_or(false);
     if !was_raw {
         crossterm::terminal::enable_raw_mode()?;
@@ -1397,14 +1953,24 @@ fn prompt_password(user: &str) -> Result<Zeroizing<String>> {
         disable_on_drop: !was_raw,
     };
 
-    let mut password = Zeroizing::new(String::new());
+    let mut input = Zeroizing::new(String::new());
     loop {
         if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
             match key.code {
                 crossterm::event::KeyCode::Enter => break,
-                crossterm::event::KeyCode::Char(c) => password.push(c),
+                crossterm::event::KeyCode::Char(c) => {
+                    input.push(c);
+                    if echo {
+                        eprint!("{c}");
+                        std::io::stderr().flush()?;
+                    }
+                }
                 crossterm::event::KeyCode::Backspace => {
-                    password.pop();
+                    let erased = input.pop().is_some();
+                    if erased && echo {
+                        eprint!("\x08 \x08");
+                        std::io::stderr().flush()?;
+                    }
                 }
                 crossterm::event::KeyCode::Esc => {
                     eprintln!();
@@ -1414,9 +1980,9 @@ fn prompt_password(user: &str) -> Result<Zeroizing<String>> {
             }
         }
     }
-    eprintln!(); // Newline after password entry
+    eprintln!(); // Newline after input
 
-    Ok(password)
+    Ok(input)
 }
 
 /// Grace period after sending a captured sudo password before auto-saving.
@@ -2174,6 +2740,9 @@ mod tests {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         }
     }
 
@@ -88,6 +88,9 @@ pub async fn mux_open_shell(
         connect_timeout_secs,
         ssh_options: session.effective_ssh_options(group, &config.profiles),
         profile: group.profile.clone(),
+        use_agent: None,
+        certificate_file: None,
+        tunnels: vec![],
     };
 
     // Establish SSH session via temp config (reuse establish_session)
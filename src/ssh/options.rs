@@ -0,0 +1,506 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use russh::keys::Algorithm;
+use russh::{Preferred, cipher, compression, kex, mac};
+
+use super::client::HostKeyCheckMode;
+
+/// Ciphers offered by default. AES-GCM first: hardware-accelerated on most CPUs.
+const DEFAULT_CIPHERS: &[cipher::Name] = &[
+    cipher::AES_256_GCM,
+    cipher::AES_128_GCM,
+    cipher::CHACHA20_POLY1305,
+    cipher::AES_256_CTR,
+    cipher::AES_192_CTR,
+    cipher::AES_128_CTR,
+];
+
+/// KEX pseudo-algorithms that advertise protocol extensions rather than a real
+/// key exchange. Always kept so a custom `KexAlgorithms` list can't silently
+/// disable strict KEX or `server-sig-algs`.
+const KEX_EXTENSIONS: &[kex::Name] = &[
+    kex::EXTENSION_SUPPORT_AS_CLIENT,
+    kex::EXTENSION_OPENSSH_STRICT_KEX_AS_CLIENT,
+];
+
+/// Compression order when `Compression yes` is set.
+const COMPRESSION_ON: &[compression::Name] = &[
+    compression::ZLIB_LEGACY,
+    compression::ZLIB,
+    compression::NONE,
+];
+
+/// Compression order when `Compression no` is set.
+const COMPRESSION_OFF: &[compression::Name] = &[compression::NONE];
+
+/// Directives the ssh_config importer records that have no per-connection
+/// effect in sshore. Accepted silently so imported bookmarks don't warn on
+/// every connect.
+const SILENT_OPTIONS: &[&str] = &[
+    // sshore never adds keys to an agent; it only reads identities from one.
+    "addkeystoagent",
+];
+
+/// When to request a PTY (OpenSSH `RequestTTY`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RequestTty {
+    /// PTY for interactive sessions, none for exec (the default).
+    #[default]
+    Auto,
+    /// Always request a PTY.
+    Yes,
+    /// Never request a PTY.
+    No,
+    /// Always request a PTY (same as `Yes`; sshore has no stdin-is-a-tty check).
+    Force,
+}
+
+/// Per-connection behavior derived from a bookmark's merged `ssh_options`.
+///
+/// `None` fields mean "not set" — the caller's defaults apply.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionOptions {
+    /// `ServerAliveInterval`. `Some(None)` disables keepalives (interval 0).
+    pub keepalive_interval: Option<Option<Duration>>,
+    /// `ServerAliveCountMax`.
+    pub keepalive_max: Option<usize>,
+    /// `Compression`.
+    pub compression: Option<bool>,
+    /// `StrictHostKeyChecking`, overriding `settings.host_key_checking`.
+    pub host_key_checking: Option<HostKeyCheckMode>,
+    /// `RequestTTY`.
+    pub request_tty: RequestTty,
+    /// `ForwardAgent`.
+    pub forward_agent: bool,
+    /// `Ciphers`.
+    pub ciphers: Option<Vec<cipher::Name>>,
+    /// `KexAlgorithms`.
+    pub kex: Option<Vec<kex::Name>>,
+    /// `HostKeyAlgorithms`.
+    pub host_key_algorithms: Option<Vec<Algorithm>>,
+    /// `MACs`.
+    pub macs: Option<Vec<mac::Name>>,
+    /// Problems found while parsing: unsupported directives and invalid values.
+    pub warnings: Vec<String>,
+}
+
+impl ConnectionOptions {
+    /// Parse merged `ssh_options`. Directive names match case-insensitively,
+    /// like ssh_config. Never fails — bad values become warnings.
+    pub fn from_ssh_options(ssh_options: &BTreeMap<String, String>) -> Self {
+        let mut opts = Self::default();
+
+        for (key, value) in ssh_options {
+            let value = value.trim();
+            match key.to_ascii_lowercase().as_str() {
+                "serveraliveinterval" => match value.parse::<u64>() {
+                    Ok(0) => opts.keepalive_interval = Some(None),
+                    Ok(secs) => {
+                        opts.keepalive_interval = Some(Some(Duration::from_secs(secs)));
+                    }
+                    Err(_) => opts.invalid(key, value),
+                },
+                "serveralivecountmax" => match value.parse::<usize>() {
+                    Ok(n) => opts.keepalive_max = Some(n),
+                    Err(_) => opts.invalid(key, value),
+                },
+                "compression" => match parse_yes_no(value) {
+                    Some(on) => opts.compression = Some(on),
+                    None => opts.invalid(key, value),
+                },
+                "stricthostkeychecking" => match value.to_ascii_lowercase().as_str() {
+                    "yes" | "ask" => opts.host_key_checking = Some(HostKeyCheckMode::Strict),
+                    // "no" still refuses changed keys — sshore never silently
+                    // accepts a changed host key from a bookmark option.
+                    "accept-new" | "no" => {
+                        opts.host_key_checking = Some(HostKeyCheckMode::AcceptNew);
+                    }
+                    "off" => opts.host_key_checking = Some(HostKeyCheckMode::Off),
+                    _ => opts.invalid(key, value),
+                },
+                "requesttty" => match value.to_ascii_lowercase().as_str() {
+                    "auto" => opts.request_tty = RequestTty::Auto,
+                    "yes" | "true" => opts.request_tty = RequestTty::Yes,
+                    "no" | "false" => opts.request_tty = RequestTty::No,
+                    "force" => opts.request_tty = RequestTty::Force,
+                    _ => opts.invalid(key, value),
+                },
+                "forwardagent" => match parse_yes_no(value) {
+                    Some(on) => opts.forward_agent = on,
+                    None => opts.invalid(key, value),
+                },
+                "ciphers" => {
+                    opts.ciphers = parse_algorithm_list(
+                        key,
+                        value,
+                        DEFAULT_CIPHERS,
+                        |name| {
+                            cipher::Name::try_from(name)
+                                .ok()
+                                .filter(|n| !matches!(n.as_ref(), "none" | "clear"))
+                        },
+                        &mut opts.warnings,
+                    );
+                }
+                "kexalgorithms" => {
+                    let defaults: Vec<kex::Name> = Preferred::DEFAULT
+                        .kex
+                        .iter()
+                        .filter(|n| !is_kex_extension(n))
+                        .copied()
+                        .collect();
+                    opts.kex = parse_algorithm_list(
+                        key,
+                        value,
+                        &defaults,
+                        |name| kex::Name::try_from(name).ok().filter(|n| *n != kex::NONE),
+                        &mut opts.warnings,
+                    );
+                }
+                "hostkeyalgorithms" => {
+                    opts.host_key_algorithms = parse_algorithm_list(
+                        key,
+                        value,
+                        &Preferred::DEFAULT.key,
+                        |name| {
+                            Algorithm::new(name)
+                                .ok()
+                                .filter(|a| Preferred::DEFAULT.key.contains(a))
+                        },
+                        &mut opts.warnings,
+                    );
+                }
+                "macs" => {
+                    opts.macs = parse_algorithm_list(
+                        key,
+                        value,
+                        &Preferred::DEFAULT.mac,
+                        |name| mac::Name::try_from(name).ok().filter(|n| *n != mac::NONE),
+                        &mut opts.warnings,
+                    );
+                }
+                name if SILENT_OPTIONS.contains(&name) => {}
+                _ => opts.warnings.push(format!(
+                    "ssh option '{key}' is not supported and was ignored"
+                )),
+            }
+        }
+
+        opts
+    }
+
+    fn invalid(&mut self, key: &str, value: &str) {
+        self.warnings.push(format!(
+            "invalid value '{value}' for ssh option '{key}', ignored"
+        ));
+    }
+
+    /// Print parse warnings to stderr. Only the interactive `connect` command
+    /// does this, so the text never lands on top of the TUI.
+    pub fn print_warnings(&self) {
+        for warning in &self.warnings {
+            eprintln!("Warning: {warning}");
+        }
+    }
+
+    /// Send parse warnings to the debug log.
+    pub fn log_warnings(&self) {
+        for warning in &self.warnings {
+            tracing::warn!("{warning}");
+        }
+    }
+
+    /// Resolve the host key checking mode: bookmark option wins over the global setting.
+    pub fn host_key_check_mode(&self, settings_mode: HostKeyCheckMode) -> HostKeyCheckMode {
+        self.host_key_checking.clone().unwrap_or(settings_mode)
+    }
+
+    /// Whether to request a PTY. `interactive` is true for shell sessions.
+    pub fn wants_pty(&self, interactive: bool) -> bool {
+        match self.request_tty {
+            RequestTty::Auto => interactive,
+            RequestTty::Yes | RequestTty::Force => true,
+            RequestTty::No => false,
+        }
+    }
+
+    /// Build the algorithm preferences, starting from sshore's defaults.
+    pub fn preferred(&self) -> Preferred {
+        let mut p = Preferred::DEFAULT;
+        p.cipher = Cow::Owned(
+            self.ciphers
+                .clone()
+                .unwrap_or_else(|| DEFAULT_CIPHERS.to_vec()),
+        );
+        if let Some(ref kex) = self.kex {
+            let mut list = kex.clone();
+            list.extend_from_slice(KEX_EXTENSIONS);
+            p.kex = Cow::Owned(list);
+        }
+        if let Some(ref key) = self.host_key_algorithms {
+            p.key = Cow::Owned(key.clone());
+        }
+        if let Some(ref macs) = self.macs {
+            p.mac = Cow::Owned(macs.clone());
+        }
+        match self.compression {
+            Some(true) => p.compression = Cow::Borrowed(COMPRESSION_ON),
+            Some(false) => p.compression = Cow::Borrowed(COMPRESSION_OFF),
+            None => {}
+        }
+        p
+    }
+
+    /// Apply keepalive overrides and algorithm preferences to a russh config.
+    pub fn apply(&self, config: &mut russh::client::Config) {
+        if let Some(interval) = self.keepalive_interval {
+            config.keepalive_interval = interval;
+        }
+        if let Some(max) = self.keepalive_max {
+            config.keepalive_max = max;
+        }
+        config.preferred = self.preferred();
+    }
+}
+
+fn parse_yes_no(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" | "true" => Some(true),
+        "no" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn is_kex_extension(name: &kex::Name) -> bool {
+    matches!(
+        name.as_ref(),
+        "ext-info-c"
+            | "ext-info-s"
+            | "kex-strict-c-v00@openssh.com"
+            | "kex-strict-s-v00@openssh.com"
+    )
+}
+
+/// Parse an OpenSSH algorithm list. A leading `+` appends to the defaults,
+/// `-` removes from them, `^` moves to the front; otherwise the list replaces
+/// the defaults. Unknown names are skipped with a warning. Returns None when
+/// nothing usable is left, so the defaults stay in effect.
+fn parse_algorithm_list<T: Clone + PartialEq>(
+    directive: &str,
+    value: &str,
+    defaults: &[T],
+    lookup: impl Fn(&str) -> Option<T>,
+    warnings: &mut Vec<String>,
+) -> Option<Vec<T>> {
+    let (modifier, list) = match value.chars().next() {
+        Some(c @ ('+' | '-' | '^')) => (Some(c), &value[1..]),
+        _ => (None, value),
+    };
+
+    let mut names = Vec::new();
+    for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        match lookup(name) {
+            Some(alg) => names.push(alg),
+            None => warnings.push(format!(
+                "{directive}: unsupported algorithm '{name}' was ignored"
+            )),
+        }
+    }
+
+    let result: Vec<T> = match modifier {
+        Some('+') => {
+            let mut all = defaults.to_vec();
+            all.extend(names.into_iter().filter(|n| !defaults.contains(n)));
+            all
+        }
+        Some('-') => defaults
+            .iter()
+            .filter(|d| !names.contains(d))
+            .cloned()
+            .collect(),
+        Some('^') => {
+            let rest: Vec<T> = defaults
+                .iter()
+                .filter(|d| !names.contains(d))
+                .cloned()
+                .collect();
+            names.into_iter().chain(rest).collect()
+        }
+        _ => names,
+    };
+
+    if result.is_empty() {
+        warnings.push(format!(
+            "{directive}: no supported algorithms left, using defaults"
+        ));
+        None
+    } else {
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(pairs: &[(&str, &str)]) -> ConnectionOptions {
+        let map: BTreeMap<String, String> = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        ConnectionOptions::from_ssh_options(&map)
+    }
+
+    #[test]
+    fn test_empty_options_keep_defaults() {
+        let opts = parse(&[]);
+        assert!(opts.warnings.is_empty());
+        let mut config = russh::client::Config {
+            keepalive_max: 3,
+            ..Default::default()
+        };
+        opts.apply(&mut config);
+        assert_eq!(config.keepalive_max, 3);
+        assert_eq!(config.preferred.cipher.as_ref(), DEFAULT_CIPHERS);
+    }
+
+    #[test]
+    fn test_server_alive_interval_and_count() {
+        let opts = parse(&[("ServerAliveInterval", "30"), ("ServerAliveCountMax", "5")]);
+        assert_eq!(opts.keepalive_interval, Some(Some(Duration::from_secs(30))));
+        assert_eq!(opts.keepalive_max, Some(5));
+    }
+
+    #[test]
+    fn test_server_alive_interval_zero_disables() {
+        let opts = parse(&[("ServerAliveInterval", "0")]);
+        let mut config = russh::client::Config {
+            keepalive_interval: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        opts.apply(&mut config);
+        assert_eq!(config.keepalive_interval, None);
+    }
+
+    #[test]
+    fn test_invalid_value_warns() {
+        let opts = parse(&[("ServerAliveInterval", "soon")]);
+        assert_eq!(opts.keepalive_interval, None);
+        assert_eq!(opts.warnings.len(), 1);
+        assert!(opts.warnings[0].contains("ServerAliveInterval"));
+    }
+
+    #[test]
+    fn test_unsupported_directive_warns() {
+        let opts = parse(&[("ControlMaster", "auto")]);
+        assert_eq!(opts.warnings.len(), 1);
+        assert!(opts.warnings[0].contains("ControlMaster"));
+    }
+
+    #[test]
+    fn test_imported_directives_accepted_silently() {
+        let opts = parse(&[("AddKeysToAgent", "yes")]);
+        assert!(opts.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_directive_names_case_insensitive() {
+        let opts = parse(&[("compression", "yes")]);
+        assert_eq!(opts.compression, Some(true));
+        assert_eq!(opts.preferred().compression.as_ref(), COMPRESSION_ON);
+    }
+
+    #[test]
+    fn test_strict_host_key_checking_values() {
+        let strict = parse(&[("StrictHostKeyChecking", "yes")]);
+        assert_eq!(strict.host_key_checking, Some(HostKeyCheckMode::Strict));
+        let no = parse(&[("StrictHostKeyChecking", "no")]);
+        assert_eq!(no.host_key_checking, Some(HostKeyCheckMode::AcceptNew));
+        assert_eq!(
+            parse(&[]).host_key_check_mode(HostKeyCheckMode::AcceptNew),
+            HostKeyCheckMode::AcceptNew
+        );
+    }
+
+    #[test]
+    fn test_request_tty() {
+        assert!(parse(&[]).wants_pty(true));
+        assert!(!parse(&[]).wants_pty(false));
+        assert!(!parse(&[("RequestTTY", "no")]).wants_pty(true));
+        assert!(parse(&[("RequestTTY", "force")]).wants_pty(false));
+    }
+
+    #[test]
+    fn test_forward_agent() {
+        assert!(parse(&[("ForwardAgent", "yes")]).forward_agent);
+        assert!(!parse(&[("ForwardAgent", "no")]).forward_agent);
+        assert!(!parse(&[]).forward_agent);
+    }
+
+    #[test]
+    fn test_ciphers_replace_list() {
+        let opts = parse(&[("Ciphers", "chacha20-poly1305@openssh.com,aes256-ctr")]);
+        assert_eq!(
+            opts.ciphers,
+            Some(vec![cipher::CHACHA20_POLY1305, cipher::AES_256_CTR])
+        );
+    }
+
+    #[test]
+    fn test_ciphers_remove_from_defaults() {
+        let opts = parse(&[("Ciphers", "-aes128-ctr,aes192-ctr")]);
+        let ciphers = opts.ciphers.unwrap();
+        assert!(!ciphers.contains(&cipher::AES_128_CTR));
+        assert!(!ciphers.contains(&cipher::AES_192_CTR));
+        assert!(ciphers.contains(&cipher::AES_256_GCM));
+    }
+
+    #[test]
+    fn test_ciphers_prepend() {
+        let opts = parse(&[("Ciphers", "^aes128-ctr")]);
+        let ciphers = opts.ciphers.unwrap();
+        assert_eq!(ciphers[0], cipher::AES_128_CTR);
+        assert_eq!(ciphers.len(), DEFAULT_CIPHERS.len());
+    }
+
+    #[test]
+    fn test_ciphers_none_refused() {
+        let opts = parse(&[("Ciphers", "none")]);
+        assert!(opts.ciphers.is_none());
+        assert_eq!(opts.warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_kex_keeps_extensions() {
+        let opts = parse(&[("KexAlgorithms", "curve25519-sha256")]);
+        let preferred = opts.preferred();
+        assert_eq!(preferred.kex[0], kex::CURVE25519);
+        assert!(
+            preferred
+                .kex
+                .contains(&kex::EXTENSION_OPENSSH_STRICT_KEX_AS_CLIENT)
+        );
+    }
+
+    #[test]
+    fn test_host_key_algorithms_and_macs() {
+        let opts = parse(&[
+            ("HostKeyAlgorithms", "ssh-ed25519,rsa-sha2-512"),
+            ("MACs", "hmac-sha2-256-etm@openssh.com,bogus-mac"),
+        ]);
+        assert_eq!(
+            opts.host_key_algorithms,
+            Some(vec![
+                Algorithm::Ed25519,
+                Algorithm::Rsa {
+                    hash: Some(russh::keys::HashAlg::Sha512)
+                }
+            ])
+        );
+        assert_eq!(opts.macs, Some(vec![mac::HMAC_SHA256_ETM]));
+        assert_eq!(opts.warnings.len(), 1);
+        assert!(opts.warnings[0].contains("bogus-mac"));
+    }
+}
@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+
+/// A host count given either absolutely ("5") or relative to the fleet ("25%").
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostCount {
+    Count(usize),
+    Percent(u8),
+}
+
+impl HostCount {
+    /// Parse `N` or `P%` (1-100).
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if let Some(percent) = spec.strip_suffix('%') {
+            match percent.trim().parse::<u8>() {
+                Ok(p) if (1..=100).contains(&p) => Ok(Self::Percent(p)),
+                _ => bail!("Invalid percentage '{spec}': expected 1%-100%"),
+            }
+        } else {
+            match spec.parse::<usize>() {
+                Ok(n) => Ok(Self::Count(n)),
+                Err(_) => bail!("Invalid host count '{spec}': expected a number or a percentage"),
+            }
+        }
+    }
+
+    /// Resolve to a number of hosts out of `total`, rounding percentages up.
+    fn resolve_ceil(self, total: usize) -> usize {
+        match self {
+            Self::Count(n) => n,
+            Self::Percent(p) => (total * p as usize).div_ceil(100),
+        }
+    }
+
+    /// Resolve to a number of hosts out of `total`, rounding percentages down.
+    fn resolve_floor(self, total: usize) -> usize {
+        match self {
+            Self::Count(n) => n,
+            Self::Percent(p) => total * p as usize / 100,
+        }
+    }
+}
+
+/// Staged rollout settings for multi-host exec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RolloutPlan {
+    /// Run the first host alone before any batch.
+    pub canary: bool,
+    /// Hosts per batch after the canary.
+    pub batch_size: HostCount,
+    /// Wait between batches.
+    pub pause: Duration,
+    /// Failures tolerated within one batch before the rollout halts.
+    pub max_failures: HostCount,
+}
+
+impl RolloutPlan {
+    /// Split `hosts` into consecutive batches: an optional single-host canary,
+    /// then chunks of `batch_size` (at least one host each).
+    pub fn batches<T: Clone>(&self, hosts: &[T]) -> Vec<Vec<T>> {
+        let mut batches = Vec::new();
+        let mut rest = hosts;
+
+        if self.canary && !rest.is_empty() {
+            batches.push(vec![rest[0].clone()]);
+            rest = &rest[1..];
+        }
+
+        let size = self.batch_size.resolve_ceil(rest.len()).max(1);
+        batches.extend(rest.chunks(size).map(<[T]>::to_vec));
+        batches
+    }
+
+    /// Whether `failures` in a batch of `batch_len` hosts should halt the rollout.
+    pub fn exceeds_threshold(&self, failures: usize, batch_len: usize) -> bool {
+        failures > self.max_failures.resolve_floor(batch_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(canary: bool, batch_size: HostCount, max_failures: HostCount) -> RolloutPlan {
+        RolloutPlan {
+            canary,
+            batch_size,
+            pause: Duration::ZERO,
+            max_failures,
+        }
+    }
+
+    #[test]
+    fn test_parse_host_count() {
+        assert_eq!(HostCount::parse("5").unwrap(), HostCount::Count(5));
+        assert_eq!(HostCount::parse("25%").unwrap(), HostCount::Percent(25));
+        assert_eq!(HostCount::parse(" 100% ").unwrap(), HostCount::Percent(100));
+        assert!(HostCount::parse("0%").is_err());
+        assert!(HostCount::parse("150%").is_err());
+        assert!(HostCount::parse("many").is_err());
+    }
+
+    #[test]
+    fn test_batches_by_count() {
+        let p = plan(false, HostCount::Count(2), HostCount::Count(0));
+        assert_eq!(
+            p.batches(&[1, 2, 3, 4, 5]),
+            vec![vec![1, 2], vec![3, 4], vec![5]]
+        );
+    }
+
+    #[test]
+    fn test_batches_with_canary_and_percent() {
+        // Canary first, then 50% of the remaining 4 hosts per batch
+        let p = plan(true, HostCount::Percent(50), HostCount::Count(0));
+        assert_eq!(
+            p.batches(&[1, 2, 3, 4, 5]),
+            vec![vec![1], vec![2, 3], vec![4, 5]]
+        );
+    }
+
+    #[test]
+    fn test_batches_never_empty() {
+        let p = plan(false, HostCount::Count(0), HostCount::Count(0));
+        assert_eq!(p.batches(&[1, 2]), vec![vec![1], vec![2]]);
+
+        let p = plan(true, HostCount::Percent(10), HostCount::Count(0));
+        assert_eq!(p.batches(&[1, 2, 3]), vec![vec![1], vec![2], vec![3]]);
+        assert!(p.batches::<i32>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_exceeds_threshold() {
+        let p = plan(false, HostCount::Count(4), HostCount::Count(0));
+        assert!(!p.exceeds_threshold(0, 4));
+        assert!(p.exceeds_threshold(1, 4));
+
+        let p = plan(false, HostCount::Count(4), HostCount::Percent(25));
+        assert!(!p.exceeds_threshold(1, 4));
+        assert!(p.exceeds_threshold(2, 4));
+        // 25% of a single-host canary rounds down to zero tolerated failures
+        assert!(p.exceeds_threshold(1, 1));
+    }
+}
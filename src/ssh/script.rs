@@ -0,0 +1,165 @@
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+
+/// Default interpreter for `sshore exec --script`.
+pub const DEFAULT_INTERPRETER: &str = "sh";
+
+/// Shells that read a script from stdin with `-s` and take its arguments
+/// after `--`. Other interpreters (python3, perl, ruby, node) read a script
+/// from stdin when given `-` as the script path.
+const STDIN_SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh", "ash"];
+
+/// Largest local script, or buffered `--stdin` input, we are willing to send.
+const MAX_SCRIPT_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Read a local script to send over the exec channel's stdin.
+pub fn read_script(path: &Path) -> Result<Vec<u8>> {
+    let metadata = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read script {}", path.display()))?;
+    if !metadata.is_file() {
+        bail!("Script {} is not a regular file", path.display());
+    }
+    if metadata.len() > MAX_SCRIPT_BYTES {
+        bail!(
+            "Script {} is too large ({} bytes, limit {MAX_SCRIPT_BYTES})",
+            path.display(),
+            metadata.len()
+        );
+    }
+    std::fs::read(path).with_context(|| format!("Failed to read script {}", path.display()))
+}
+
+/// Buffer `--stdin` input for multi-host exec, which sends the same bytes to
+/// every host. Capped like scripts; larger inputs belong in a file pushed
+/// with `sshore scp` or streamed to one host at a time.
+pub fn read_fan_out_input(mut reader: impl Read) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader
+        .by_ref()
+        .take(MAX_SCRIPT_BYTES + 1)
+        .read_to_end(&mut buf)
+        .context("Failed to read stdin")?;
+    if buf.len() as u64 > MAX_SCRIPT_BYTES {
+        bail!(
+            "--stdin input is too large (limit {MAX_SCRIPT_BYTES} bytes); \
+             stream it to a single host instead"
+        );
+    }
+    Ok(buf)
+}
+
+/// Build the remote command that runs a script piped over stdin, e.g.
+/// `bash -s -- 'arg one' two` or `python3 - two`.
+pub fn remote_script_command(interpreter: &str, args: &[String]) -> String {
+    let mut command = if STDIN_SHELLS.contains(&interpreter_name(interpreter)) {
+        format!("{interpreter} -s --")
+    } else {
+        format!("{interpreter} -")
+    };
+    for arg in args {
+        command.push(' ');
+        command.push_str(&shell_quote(arg));
+    }
+    command
+}
+
+/// Basename of the program an interpreter string runs, looking through an
+/// `env` prefix and its options and `NAME=value` assignments, so
+/// `/usr/bin/env bash` is treated as `bash`.
+fn interpreter_name(interpreter: &str) -> &str {
+    let mut words = interpreter
+        .split_whitespace()
+        .map(|word| word.rsplit('/').next().unwrap_or(word));
+    let program = words.next().unwrap_or(DEFAULT_INTERPRETER);
+    if program != "env" {
+        return program;
+    }
+    words
+        .find(|word| !word.starts_with('-') && !word.contains('='))
+        .unwrap_or(program)
+}
+
+/// Quote `arg` for a POSIX shell. Plain words are left as-is; anything else
+/// is single-quoted, with embedded single quotes written as `'\''`.
+pub fn shell_quote(arg: &str) -> String {
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c));
+    if is_plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("plain-arg_1.0"), "plain-arg_1.0");
+        assert_eq!(shell_quote("two words"), "'two words'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+    }
+
+    #[test]
+    fn test_remote_script_command_shells() {
+        assert_eq!(remote_script_command("sh", &[]), "sh -s --");
+        assert_eq!(
+            remote_script_command("/bin/bash", &["--env".into(), "prod db".into()]),
+            "/bin/bash -s -- --env 'prod db'"
+        );
+    }
+
+    #[test]
+    fn test_remote_script_command_other_interpreters() {
+        assert_eq!(
+            remote_script_command("python3", &["x".into()]),
+            "python3 - x"
+        );
+        assert_eq!(remote_script_command("perl -w", &[]), "perl -w -");
+    }
+
+    #[test]
+    fn test_remote_script_command_env_prefix() {
+        assert_eq!(
+            remote_script_command("/usr/bin/env bash", &["x".into()]),
+            "/usr/bin/env bash -s -- x"
+        );
+        assert_eq!(
+            remote_script_command("env -i LANG=C sh", &[]),
+            "env -i LANG=C sh -s --"
+        );
+        assert_eq!(
+            remote_script_command("/usr/bin/env python3", &[]),
+            "/usr/bin/env python3 -"
+        );
+    }
+
+    #[test]
+    fn test_read_fan_out_input_limit() {
+        let input = read_fan_out_input(&b"SELECT 1;\n"[..]).unwrap();
+        assert_eq!(input, b"SELECT 1;\n");
+
+        let oversized = std::io::repeat(b'x').take(MAX_SCRIPT_BYTES + 1);
+        let err = read_fan_out_input(oversized).unwrap_err();
+        assert!(err.to_string().contains("too large"));
+    }
+
+    #[test]
+    fn test_read_script() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deploy.sh");
+        std::fs::write(&path, "echo hi\n").unwrap();
+        assert_eq!(read_script(&path).unwrap(), b"echo hi\n");
+
+        assert!(read_script(&dir.path().join("missing.sh")).is_err());
+        assert!(read_script(dir.path()).is_err());
+    }
+}
@@ -565,6 +565,9 @@ pub fn show_save_bookmark_form(
                         connect_timeout_secs: None,
                         ssh_options: std::collections::BTreeMap::new(),
                         profile: None,
+                        use_agent: None,
+                        certificate_file: None,
+                        tunnels: vec![],
                     };
                     return Ok(Some(bookmark));
                 }
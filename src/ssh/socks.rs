@@ -0,0 +1,287 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use anyhow::{Context, Result, bail};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Longest SOCKS4 user ID / SOCKS4a host name we accept before giving up.
+const MAX_SOCKS4_FIELD_LEN: usize = 255;
+
+/// SOCKS protocol version spoken by a client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocksVersion {
+    V4,
+    V5,
+}
+
+/// A CONNECT request read from a SOCKS client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocksRequest {
+    pub version: SocksVersion,
+    pub host: String,
+    pub port: u16,
+}
+
+/// Read a SOCKS4, SOCKS4a or SOCKS5 CONNECT request from a client.
+///
+/// For SOCKS5 this also performs method negotiation; only "no authentication"
+/// is offered, since the listener is meant for local use. Unsupported commands
+/// (BIND, UDP ASSOCIATE) are refused on the wire before returning an error.
+pub async fn read_request<S>(stream: &mut S) -> Result<SocksRequest>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let version = stream
+        .read_u8()
+        .await
+        .context("Failed to read SOCKS version")?;
+    match version {
+        4 => read_socks4_request(stream).await,
+        5 => read_socks5_request(stream).await,
+        other => bail!("Unsupported SOCKS version {other}"),
+    }
+}
+
+/// Tell the client whether the requested connection was established.
+pub async fn send_reply<S>(stream: &mut S, version: SocksVersion, success: bool) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let reply: &[u8] = match (version, success) {
+        // VN=0, CD=90 granted / 91 rejected, DSTPORT and DSTIP ignored by clients
+        (SocksVersion::V4, true) => &[0x00, 0x5A, 0, 0, 0, 0, 0, 0],
+        (SocksVersion::V4, false) => &[0x00, 0x5B, 0, 0, 0, 0, 0, 0],
+        // VER, REP (0 succeeded / 1 general failure), RSV, ATYP=IPv4, 0.0.0.0:0
+        (SocksVersion::V5, true) => &[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0],
+        (SocksVersion::V5, false) => &[0x05, 0x01, 0x00, 0x01, 0, 0, 0, 0, 0, 0],
+    };
+    stream.write_all(reply).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// SOCKS4/4a: CD DSTPORT DSTIP USERID NUL [HOST NUL]. The version byte has
+/// already been consumed.
+async fn read_socks4_request<S>(stream: &mut S) -> Result<SocksRequest>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let command = stream.read_u8().await?;
+    let port = stream.read_u16().await?;
+    let mut ip = [0u8; 4];
+    stream.read_exact(&mut ip).await?;
+    // User ID is unused; we don't authenticate local clients
+    read_nul_terminated(stream).await?;
+
+    if command != 0x01 {
+        send_reply(stream, SocksVersion::V4, false).await?;
+        bail!("Unsupported SOCKS4 command {command}");
+    }
+
+    // SOCKS4a: an address of 0.0.0.x (x != 0) means the host name follows
+    let host = if ip[..3] == [0, 0, 0] && ip[3] != 0 {
+        read_nul_terminated(stream).await?
+    } else {
+        Ipv4Addr::from(ip).to_string()
+    };
+
+    Ok(SocksRequest {
+        version: SocksVersion::V4,
+        host,
+        port,
+    })
+}
+
+/// SOCKS5 (RFC 1928): method negotiation followed by the request. The
+/// version byte of the greeting has already been consumed.
+async fn read_socks5_request<S>(stream: &mut S) -> Result<SocksRequest>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let method_count = stream.read_u8().await?;
+    let mut methods = vec![0u8; method_count as usize];
+    stream.read_exact(&mut methods).await?;
+
+    if !methods.contains(&0x00) {
+        // 0xFF: no acceptable methods
+        stream.write_all(&[0x05, 0xFF]).await?;
+        bail!("SOCKS5 client does not support unauthenticated access");
+    }
+    stream.write_all(&[0x05, 0x00]).await?;
+    stream.flush().await?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await?;
+    let [version, command, _reserved, address_type] = header;
+    if version != 5 {
+        bail!("Unexpected SOCKS version {version} in request");
+    }
+
+    let host = match address_type {
+        0x01 => {
+            let mut ip = [0u8; 4];
+            stream.read_exact(&mut ip).await?;
+            Ipv4Addr::from(ip).to_string()
+        }
+        0x03 => {
+            let len = stream.read_u8().await?;
+            let mut name = vec![0u8; len as usize];
+            stream.read_exact(&mut name).await?;
+            String::from_utf8(name).context("SOCKS5 host name is not valid UTF-8")?
+        }
+        0x04 => {
+            let mut ip = [0u8; 16];
+            stream.read_exact(&mut ip).await?;
+            Ipv6Addr::from(ip).to_string()
+        }
+        other => {
+            // 0x08: address type not supported
+            stream
+                .write_all(&[0x05, 0x08, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+                .await?;
+            bail!("Unsupported SOCKS5 address type {other}");
+        }
+    };
+    let port = stream.read_u16().await?;
+
+    if command != 0x01 {
+        // 0x07: command not supported
+        stream
+            .write_all(&[0x05, 0x07, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+            .await?;
+        bail!("Unsupported SOCKS5 command {command}");
+    }
+
+    Ok(SocksRequest {
+        version: SocksVersion::V5,
+        host,
+        port,
+    })
+}
+
+/// Read a NUL-terminated string (SOCKS4 user ID or SOCKS4a host name).
+async fn read_nul_terminated<S>(stream: &mut S) -> Result<String>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut bytes = Vec::new();
+    loop {
+        let byte = stream.read_u8().await?;
+        if byte == 0 {
+            break;
+        }
+        if bytes.len() >= MAX_SOCKS4_FIELD_LEN {
+            bail!("SOCKS4 field too long");
+        }
+        bytes.push(byte);
+    }
+    String::from_utf8(bytes).context("SOCKS4 field is not valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `client_bytes` to `read_request` and return its result together
+    /// with everything the server wrote back.
+    async fn run(client_bytes: &[u8]) -> (Result<SocksRequest>, Vec<u8>) {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        client.write_all(client_bytes).await.unwrap();
+        let result = read_request(&mut server).await;
+        drop(server);
+        let mut written = Vec::new();
+        client.read_to_end(&mut written).await.unwrap();
+        (result, written)
+    }
+
+    #[tokio::test]
+    async fn test_socks5_domain_request() {
+        let mut bytes = vec![0x05, 0x01, 0x00, 0x05, 0x01, 0x00, 0x03, 11];
+        bytes.extend_from_slice(b"example.com");
+        bytes.extend_from_slice(&443u16.to_be_bytes());
+
+        let (result, written) = run(&bytes).await;
+        assert_eq!(
+            result.unwrap(),
+            SocksRequest {
+                version: SocksVersion::V5,
+                host: "example.com".into(),
+                port: 443,
+            }
+        );
+        assert_eq!(written, vec![0x05, 0x00]);
+    }
+
+    #[tokio::test]
+    async fn test_socks5_ipv4_and_ipv6_requests() {
+        let bytes = [
+            0x05, 0x01, 0x00, 0x05, 0x01, 0x00, 0x01, 10, 0, 0, 5, 0x00, 0x16,
+        ];
+        let (result, _) = run(&bytes).await;
+        let request = result.unwrap();
+        assert_eq!((request.host.as_str(), request.port), ("10.0.0.5", 22));
+
+        let mut bytes = vec![0x05, 0x01, 0x00, 0x05, 0x01, 0x00, 0x04];
+        bytes.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        bytes.extend_from_slice(&8080u16.to_be_bytes());
+        let (result, _) = run(&bytes).await;
+        assert_eq!(result.unwrap().host, "::1");
+    }
+
+    #[tokio::test]
+    async fn test_socks5_requires_no_auth_method() {
+        // Client only offers username/password (0x02)
+        let (result, written) = run(&[0x05, 0x01, 0x02]).await;
+        assert!(result.is_err());
+        assert_eq!(written, vec![0x05, 0xFF]);
+    }
+
+    #[tokio::test]
+    async fn test_socks5_rejects_udp_associate() {
+        let bytes = [0x05, 0x01, 0x00, 0x05, 0x03, 0x00, 0x01, 0, 0, 0, 0, 0, 0];
+        let (result, written) = run(&bytes).await;
+        assert!(result.is_err());
+        assert_eq!(&written[2..4], &[0x05, 0x07]);
+    }
+
+    #[tokio::test]
+    async fn test_socks4_request() {
+        let mut bytes = vec![0x04, 0x01, 0x00, 0x50, 192, 168, 1, 10];
+        bytes.extend_from_slice(b"alice\0");
+        let (result, _) = run(&bytes).await;
+        assert_eq!(
+            result.unwrap(),
+            SocksRequest {
+                version: SocksVersion::V4,
+                host: "192.168.1.10".into(),
+                port: 80,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_socks4a_request_with_hostname() {
+        let mut bytes = vec![0x04, 0x01, 0x01, 0xBB, 0, 0, 0, 1];
+        bytes.extend_from_slice(b"\0intranet.local\0");
+        let (result, _) = run(&bytes).await;
+        let request = result.unwrap();
+        assert_eq!(request.host, "intranet.local");
+        assert_eq!(request.port, 443);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_version_rejected() {
+        let (result, _) = run(&[0x06, 0x01]).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_reply_bytes() {
+        let mut buf = Vec::new();
+        send_reply(&mut buf, SocksVersion::V5, true).await.unwrap();
+        assert_eq!(buf[..2], [0x05, 0x00]);
+
+        let mut buf = Vec::new();
+        send_reply(&mut buf, SocksVersion::V4, false).await.unwrap();
+        assert_eq!(buf[..2], [0x00, 0x5B]);
+    }
+}
@@ -183,7 +183,7 @@ fn reset_background() {
 }
 
 /// Parse a hex color string "#RRGGBB" into (r, g, b) components.
-fn parse_hex_rgb(hex: &str) -> Option<(u8, u8, u8)> {
+pub(crate) fn parse_hex_rgb(hex: &str) -> Option<(u8, u8, u8)> {
     let hex = hex.strip_prefix('#')?;
     if hex.len() != 6 {
         return None;
@@ -415,6 +415,9 @@ mod tests {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         }
     }
 
@@ -497,6 +500,9 @@ mod tests {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         };
         let result = render_tab_title("{name}", &bookmark, &settings);
         assert_eq!(result, "prod]0;hacked");
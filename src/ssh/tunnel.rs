@@ -12,9 +12,10 @@ use tokio::net::TcpListener;
 use tokio::sync::Mutex;
 use tokio::sync::oneshot;
 
-use crate::config::model::{AppConfig, validate_hostname};
+use crate::config::model::{AppConfig, SavedTunnel, validate_hostname};
 
 use super::client::RemoteForwardMap;
+use super::socks;
 
 /// Shared handle to an SSH session, wrapped for concurrent access.
 type SharedSession = Arc<Mutex<russh::client::Handle<super::client::SshoreHandler>>>;
@@ -69,9 +70,15 @@ pub const RECONNECT_MAX_DELAY_SECS: u64 = 60;
 
 /// Backoff multiplier between reconnect attempts.
 pub const RECONNECT_BACKOFF_MULTIPLIER: u64 = 2;
+/// Default local bind address for -L forwards (loopback-only for safety).
+const LOCAL_FORWARD_BIND_ADDR: &str = "127.0.0.1";
+
 /// Default remote bind address for -R forwards (loopback-only for safety).
 const REMOTE_FORWARD_BIND_ADDR: &str = "127.0.0.1";
 
+/// Default local bind address for -D SOCKS listeners (loopback-only for safety).
+const DYNAMIC_FORWARD_BIND_ADDR: &str = "127.0.0.1";
+
 // ---------------------------------------------------------------------------
 // Types
 // ---------------------------------------------------------------------------
@@ -82,6 +89,8 @@ const REMOTE_FORWARD_BIND_ADDR: &str = "127.0.0.1";
 pub enum ForwardDirection {
     Local,
     Remote,
+    /// SOCKS proxy: the destination comes from each client request.
+    Dynamic,
 }
 
 impl std::fmt::Display for ForwardDirection {
@@ -89,25 +98,41 @@ impl std::fmt::Display for ForwardDirection {
         match self {
             ForwardDirection::Local => write!(f, "-L"),
             ForwardDirection::Remote => write!(f, "-R"),
+            ForwardDirection::Dynamic => write!(f, "-D"),
         }
     }
 }
 
 /// A parsed port-forwarding specification (e.g. "5432:localhost:5432").
+///
+/// Dynamic (-D) forwards only use `local_port` and `bind_address`; the
+/// remote side is chosen per SOCKS request.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ForwardSpec {
     pub direction: ForwardDirection,
     pub local_port: u16,
     pub remote_host: String,
     pub remote_port: u16,
+    /// Listen address (local for -L/-D, on the server for -R); `None` means loopback.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind_address: Option<String>,
 }
 
 impl std::fmt::Display for ForwardSpec {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ", self.direction)?;
+        match &self.bind_address {
+            Some(bind) if bind.contains(':') => write!(f, "[{bind}]:")?,
+            Some(bind) => write!(f, "{bind}:")?,
+            None => {}
+        }
+        if self.direction == ForwardDirection::Dynamic {
+            return write!(f, "{}", self.local_port);
+        }
         write!(
             f,
-            "{} {}:{}:{}",
-            self.direction, self.local_port, self.remote_host, self.remote_port
+            "{}:{}:{}",
+            self.local_port, self.remote_host, self.remote_port
         )
     }
 }
@@ -132,8 +157,17 @@ impl std::fmt::Display for TunnelStatus {
 }
 
 /// A single tunnel entry in the state file.
+///
+/// Each tunnel process owns exactly one entry, so several tunnels to the same
+/// bookmark can run side by side and be stopped independently.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TunnelEntry {
+    /// Short unique ID, derived from the process ID and start time.
+    #[serde(default)]
+    pub id: String,
+    /// Optional user-chosen name (`tunnel start --name`), unique per bookmark.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub bookmark: String,
     pub forwards: Vec<ForwardSpec>,
     pub persistent: bool,
@@ -144,6 +178,45 @@ pub struct TunnelEntry {
     pub status: TunnelStatus,
 }
 
+impl TunnelEntry {
+    /// Create an entry for a tunnel run by the current process.
+    pub fn new(
+        bookmark: &str,
+        name: Option<&str>,
+        forwards: &[ForwardSpec],
+        persistent: bool,
+    ) -> Self {
+        let pid = std::process::id();
+        let started_at = Utc::now();
+        Self {
+            id: tunnel_id(pid, started_at),
+            name: name.map(str::to_string),
+            bookmark: bookmark.to_string(),
+            forwards: forwards.to_vec(),
+            persistent,
+            pid,
+            started_at,
+            reconnect_count: 0,
+            status: TunnelStatus::Connected,
+        }
+    }
+
+    /// Name for messages: the tunnel's name if it has one, otherwise its ID.
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// Derive a short tunnel ID from the owning process and its start time.
+///
+/// Deterministic so entries written before IDs existed get a stable ID on load.
+pub fn tunnel_id(pid: u32, started_at: DateTime<Utc>) -> String {
+    use sha2::{Digest, Sha256};
+
+    let hash = Sha256::digest(format!("{pid}:{}", started_at.to_rfc3339()));
+    hash[..4].iter().map(|b| format!("{b:02x}")).collect()
+}
+
 /// Top-level state file holding all active tunnels.
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct TunnelState {
@@ -157,21 +230,34 @@ pub struct TunnelState {
 
 /// Parse a forward spec string like "5432:localhost:5432" into a `ForwardSpec`.
 ///
-/// Format: `local_port:remote_host:remote_port`
+/// Format: `[bind_address:]local_port:remote_host:remote_port`
 ///
 /// For -L: local_port is bound locally, traffic goes to remote_host:remote_port via SSH.
 /// For -R: local_port is bound on the remote, traffic comes back to remote_host:remote_port locally.
+/// The bind address works as for -D: `*` means all interfaces and IPv6
+/// addresses are bracketed; without one, the listener stays on loopback.
 pub fn parse_forward_spec(spec: &str, direction: ForwardDirection) -> Result<ForwardSpec> {
     if spec.is_empty() {
         bail!("Forward spec cannot be empty");
     }
 
-    let parts: Vec<&str> = spec.split(':').collect();
+    let (bind, rest) = match spec.strip_prefix('[').and_then(|s| s.split_once("]:")) {
+        Some((bind, rest)) => (Some(bind), rest),
+        None => (None, spec),
+    };
+    let mut parts: Vec<&str> = rest.split(':').collect();
+    let bind = match (bind, parts.len()) {
+        (None, 4) => Some(parts.remove(0)),
+        (bind, _) => bind,
+    };
     if parts.len() != 3 {
         bail!(
-            "Invalid forward spec '{spec}': expected format local_port:host:remote_port (3 parts separated by ':')"
+            "Invalid forward spec '{spec}': expected format [bind_address:]local_port:host:remote_port"
         );
     }
+    let bind_address = bind
+        .map(|bind| parse_bind_address(bind, spec))
+        .transpose()?;
 
     let local_port = parse_port(parts[0], spec)?;
     let remote_host = parts[1];
@@ -184,6 +270,55 @@ pub fn parse_forward_spec(spec: &str, direction: ForwardDirection) -> Result<For
         local_port,
         remote_host: remote_host.to_string(),
         remote_port,
+        bind_address,
+    })
+}
+
+/// Validate a forward's bind address (brackets already stripped).
+/// As in OpenSSH, `*` listens on all interfaces.
+fn parse_bind_address(bind: &str, spec: &str) -> Result<String> {
+    if bind == "*" {
+        return Ok("0.0.0.0".to_string());
+    }
+    if bind.is_empty() {
+        bail!("Invalid forward spec '{spec}': empty bind address");
+    }
+    validate_hostname(bind)
+        .with_context(|| format!("Invalid bind address in forward spec '{spec}'"))?;
+    Ok(bind.to_string())
+}
+
+/// Parse a dynamic (-D) forward spec like "1080" or "0.0.0.0:1080".
+///
+/// Format: `[bind_address:]port`. As in OpenSSH, a bind address of `*` listens
+/// on all interfaces; IPv6 addresses must be bracketed (`[::1]:1080`).
+pub fn parse_dynamic_spec(spec: &str) -> Result<ForwardSpec> {
+    if spec.is_empty() {
+        bail!("Forward spec cannot be empty");
+    }
+
+    let (bind, port) = match spec.rsplit_once(':') {
+        Some((bind, port)) => (Some(bind), port),
+        None => (None, spec),
+    };
+    let local_port = parse_port(port, spec)?;
+
+    let bind_address = bind
+        .map(|bind| {
+            let bind = bind
+                .strip_prefix('[')
+                .and_then(|b| b.strip_suffix(']'))
+                .unwrap_or(bind);
+            parse_bind_address(bind, spec)
+        })
+        .transpose()?;
+
+    Ok(ForwardSpec {
+        direction: ForwardDirection::Dynamic,
+        local_port,
+        remote_host: String::new(),
+        remote_port: 0,
+        bind_address,
     })
 }
 
@@ -200,6 +335,79 @@ fn parse_port(s: &str, spec: &str) -> Result<u16> {
     Ok(port as u16)
 }
 
+/// Parse all forward specs of a saved tunnel.
+pub fn saved_tunnel_forwards(tunnel: &SavedTunnel) -> Result<Vec<ForwardSpec>> {
+    let mut forwards = Vec::new();
+    for spec in &tunnel.local {
+        forwards.push(parse_forward_spec(spec, ForwardDirection::Local)?);
+    }
+    for spec in &tunnel.remote {
+        forwards.push(parse_forward_spec(spec, ForwardDirection::Remote)?);
+    }
+    for spec in &tunnel.dynamic {
+        forwards.push(parse_dynamic_spec(spec)?);
+    }
+    if forwards.is_empty() {
+        bail!("Saved tunnel '{}' has no forwards", tunnel.name);
+    }
+    Ok(forwards)
+}
+
+// ---------------------------------------------------------------------------
+// Saved tunnels
+// ---------------------------------------------------------------------------
+
+/// A saved tunnel together with the bookmark it belongs to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeclaredTunnel {
+    pub bookmark: String,
+    pub tunnel: SavedTunnel,
+}
+
+/// All saved tunnels in the config, with profile tunnels resolved per bookmark.
+pub fn declared_tunnels(config: &AppConfig) -> Vec<DeclaredTunnel> {
+    config
+        .bookmarks
+        .iter()
+        .flat_map(|b| {
+            b.effective_tunnels(&config.profiles)
+                .into_iter()
+                .map(|tunnel| DeclaredTunnel {
+                    bookmark: b.name.clone(),
+                    tunnel,
+                })
+        })
+        .collect()
+}
+
+/// Select saved tunnels for `tunnel up`/`tunnel down`.
+///
+/// Without a target every tunnel is returned. A target selects the tunnels
+/// with that name, or failing that all tunnels of the bookmark with that name.
+pub fn select_declared_tunnels(
+    declared: Vec<DeclaredTunnel>,
+    target: Option<&str>,
+) -> Result<Vec<DeclaredTunnel>> {
+    let Some(target) = target else {
+        return Ok(declared);
+    };
+
+    let (named, rest): (Vec<_>, Vec<_>) =
+        declared.into_iter().partition(|d| d.tunnel.name == target);
+    if !named.is_empty() {
+        return Ok(named);
+    }
+
+    let by_bookmark: Vec<DeclaredTunnel> = rest
+        .into_iter()
+        .filter(|d| d.bookmark.eq_ignore_ascii_case(target))
+        .collect();
+    if by_bookmark.is_empty() {
+        bail!("No saved tunnel or bookmark with saved tunnels named '{target}'");
+    }
+    Ok(by_bookmark)
+}
+
 // ---------------------------------------------------------------------------
 // State file I/O
 // ---------------------------------------------------------------------------
@@ -224,9 +432,14 @@ pub fn load_tunnel_state_from(path: &PathBuf) -> Result<TunnelState> {
     let content = fs::read_to_string(path)
         .with_context(|| format!("Failed to read tunnel state: {}", path.display()))?;
 
-    let state: TunnelState = serde_json::from_str(&content)
+    let mut state: TunnelState = serde_json::from_str(&content)
         .with_context(|| format!("Failed to parse tunnel state: {}", path.display()))?;
 
+    // Entries from older versions have no ID
+    for entry in state.tunnels.iter_mut().filter(|t| t.id.is_empty()) {
+        entry.id = tunnel_id(entry.pid, entry.started_at);
+    }
+
     Ok(state)
 }
 
@@ -280,27 +493,23 @@ pub fn save_tunnel_state_to(state: &TunnelState, path: &PathBuf) -> Result<()> {
 /// Register a new tunnel entry in the state file.
 pub fn register_tunnel(entry: TunnelEntry) -> Result<()> {
     let mut state = load_tunnel_state()?;
-    // Remove any existing entry for this bookmark
-    state.tunnels.retain(|t| t.bookmark != entry.bookmark);
+    // Remove any existing entry with this ID
+    state.tunnels.retain(|t| t.id != entry.id);
     state.tunnels.push(entry);
     save_tunnel_state(&state)
 }
 
-/// Remove a tunnel entry for the given bookmark from the state file.
-pub fn unregister_tunnel(bookmark: &str) -> Result<()> {
+/// Remove the tunnel entry with the given ID from the state file.
+pub fn unregister_tunnel(id: &str) -> Result<()> {
     let mut state = load_tunnel_state()?;
-    state.tunnels.retain(|t| t.bookmark != bookmark);
+    state.tunnels.retain(|t| t.id != id);
     save_tunnel_state(&state)
 }
 
-/// Update tunnel status and reconnect count for a bookmark.
-pub fn update_tunnel_status(
-    bookmark: &str,
-    status: TunnelStatus,
-    reconnect_count: u32,
-) -> Result<()> {
+/// Update tunnel status and reconnect count for the tunnel with the given ID.
+pub fn update_tunnel_status(id: &str, status: TunnelStatus, reconnect_count: u32) -> Result<()> {
     let mut state = load_tunnel_state()?;
-    if let Some(entry) = state.tunnels.iter_mut().find(|t| t.bookmark == bookmark) {
+    if let Some(entry) = state.tunnels.iter_mut().find(|t| t.id == id) {
         entry.status = status;
         entry.reconnect_count = reconnect_count;
     }
@@ -389,6 +598,57 @@ pub fn pid_matches_tunnel_entry(_pid: u32, _expected_started_at: DateTime<Utc>)
     true
 }
 
+/// Find a running tunnel for `bookmark` with the given name.
+pub fn find_named_tunnel<'a>(
+    state: &'a TunnelState,
+    bookmark: &str,
+    name: &str,
+) -> Option<&'a TunnelEntry> {
+    state
+        .tunnels
+        .iter()
+        .find(|t| t.bookmark.eq_ignore_ascii_case(bookmark) && t.name.as_deref() == Some(name))
+}
+
+/// Resolve a `tunnel stop` target to the entries it refers to.
+///
+/// The target is tried as a tunnel ID, then as a tunnel name, then as a
+/// bookmark name (selecting all of that bookmark's tunnels). A name shared by
+/// tunnels of different bookmarks is ambiguous and must be given as an ID.
+pub fn resolve_tunnel_target<'a>(
+    state: &'a TunnelState,
+    target: &str,
+) -> Result<Vec<&'a TunnelEntry>> {
+    if let Some(entry) = state.tunnels.iter().find(|t| t.id == target) {
+        return Ok(vec![entry]);
+    }
+
+    let named: Vec<&TunnelEntry> = state
+        .tunnels
+        .iter()
+        .filter(|t| t.name.as_deref() == Some(target))
+        .collect();
+    if named.len() > 1 {
+        let ids: Vec<String> = named
+            .iter()
+            .map(|t| format!("{} ({})", t.id, t.bookmark))
+            .collect();
+        bail!(
+            "Tunnel name '{target}' is used by several bookmarks; stop one by ID: {}",
+            ids.join(", ")
+        );
+    }
+    if !named.is_empty() {
+        return Ok(named);
+    }
+
+    Ok(state
+        .tunnels
+        .iter()
+        .filter(|t| t.bookmark.eq_ignore_ascii_case(target))
+        .collect())
+}
+
 /// Get the set of bookmark names that have active tunnels.
 pub fn active_tunnel_bookmarks() -> HashSet<String> {
     let mut state = load_tunnel_state().unwrap_or_default();
@@ -406,14 +666,21 @@ async fn run_local_forward(
     session: SharedSession,
     spec: &ForwardSpec,
 ) -> Result<LocalForwardRuntime> {
-    let addr = format!("127.0.0.1:{}", spec.local_port);
-    let listener = TcpListener::bind(&addr)
+    let bind = spec
+        .bind_address
+        .as_deref()
+        .unwrap_or(LOCAL_FORWARD_BIND_ADDR);
+    let listener = TcpListener::bind((bind, spec.local_port))
         .await
         .with_context(|| format!("Failed to bind local port {}", spec.local_port))?;
 
     eprintln!(
         "Forwarding {} → {}:{}",
-        addr, spec.remote_host, spec.remote_port
+        listener
+            .local_addr()
+            .map_or_else(|_| format!("{bind}:{}", spec.local_port), |a| a.to_string()),
+        spec.remote_host,
+        spec.remote_port
     );
 
     let remote_host = spec.remote_host.clone();
@@ -471,6 +738,105 @@ async fn run_local_forward(
     })
 }
 
+/// Run a dynamic port forward (-D): binds a local SOCKS4/4a/5 listener and,
+/// for each client request, opens a direct-tcpip channel to the requested
+/// destination through the SSH session.
+async fn run_dynamic_forward(
+    session: SharedSession,
+    spec: &ForwardSpec,
+) -> Result<LocalForwardRuntime> {
+    let bind = spec
+        .bind_address
+        .as_deref()
+        .unwrap_or(DYNAMIC_FORWARD_BIND_ADDR);
+    let listener = TcpListener::bind((bind, spec.local_port))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to bind SOCKS listener on {bind}:{}",
+                spec.local_port
+            )
+        })?;
+
+    eprintln!("SOCKS proxy listening on {bind}:{}", spec.local_port);
+
+    let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
+
+    let task = tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = &mut shutdown_rx => {
+                    break;
+                }
+                accept_result = listener.accept() => {
+                    let (mut tcp_stream, _peer) = match accept_result {
+                        Ok(conn) => conn,
+                        Err(e) => {
+                            eprintln!("Warning: failed to accept connection: {e}");
+                            continue;
+                        }
+                    };
+
+                    let session = Arc::clone(&session);
+
+                    tokio::spawn(async move {
+                        let request = match socks::read_request(&mut tcp_stream).await {
+                            Ok(request) => request,
+                            Err(e) => {
+                                tracing::debug!(error = %e, "tunnel: bad SOCKS request");
+                                return;
+                            }
+                        };
+
+                        let channel = {
+                            let handle = session.lock().await;
+                            handle
+                                .channel_open_direct_tcpip(
+                                    &request.host,
+                                    request.port as u32,
+                                    "127.0.0.1",
+                                    0,
+                                )
+                                .await
+                        };
+
+                        match channel {
+                            Ok(channel) => {
+                                if socks::send_reply(&mut tcp_stream, request.version, true)
+                                    .await
+                                    .is_err()
+                                {
+                                    return;
+                                }
+                                let mut channel_stream = channel.into_stream();
+                                if let Err(e) =
+                                    copy_bidirectional(&mut tcp_stream, &mut channel_stream).await
+                                {
+                                    let _ = e; // Normal when either side closes
+                                }
+                            }
+                            Err(e) => {
+                                eprintln!(
+                                    "Warning: failed to open direct-tcpip channel to {}:{}: {e}",
+                                    request.host, request.port
+                                );
+                                let _ =
+                                    socks::send_reply(&mut tcp_stream, request.version, false)
+                                        .await;
+                            }
+                        }
+                    });
+                }
+            }
+        }
+    });
+
+    Ok(LocalForwardRuntime {
+        shutdown_tx: Some(shutdown_tx),
+        task,
+    })
+}
+
 /// Stop local forward listener tasks and wait for them to exit.
 async fn stop_local_forwards(local_forwards: &mut Vec<LocalForwardRuntime>) {
     for forward in local_forwards.iter_mut() {
@@ -494,10 +860,14 @@ async fn setup_remote_forward(
 ) -> Result<()> {
     // Ask the server to listen. The returned port is the actual bound port
     // (may differ from requested if the server chose one).
+    let bind = spec
+        .bind_address
+        .as_deref()
+        .unwrap_or(REMOTE_FORWARD_BIND_ADDR);
     let bound_port = {
         let mut handle = session.lock().await;
         handle
-            .tcpip_forward(REMOTE_FORWARD_BIND_ADDR, spec.local_port as u32)
+            .tcpip_forward(bind, spec.local_port as u32)
             .await
             .with_context(|| {
                 format!(
@@ -526,14 +896,14 @@ async fn setup_remote_forward(
     {
         let mut map = remote_map.lock().await;
         map.insert(
-            (REMOTE_FORWARD_BIND_ADDR.to_string(), actual_port),
+            (bind.to_string(), actual_port),
             (spec.remote_host.clone(), spec.remote_port),
         );
     }
 
     eprintln!(
-        "Remote forward: remote:{} → {}:{}",
-        actual_port, spec.remote_host, spec.remote_port
+        "Remote forward: remote {}:{} → {}:{}",
+        bind, actual_port, spec.remote_host, spec.remote_port
     );
 
     Ok(())
@@ -543,9 +913,11 @@ async fn setup_remote_forward(
 pub async fn run_foreground(
     config: &AppConfig,
     bookmark_index: usize,
+    name: Option<&str>,
     forwards: &[ForwardSpec],
 ) -> Result<()> {
-    let (session, remote_map) = super::establish_tunnel_session(config, bookmark_index).await?;
+    let (session, remote_map) =
+        super::establish_tunnel_session(config, bookmark_index, false).await?;
     let session = Arc::new(Mutex::new(session));
     let mut local_forwards = Vec::new();
 
@@ -557,29 +929,30 @@ pub async fn run_foreground(
             ForwardDirection::Remote => {
                 setup_remote_forward(&session, spec, &remote_map).await?;
             }
+            ForwardDirection::Dynamic => {
+                local_forwards.push(run_dynamic_forward(Arc::clone(&session), spec).await?);
+            }
         }
     }
 
     // Register in state file
-    let entry = TunnelEntry {
-        bookmark: config.bookmarks[bookmark_index].name.clone(),
-        forwards: forwards.to_vec(),
-        persistent: false,
-        pid: std::process::id(),
-        started_at: Utc::now(),
-        reconnect_count: 0,
-        status: TunnelStatus::Connected,
-    };
+    let entry = TunnelEntry::new(
+        &config.bookmarks[bookmark_index].name,
+        name,
+        forwards,
+        false,
+    );
+    let tunnel_id = entry.id.clone();
     register_tunnel(entry)?;
 
-    eprintln!("Tunnel active. Press Ctrl+C to stop.");
+    eprintln!("Tunnel {tunnel_id} active. Press Ctrl+C to stop.");
 
     // Wait for a termination signal from the terminal or `sshore tunnel stop`.
     wait_for_termination_signal().await?;
 
     eprintln!("\nShutting down tunnel...");
     stop_local_forwards(&mut local_forwards).await;
-    unregister_tunnel(&config.bookmarks[bookmark_index].name)?;
+    unregister_tunnel(&tunnel_id)?;
 
     Ok(())
 }
@@ -589,26 +962,19 @@ pub async fn run_foreground(
 pub async fn run_daemon_loop(
     config: &AppConfig,
     bookmark_index: usize,
+    name: Option<&str>,
     forwards: &[ForwardSpec],
 ) -> Result<()> {
-    let bookmark_name = config.bookmarks[bookmark_index].name.clone();
     let mut delay_secs = RECONNECT_INITIAL_DELAY_SECS;
     let mut reconnect_count: u32 = 0;
 
     // Register in state file
-    let entry = TunnelEntry {
-        bookmark: bookmark_name.clone(),
-        forwards: forwards.to_vec(),
-        persistent: true,
-        pid: std::process::id(),
-        started_at: Utc::now(),
-        reconnect_count: 0,
-        status: TunnelStatus::Connected,
-    };
+    let entry = TunnelEntry::new(&config.bookmarks[bookmark_index].name, name, forwards, true);
+    let tunnel_id = entry.id.clone();
     register_tunnel(entry)?;
 
     loop {
-        let result = run_single_session(config, bookmark_index, forwards).await;
+        let result = run_single_session(config, bookmark_index, &tunnel_id, forwards).await;
 
         match result {
             Ok(()) => {
@@ -621,11 +987,8 @@ pub async fn run_daemon_loop(
                     "Tunnel disconnected: {e:#}. Reconnecting in {delay_secs}s (attempt {reconnect_count})..."
                 );
 
-                let _ = update_tunnel_status(
-                    &bookmark_name,
-                    TunnelStatus::Reconnecting,
-                    reconnect_count,
-                );
+                let _ =
+                    update_tunnel_status(&tunnel_id, TunnelStatus::Reconnecting, reconnect_count);
 
                 // Check for termination while sleeping
                 tokio::select! {
@@ -644,7 +1007,7 @@ pub async fn run_daemon_loop(
         }
     }
 
-    unregister_tunnel(&bookmark_name)?;
+    unregister_tunnel(&tunnel_id)?;
     Ok(())
 }
 
@@ -652,14 +1015,16 @@ pub async fn run_daemon_loop(
 async fn run_single_session(
     config: &AppConfig,
     bookmark_index: usize,
+    tunnel_id: &str,
     forwards: &[ForwardSpec],
 ) -> Result<()> {
-    let (session, remote_map) = super::establish_tunnel_session(config, bookmark_index).await?;
+    // Daemon has no terminal: keychain-only auth
+    let (session, remote_map) =
+        super::establish_tunnel_session(config, bookmark_index, true).await?;
     let session = Arc::new(Mutex::new(session));
     let mut local_forwards = Vec::new();
 
-    let bookmark_name = &config.bookmarks[bookmark_index].name;
-    let _ = update_tunnel_status(bookmark_name, TunnelStatus::Connected, 0);
+    let _ = update_tunnel_status(tunnel_id, TunnelStatus::Connected, 0);
 
     for spec in forwards {
         match spec.direction {
@@ -669,6 +1034,9 @@ async fn run_single_session(
             ForwardDirection::Remote => {
                 setup_remote_forward(&session, spec, &remote_map).await?;
             }
+            ForwardDirection::Dynamic => {
+                local_forwards.push(run_dynamic_forward(Arc::clone(&session), spec).await?);
+            }
         }
     }
 
@@ -761,6 +1129,26 @@ mod tests {
         assert!(parse_forward_spec("5432:localhost:5432:extra", ForwardDirection::Local).is_err());
     }
 
+    #[test]
+    fn test_parse_forward_spec_with_bind_address() {
+        let spec =
+            parse_forward_spec("0.0.0.0:8080:web.internal:80", ForwardDirection::Local).unwrap();
+        assert_eq!(spec.bind_address.as_deref(), Some("0.0.0.0"));
+        assert_eq!(spec.local_port, 8080);
+        assert_eq!(spec.remote_host, "web.internal");
+        assert_eq!(spec.to_string(), "-L 0.0.0.0:8080:web.internal:80");
+
+        let spec = parse_forward_spec("*:9000:localhost:9000", ForwardDirection::Remote).unwrap();
+        assert_eq!(spec.bind_address.as_deref(), Some("0.0.0.0"));
+
+        let spec = parse_forward_spec("[::1]:8080:localhost:80", ForwardDirection::Local).unwrap();
+        assert_eq!(spec.bind_address.as_deref(), Some("::1"));
+        assert_eq!(spec.to_string(), "-L [::1]:8080:localhost:80");
+
+        assert!(parse_forward_spec(":8080:localhost:80", ForwardDirection::Local).is_err());
+        assert!(parse_forward_spec("bad;host:8080:localhost:80", ForwardDirection::Local).is_err());
+    }
+
     #[test]
     fn test_parse_forward_spec_invalid_port_zero() {
         assert!(parse_forward_spec("0:localhost:5432", ForwardDirection::Local).is_err());
@@ -793,10 +1181,51 @@ mod tests {
             local_port: 5432,
             remote_host: "localhost".into(),
             remote_port: 5432,
+            bind_address: None,
         };
         assert_eq!(spec.to_string(), "-L 5432:localhost:5432");
     }
 
+    #[test]
+    fn test_parse_dynamic_spec_port_only() {
+        let spec = parse_dynamic_spec("1080").unwrap();
+        assert_eq!(spec.direction, ForwardDirection::Dynamic);
+        assert_eq!(spec.local_port, 1080);
+        assert_eq!(spec.bind_address, None);
+        assert_eq!(spec.to_string(), "-D 1080");
+    }
+
+    #[test]
+    fn test_parse_dynamic_spec_with_bind_address() {
+        let spec = parse_dynamic_spec("0.0.0.0:1080").unwrap();
+        assert_eq!(spec.bind_address.as_deref(), Some("0.0.0.0"));
+        assert_eq!(spec.to_string(), "-D 0.0.0.0:1080");
+
+        let spec = parse_dynamic_spec("*:1080").unwrap();
+        assert_eq!(spec.bind_address.as_deref(), Some("0.0.0.0"));
+
+        let spec = parse_dynamic_spec("[::1]:1080").unwrap();
+        assert_eq!(spec.bind_address.as_deref(), Some("::1"));
+        assert_eq!(spec.to_string(), "-D [::1]:1080");
+    }
+
+    #[test]
+    fn test_parse_dynamic_spec_invalid() {
+        assert!(parse_dynamic_spec("").is_err());
+        assert!(parse_dynamic_spec("0").is_err());
+        assert!(parse_dynamic_spec("socks").is_err());
+        assert!(parse_dynamic_spec(":1080").is_err());
+        assert!(parse_dynamic_spec("bad;host:1080").is_err());
+    }
+
+    #[test]
+    fn test_tunnel_state_without_bind_address_loads() {
+        // State files written before -D support have no bind_address field
+        let json = r#"{"tunnels":[{"bookmark":"db","forwards":[{"direction":"local","local_port":5432,"remote_host":"localhost","remote_port":5432}],"persistent":false,"pid":1,"started_at":"2026-01-01T00:00:00Z","status":"connected"}]}"#;
+        let state: TunnelState = serde_json::from_str(json).unwrap();
+        assert_eq!(state.tunnels[0].forwards[0].bind_address, None);
+    }
+
     #[test]
     fn test_tunnel_state_roundtrip() {
         let dir = tempfile::tempdir().unwrap();
@@ -804,12 +1233,15 @@ mod tests {
 
         let state = TunnelState {
             tunnels: vec![TunnelEntry {
+                id: "a1b2c3d4".into(),
+                name: None,
                 bookmark: "test-server".into(),
                 forwards: vec![ForwardSpec {
                     direction: ForwardDirection::Local,
                     local_port: 5432,
                     remote_host: "localhost".into(),
                     remote_port: 5432,
+                    bind_address: None,
                 }],
                 persistent: true,
                 pid: 12345,
@@ -824,7 +1256,7 @@ mod tests {
 
         assert_eq!(loaded.tunnels.len(), 1);
         assert_eq!(loaded.tunnels[0].bookmark, "test-server");
-        assert_eq!(loaded.tunnels[0].persistent, true);
+        assert!(loaded.tunnels[0].persistent);
         assert_eq!(loaded.tunnels[0].status, TunnelStatus::Connected);
     }
 
@@ -842,6 +1274,8 @@ mod tests {
         let mut state = TunnelState::default();
 
         let entry = TunnelEntry {
+            id: "a1b2c3d4".into(),
+            name: None,
             bookmark: "test-server".into(),
             forwards: vec![],
             persistent: false,
@@ -865,6 +1299,8 @@ mod tests {
     fn test_cleanup_stale_tunnels() {
         let mut state = TunnelState {
             tunnels: vec![TunnelEntry {
+                id: "a1b2c3d4".into(),
+                name: None,
                 bookmark: "dead-tunnel".into(),
                 forwards: vec![],
                 persistent: false,
@@ -897,6 +1333,8 @@ mod tests {
     fn test_update_tunnel_status() {
         let mut state = TunnelState {
             tunnels: vec![TunnelEntry {
+                id: "a1b2c3d4".into(),
+                name: None,
                 bookmark: "test-server".into(),
                 forwards: vec![],
                 persistent: true,
@@ -921,6 +1359,146 @@ mod tests {
         assert_eq!(state.tunnels[0].reconnect_count, 3);
     }
 
+    fn entry(id: &str, name: Option<&str>, bookmark: &str) -> TunnelEntry {
+        TunnelEntry {
+            id: id.into(),
+            name: name.map(str::to_string),
+            bookmark: bookmark.into(),
+            forwards: vec![],
+            persistent: true,
+            pid: 12345,
+            started_at: Utc::now(),
+            reconnect_count: 0,
+            status: TunnelStatus::Connected,
+        }
+    }
+
+    #[test]
+    fn test_tunnel_id_is_stable_and_short() {
+        let started_at = Utc::now();
+        let id = tunnel_id(4242, started_at);
+        assert_eq!(id.len(), 8);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(id, tunnel_id(4242, started_at));
+        assert_ne!(id, tunnel_id(4243, started_at));
+    }
+
+    #[test]
+    fn test_tunnel_state_backfills_missing_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tunnels.json");
+        let json = r#"{"tunnels":[{"bookmark":"db","forwards":[],"persistent":true,"pid":77,"started_at":"2026-01-01T00:00:00Z","status":"connected"}]}"#;
+        fs::write(&path, json).unwrap();
+
+        let state = load_tunnel_state_from(&path).unwrap();
+        let loaded = &state.tunnels[0];
+        assert_eq!(loaded.id, tunnel_id(77, loaded.started_at));
+        assert_eq!(loaded.name, None);
+        assert_eq!(loaded.label(), loaded.id);
+    }
+
+    #[test]
+    fn test_resolve_tunnel_target() {
+        let state = TunnelState {
+            tunnels: vec![
+                entry("11111111", Some("db"), "bastion"),
+                entry("22222222", Some("metrics"), "bastion"),
+                entry("33333333", None, "other"),
+            ],
+        };
+
+        let by_id = resolve_tunnel_target(&state, "22222222").unwrap();
+        assert_eq!(by_id.len(), 1);
+        assert_eq!(by_id[0].label(), "metrics");
+
+        let by_name = resolve_tunnel_target(&state, "db").unwrap();
+        assert_eq!(by_name[0].id, "11111111");
+
+        let by_bookmark = resolve_tunnel_target(&state, "Bastion").unwrap();
+        assert_eq!(by_bookmark.len(), 2);
+
+        assert!(resolve_tunnel_target(&state, "nope").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_tunnel_target_ambiguous_name() {
+        let state = TunnelState {
+            tunnels: vec![
+                entry("11111111", Some("db"), "bastion-eu"),
+                entry("22222222", Some("db"), "bastion-us"),
+            ],
+        };
+        let err = resolve_tunnel_target(&state, "db").unwrap_err().to_string();
+        assert!(err.contains("11111111") && err.contains("22222222"));
+    }
+
+    #[test]
+    fn test_find_named_tunnel() {
+        let state = TunnelState {
+            tunnels: vec![entry("11111111", Some("db"), "bastion")],
+        };
+        assert!(find_named_tunnel(&state, "bastion", "db").is_some());
+        assert!(find_named_tunnel(&state, "bastion", "metrics").is_none());
+        assert!(find_named_tunnel(&state, "other", "db").is_none());
+    }
+
+    fn saved(name: &str, local: &[&str], autostart: bool) -> SavedTunnel {
+        SavedTunnel {
+            name: name.into(),
+            local: local.iter().map(|s| s.to_string()).collect(),
+            remote: vec![],
+            dynamic: vec![],
+            autostart,
+        }
+    }
+
+    #[test]
+    fn test_saved_tunnel_forwards() {
+        let mut tunnel = saved("db", &["5432:localhost:5432"], false);
+        tunnel.dynamic = vec!["1080".into()];
+        let forwards = saved_tunnel_forwards(&tunnel).unwrap();
+        assert_eq!(forwards.len(), 2);
+        assert_eq!(forwards[0].direction, ForwardDirection::Local);
+        assert_eq!(forwards[1].direction, ForwardDirection::Dynamic);
+
+        assert!(saved_tunnel_forwards(&saved("empty", &[], false)).is_err());
+        assert!(saved_tunnel_forwards(&saved("bad", &["nope"], false)).is_err());
+    }
+
+    #[test]
+    fn test_select_declared_tunnels() {
+        let declared = vec![
+            DeclaredTunnel {
+                bookmark: "bastion".into(),
+                tunnel: saved("db", &["5432:db:5432"], true),
+            },
+            DeclaredTunnel {
+                bookmark: "bastion".into(),
+                tunnel: saved("metrics", &["9090:prom:9090"], false),
+            },
+            DeclaredTunnel {
+                bookmark: "jump-eu".into(),
+                tunnel: saved("db", &["5433:db:5432"], false),
+            },
+        ];
+
+        assert_eq!(
+            select_declared_tunnels(declared.clone(), None)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let by_name = select_declared_tunnels(declared.clone(), Some("db")).unwrap();
+        assert_eq!(by_name.len(), 2);
+        assert!(by_name.iter().all(|d| d.tunnel.name == "db"));
+
+        let by_bookmark = select_declared_tunnels(declared.clone(), Some("bastion")).unwrap();
+        assert_eq!(by_bookmark.len(), 2);
+
+        assert!(select_declared_tunnels(declared, Some("nope")).is_err());
+    }
+
     #[test]
     fn test_tunnel_status_display() {
         assert_eq!(TunnelStatus::Connected.to_string(), "connected");
@@ -932,6 +1510,7 @@ mod tests {
     fn test_forward_direction_display() {
         assert_eq!(ForwardDirection::Local.to_string(), "-L");
         assert_eq!(ForwardDirection::Remote.to_string(), "-R");
+        assert_eq!(ForwardDirection::Dynamic.to_string(), "-D");
     }
 
     #[cfg(unix)]
@@ -5,7 +5,8 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-use super::FileEntry;
+use super::{FileEntry, LinkInfo, LinkKind};
+use crate::sftp::preserve::PreservedAttrs;
 
 /// Buffer size for local file transfers with progress tracking (256 KB).
 const LOCAL_CHUNK_SIZE: usize = 256 * 1024;
@@ -50,27 +51,23 @@ impl LocalBackend {
             .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
 
         while let Some(entry) = read_dir.next_entry().await? {
-            let metadata = entry.metadata().await?;
             let name = entry.file_name().to_string_lossy().to_string();
-            let full_path = entry.path().to_string_lossy().to_string();
-            let modified: Option<DateTime<Utc>> =
-                metadata.modified().ok().map(DateTime::<Utc>::from);
-
-            let permissions = format_local_permissions(&metadata);
-
-            entries.push(FileEntry {
-                name,
-                path: full_path,
-                is_dir: metadata.is_dir(),
-                size: metadata.len(),
-                modified,
-                permissions: Some(permissions),
-            });
+            entries.push(local_entry(&entry.path(), name).await?);
         }
 
         Ok(entries)
     }
 
+    pub async fn stat(&self, path: &str) -> Result<FileEntry> {
+        let name = Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+        local_entry(Path::new(path), name)
+            .await
+            .with_context(|| format!("Failed to stat: {path}"))
+    }
+
     pub async fn cd(&mut self, path: &str) -> Result<()> {
         let new_dir = if path == ".." {
             self.cwd.parent().unwrap_or(&self.cwd).to_path_buf()
@@ -93,7 +90,7 @@ impl LocalBackend {
     }
 
     pub async fn download(&self, remote_path: &str, local_path: &Path) -> Result<()> {
-        self.download_with_progress(remote_path, local_path, None, None)
+        self.download_with_progress(remote_path, local_path, None, None, false)
             .await
     }
 
@@ -104,41 +101,13 @@ impl LocalBackend {
         dst_path: &Path,
         progress: Option<&AtomicU64>,
         cancel: Option<&AtomicBool>,
+        preserve: bool,
     ) -> Result<()> {
-        // Fast path: no progress tracking needed
-        if progress.is_none() && cancel.is_none() {
-            tokio::fs::copy(src_path, dst_path).await.with_context(|| {
-                format!("Failed to copy {} to {}", src_path, dst_path.display())
-            })?;
-            return Ok(());
-        }
-
-        let mut src = tokio::fs::File::open(src_path)
-            .await
-            .with_context(|| format!("Failed to open: {src_path}"))?;
-        let mut dst = tokio::fs::File::create(dst_path)
-            .await
-            .with_context(|| format!("Failed to create: {}", dst_path.display()))?;
-
-        let mut buf = vec![0u8; LOCAL_CHUNK_SIZE];
-        loop {
-            if cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
-                anyhow::bail!("Transfer cancelled");
-            }
-            let n = src.read(&mut buf).await?;
-            if n == 0 {
-                break;
-            }
-            dst.write_all(&buf[..n]).await?;
-            if let Some(p) = progress {
-                p.fetch_add(n as u64, Ordering::Relaxed);
-            }
-        }
-        Ok(())
+        copy_local(Path::new(src_path), dst_path, progress, cancel, preserve).await
     }
 
     pub async fn upload(&self, local_path: &Path, remote_path: &str) -> Result<()> {
-        self.upload_with_progress(local_path, remote_path, None, None)
+        self.upload_with_progress(local_path, remote_path, None, None, false)
             .await
     }
 
@@ -149,21 +118,88 @@ impl LocalBackend {
         dst_path: &str,
         progress: Option<&AtomicU64>,
         cancel: Option<&AtomicBool>,
+        preserve: bool,
     ) -> Result<()> {
-        // Fast path: no progress tracking needed
-        if progress.is_none() && cancel.is_none() {
-            tokio::fs::copy(src_path, dst_path).await.with_context(|| {
-                format!("Failed to copy {} to {}", src_path.display(), dst_path)
-            })?;
-            return Ok(());
-        }
+        copy_local(src_path, Path::new(dst_path), progress, cancel, preserve).await
+    }
 
+    pub async fn delete(&self, path: &str) -> Result<()> {
+        tokio::fs::remove_file(path)
+            .await
+            .with_context(|| format!("Failed to delete: {path}"))
+    }
+
+    pub async fn rmdir(&self, path: &str) -> Result<()> {
+        tokio::fs::remove_dir_all(path)
+            .await
+            .with_context(|| format!("Failed to remove directory: {path}"))
+    }
+
+    pub async fn mkdir(&self, path: &str) -> Result<()> {
+        tokio::fs::create_dir_all(path)
+            .await
+            .with_context(|| format!("Failed to create directory: {path}"))
+    }
+
+    pub async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        tokio::fs::rename(from, to)
+            .await
+            .with_context(|| format!("Failed to rename {from} to {to}"))
+    }
+
+    pub async fn symlink(&self, target: &str, link_path: &str) -> Result<()> {
+        create_symlink(target, link_path).await
+    }
+
+    pub async fn checksum(&self, path: &str) -> Result<[u8; 32]> {
+        let file = tokio::fs::File::open(path)
+            .await
+            .with_context(|| format!("Failed to open: {path}"))?;
+        super::sha256_reader(file)
+            .await
+            .with_context(|| format!("Failed to read: {path}"))
+    }
+}
+
+/// Create a local symlink at `link_path` pointing at `target`.
+pub async fn create_symlink(target: &str, link_path: &str) -> Result<()> {
+    #[cfg(unix)]
+    {
+        tokio::fs::symlink(target, link_path)
+            .await
+            .with_context(|| format!("Failed to create symlink {link_path} -> {target}"))
+    }
+    #[cfg(not(unix))]
+    {
+        anyhow::bail!("Creating symlinks is not supported on this platform ({link_path})")
+    }
+}
+
+/// Copy a local file with optional progress tracking, cancellation and
+/// attribute preservation. Both "directions" of the local backend use this.
+async fn copy_local(
+    src_path: &Path,
+    dst_path: &Path,
+    progress: Option<&AtomicU64>,
+    cancel: Option<&AtomicBool>,
+    preserve: bool,
+) -> Result<()> {
+    // Fast path: no progress tracking needed
+    if progress.is_none() && cancel.is_none() {
+        tokio::fs::copy(src_path, dst_path).await.with_context(|| {
+            format!(
+                "Failed to copy {} to {}",
+                src_path.display(),
+                dst_path.display()
+            )
+        })?;
+    } else {
         let mut src = tokio::fs::File::open(src_path)
             .await
             .with_context(|| format!("Failed to open: {}", src_path.display()))?;
         let mut dst = tokio::fs::File::create(dst_path)
             .await
-            .with_context(|| format!("Failed to create: {dst_path}"))?;
+            .with_context(|| format!("Failed to create: {}", dst_path.display()))?;
 
         let mut buf = vec![0u8; LOCAL_CHUNK_SIZE];
         loop {
@@ -179,32 +215,59 @@ impl LocalBackend {
                 p.fetch_add(n as u64, Ordering::Relaxed);
             }
         }
-        Ok(())
-    }
-
-    pub async fn delete(&self, path: &str) -> Result<()> {
-        tokio::fs::remove_file(path)
-            .await
-            .with_context(|| format!("Failed to delete: {path}"))
+        dst.flush().await?;
     }
 
-    pub async fn rmdir(&self, path: &str) -> Result<()> {
-        tokio::fs::remove_dir_all(path)
+    if preserve {
+        let meta = tokio::fs::metadata(src_path)
             .await
-            .with_context(|| format!("Failed to remove directory: {path}"))
+            .with_context(|| format!("Failed to stat: {}", src_path.display()))?;
+        PreservedAttrs::from_local(&meta).apply_local(dst_path)?;
     }
+    Ok(())
+}
 
-    pub async fn mkdir(&self, path: &str) -> Result<()> {
-        tokio::fs::create_dir_all(path)
+/// Build a `FileEntry` for `path` without following it if it's a symlink;
+/// a link gets its target's type, size and mtime plus `LinkInfo`.
+async fn local_entry(path: &Path, name: String) -> Result<FileEntry> {
+    let lstat = tokio::fs::symlink_metadata(path)
+        .await
+        .with_context(|| format!("Failed to stat: {}", path.display()))?;
+    let (metadata, link) = if lstat.file_type().is_symlink() {
+        let target = tokio::fs::read_link(path)
             .await
-            .with_context(|| format!("Failed to create directory: {path}"))
-    }
-
-    pub async fn rename(&self, from: &str, to: &str) -> Result<()> {
-        tokio::fs::rename(from, to)
-            .await
-            .with_context(|| format!("Failed to rename {from} to {to}"))
-    }
+            .map(|t| t.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match tokio::fs::metadata(path).await {
+            Ok(resolved) => {
+                let kind = if resolved.is_dir() {
+                    LinkKind::Dir
+                } else {
+                    LinkKind::File
+                };
+                (resolved, Some(LinkInfo { kind, target }))
+            }
+            Err(_) => (
+                lstat,
+                Some(LinkInfo {
+                    kind: LinkKind::Broken,
+                    target,
+                }),
+            ),
+        }
+    } else {
+        (lstat, None)
+    };
+
+    Ok(FileEntry {
+        name,
+        path: path.to_string_lossy().to_string(),
+        is_dir: metadata.is_dir(),
+        size: metadata.len(),
+        modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+        permissions: Some(format_local_permissions(&metadata)),
+        link,
+    })
 }
 
 /// Format local file permissions as a string.
@@ -262,6 +325,22 @@ mod tests {
         assert!(names.contains(&"subdir"));
     }
 
+    #[tokio::test]
+    async fn test_local_backend_stat() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        std::fs::write(&file, "hello").unwrap();
+
+        let backend = LocalBackend::new(dir.path().to_str().unwrap()).unwrap();
+        let entry = backend.stat(file.to_str().unwrap()).await.unwrap();
+        assert_eq!(entry.name, "file.txt");
+        assert_eq!(entry.size, 5);
+        assert!(!entry.is_dir);
+        assert!(entry.modified.is_some());
+
+        assert!(backend.stat("/nonexistent/sshore-stat").await.is_err());
+    }
+
     #[tokio::test]
     async fn test_local_backend_cd() {
         let dir = tempfile::tempdir().unwrap();
@@ -320,4 +399,82 @@ mod tests {
         // Both should be listed — filtering is done at the TUI layer
         assert_eq!(entries.len(), 2);
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_local_backend_upload_preserves_mode_and_mtime() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::sync::atomic::{AtomicBool, AtomicU64};
+
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("deploy.sh");
+        std::fs::write(&src, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&src, std::fs::Permissions::from_mode(0o750)).unwrap();
+        let mtime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(&src)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let backend = LocalBackend::new(dir.path().to_str().unwrap()).unwrap();
+        let (progress, cancel) = (AtomicU64::new(0), AtomicBool::new(false));
+        for (name, preserve) in [("plain.sh", false), ("kept.sh", true)] {
+            let dst = dir.path().join(name);
+            backend
+                .upload_with_progress(
+                    &src,
+                    dst.to_str().unwrap(),
+                    Some(&progress),
+                    Some(&cancel),
+                    preserve,
+                )
+                .await
+                .unwrap();
+            let meta = std::fs::metadata(&dst).unwrap();
+            assert_eq!(meta.modified().unwrap() == mtime, preserve, "{name}");
+            if preserve {
+                assert_eq!(meta.permissions().mode() & 0o7777, 0o750);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_local_backend_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("file.txt"), "hello").unwrap();
+        std::fs::create_dir(dir.path().join("subdir")).unwrap();
+
+        let backend = LocalBackend::new(dir.path().to_str().unwrap()).unwrap();
+        let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+        backend.symlink("file.txt", &path("to-file")).await.unwrap();
+        backend.symlink("subdir", &path("to-dir")).await.unwrap();
+        backend.symlink("missing", &path("dangling")).await.unwrap();
+
+        let entries = backend.list(dir.path().to_str().unwrap()).await.unwrap();
+        let find = |name: &str| entries.iter().find(|e| e.name == name).unwrap();
+
+        assert!(find("file.txt").link.is_none());
+        let to_file = find("to-file");
+        assert_eq!(
+            to_file.link,
+            Some(LinkInfo {
+                kind: LinkKind::File,
+                target: "file.txt".into()
+            })
+        );
+        assert_eq!(to_file.size, 5);
+        let to_dir = find("to-dir");
+        assert!(to_dir.is_dir);
+        assert_eq!(to_dir.link.as_ref().unwrap().kind, LinkKind::Dir);
+        let dangling = find("dangling");
+        assert!(!dangling.is_dir);
+        assert_eq!(dangling.link.as_ref().unwrap().kind, LinkKind::Broken);
+
+        // stat doesn't follow the link either
+        let entry = backend.stat(&path("to-dir")).await.unwrap();
+        assert_eq!(entry.link.unwrap().target, "subdir");
+    }
 }
@@ -1,11 +1,12 @@
 pub mod local_backend;
 pub mod sftp_backend;
+pub mod sync;
 
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 
 use russh_sftp::client::SftpSession;
@@ -14,6 +15,9 @@ use self::local_backend::LocalBackend;
 use self::sftp_backend::SftpBackend;
 
 /// A file entry returned by list operations.
+///
+/// For symbolic links, `is_dir`, `size` and `modified` describe the link's
+/// target (so a link to a directory can be entered) and `link` says it's a link.
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct FileEntry {
@@ -23,6 +27,46 @@ pub struct FileEntry {
     pub size: u64,
     pub modified: Option<DateTime<Utc>>,
     pub permissions: Option<String>,
+    /// Set when the entry is a symbolic link.
+    pub link: Option<LinkInfo>,
+}
+
+impl FileEntry {
+    pub fn is_symlink(&self) -> bool {
+        self.link.is_some()
+    }
+}
+
+/// Where a symbolic link points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkInfo {
+    pub kind: LinkKind,
+    /// The target as stored in the link (may be relative to the link's directory).
+    pub target: String,
+}
+
+/// What a symbolic link resolves to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    File,
+    Dir,
+    /// The target doesn't exist (or can't be read).
+    Broken,
+}
+
+/// Whether `err` says the path doesn't exist, as opposed to being unreadable
+/// or the connection failing. Covers local I/O errors and SFTP `NoSuchFile`.
+pub fn is_not_found(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            return io.kind() == std::io::ErrorKind::NotFound;
+        }
+        matches!(
+            cause.downcast_ref::<russh_sftp::client::error::Error>(),
+            Some(russh_sftp::client::error::Error::Status(status))
+                if status.status_code == russh_sftp::protocol::StatusCode::NoSuchFile
+        )
+    })
 }
 
 /// Unified storage backend enum. Wraps concrete implementations
@@ -58,6 +102,46 @@ impl Backend {
         }
     }
 
+    /// Metadata for a single file or directory.
+    pub async fn stat(&self, path: &str) -> Result<FileEntry> {
+        match self {
+            Backend::Local(b) => b.stat(path).await,
+            Backend::Sftp(b) => b.stat(path).await,
+        }
+    }
+
+    /// A second backend on the same connection (a new SFTP channel for
+    /// remote panes), for work that runs in its own task.
+    pub async fn open_another(&self) -> Result<Backend> {
+        match self {
+            Backend::Local(b) => Ok(Backend::Local(LocalBackend::new(&b.cwd()?)?)),
+            Backend::Sftp(b) => {
+                let handle = b
+                    .ssh_handle()
+                    .context("No SSH handle available for a second SFTP channel")?;
+                let name = b.display_name().trim_end_matches(" (SFTP)");
+                Ok(Backend::Sftp(SftpBackend::from_handle(handle, name).await?))
+            }
+        }
+    }
+
+    /// Create a symbolic link at `link_path` pointing at `target`.
+    pub async fn symlink(&self, target: &str, link_path: &str) -> Result<()> {
+        match self {
+            Backend::Local(b) => b.symlink(target, link_path).await,
+            Backend::Sftp(b) => b.symlink(target, link_path).await,
+        }
+    }
+
+    /// SHA-256 of a file's contents. For SFTP this reads the whole file
+    /// over the connection.
+    pub async fn checksum(&self, path: &str) -> Result<[u8; 32]> {
+        match self {
+            Backend::Local(b) => b.checksum(path).await,
+            Backend::Sftp(b) => b.checksum(path).await,
+        }
+    }
+
     /// Change directory.
     pub async fn cd(&mut self, path: &str) -> Result<()> {
         match self {
@@ -75,21 +159,35 @@ impl Backend {
     }
 
     /// Download with progress tracking and cancellation support.
+    /// With `preserve`, the source's mode and times are carried over (`scp -p`).
     pub async fn download_with_progress(
         &self,
         remote_path: &str,
         local_path: &Path,
         progress: &AtomicU64,
         cancel: &AtomicBool,
+        preserve: bool,
     ) -> Result<()> {
         match self {
             Backend::Local(b) => {
-                b.download_with_progress(remote_path, local_path, Some(progress), Some(cancel))
-                    .await
+                b.download_with_progress(
+                    remote_path,
+                    local_path,
+                    Some(progress),
+                    Some(cancel),
+                    preserve,
+                )
+                .await
             }
             Backend::Sftp(b) => {
-                b.download_with_progress(remote_path, local_path, Some(progress), Some(cancel))
-                    .await
+                b.download_with_progress(
+                    remote_path,
+                    local_path,
+                    Some(progress),
+                    Some(cancel),
+                    preserve,
+                )
+                .await
             }
         }
     }
@@ -103,21 +201,35 @@ impl Backend {
     }
 
     /// Upload with progress tracking and cancellation support.
+    /// With `preserve`, the source's mode and times are carried over (`scp -p`).
     pub async fn upload_with_progress(
         &self,
         local_path: &Path,
         remote_path: &str,
         progress: &AtomicU64,
         cancel: &AtomicBool,
+        preserve: bool,
     ) -> Result<()> {
         match self {
             Backend::Local(b) => {
-                b.upload_with_progress(local_path, remote_path, Some(progress), Some(cancel))
-                    .await
+                b.upload_with_progress(
+                    local_path,
+                    remote_path,
+                    Some(progress),
+                    Some(cancel),
+                    preserve,
+                )
+                .await
             }
             Backend::Sftp(b) => {
-                b.upload_with_progress(local_path, remote_path, Some(progress), Some(cancel))
-                    .await
+                b.upload_with_progress(
+                    local_path,
+                    remote_path,
+                    Some(progress),
+                    Some(cancel),
+                    preserve,
+                )
+                .await
             }
         }
     }
@@ -201,3 +313,20 @@ impl Backend {
         }
     }
 }
+
+/// SHA-256 of everything `reader` yields.
+async fn sha256_reader(mut reader: impl tokio::io::AsyncRead + Unpin) -> Result<[u8; 32]> {
+    use sha2::{Digest, Sha256};
+    use tokio::io::AsyncReadExt;
+
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().into())
+}
@@ -1,16 +1,18 @@
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Write};
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 use anyhow::{Context, Result};
+use futures::future::join_all;
 use russh_sftp::client::SftpSession;
 
 use crate::config::model::AppConfig;
 use crate::sftp::pipeline;
+use crate::sftp::preserve::PreservedAttrs;
 use crate::ssh;
 
-use super::FileEntry;
+use super::{FileEntry, LinkInfo, LinkKind};
 
 /// SFTP implementation of StorageBackend.
 pub struct SftpBackend {
@@ -146,27 +148,72 @@ impl SftpBackend {
             .await
             .with_context(|| format!("Failed to list: {path}"))?;
 
-        Ok(entries
-            .into_iter()
+        // READDIR attributes describe links themselves; resolve those concurrently.
+        let futures = entries
             .filter(|e| {
                 let name = e.file_name();
                 name != "." && name != ".."
             })
             .map(|e| {
-                let attrs = e.metadata();
-                let name = e.file_name().to_string();
-                FileEntry {
-                    path: format!("{}/{}", path.trim_end_matches('/'), name),
-                    name,
-                    is_dir: attrs.is_dir(),
-                    size: attrs.size.unwrap_or(0),
-                    modified: attrs
-                        .mtime
-                        .and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0)),
-                    permissions: attrs.permissions.map(format_sftp_permissions),
+                let name = e.file_name();
+                let full_path = format!("{}/{}", path.trim_end_matches('/'), name);
+                self.entry_from_attrs(full_path, name, e.metadata())
+            });
+        Ok(join_all(futures).await)
+    }
+
+    pub async fn stat(&self, path: &str) -> Result<FileEntry> {
+        let attrs = self
+            .sftp
+            .symlink_metadata(path)
+            .await
+            .with_context(|| format!("Failed to stat: {path}"))?;
+        let name = path.rsplit('/').next().unwrap_or(path).to_string();
+        Ok(self.entry_from_attrs(path.to_string(), name, attrs).await)
+    }
+
+    /// Build a `FileEntry` from `lstat`-style attributes, following symlinks
+    /// with `readlink` + `stat` to fill in `LinkInfo` and the target's metadata.
+    async fn entry_from_attrs(
+        &self,
+        path: String,
+        name: String,
+        attrs: russh_sftp::client::fs::Metadata,
+    ) -> FileEntry {
+        let (attrs, link) = if attrs.is_symlink() {
+            let target = self.sftp.read_link(path.as_str()).await.unwrap_or_default();
+            match self.sftp.metadata(path.as_str()).await {
+                Ok(resolved) => {
+                    let kind = if resolved.is_dir() {
+                        LinkKind::Dir
+                    } else {
+                        LinkKind::File
+                    };
+                    (resolved, Some(LinkInfo { kind, target }))
                 }
-            })
-            .collect())
+                Err(_) => (
+                    attrs,
+                    Some(LinkInfo {
+                        kind: LinkKind::Broken,
+                        target,
+                    }),
+                ),
+            }
+        } else {
+            (attrs, None)
+        };
+
+        FileEntry {
+            path,
+            name,
+            is_dir: attrs.is_dir(),
+            size: attrs.size.unwrap_or(0),
+            modified: attrs
+                .mtime
+                .and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0)),
+            permissions: attrs.permissions.map(format_sftp_permissions),
+            link,
+        }
     }
 
     pub async fn cd(&mut self, path: &str) -> Result<()> {
@@ -194,18 +241,20 @@ impl SftpBackend {
     }
 
     pub async fn download(&self, remote_path: &str, local_path: &Path) -> Result<()> {
-        self.download_with_progress(remote_path, local_path, None, None)
+        self.download_with_progress(remote_path, local_path, None, None, false)
             .await
     }
 
     /// Download with optional progress tracking and cancellation.
     /// Uses pipelined SFTP with bounded in-flight requests.
+    /// With `preserve`, the remote mode and times are applied to the local file.
     pub async fn download_with_progress(
         &self,
         remote_path: &str,
         local_path: &Path,
         progress: Option<&AtomicU64>,
         cancel: Option<&AtomicBool>,
+        preserve: bool,
     ) -> Result<()> {
         let handle = self
             .ssh_handle
@@ -246,22 +295,34 @@ impl SftpBackend {
             },
             cancel,
         )
-        .await
+        .await?;
+
+        if preserve {
+            // Flush first so buffered writes don't bump the mtime afterwards.
+            local_file
+                .flush()
+                .with_context(|| format!("Failed to write: {}", local_path.display()))?;
+            drop(local_file);
+            PreservedAttrs::from_sftp(&meta).apply_local(local_path)?;
+        }
+        Ok(())
     }
 
     pub async fn upload(&self, local_path: &Path, remote_path: &str) -> Result<()> {
-        self.upload_with_progress(local_path, remote_path, None, None)
+        self.upload_with_progress(local_path, remote_path, None, None, false)
             .await
     }
 
     /// Upload with optional progress tracking and cancellation.
     /// Uses pipelined SFTP with bounded in-flight requests.
+    /// With `preserve`, the local mode and times are applied with SETSTAT.
     pub async fn upload_with_progress(
         &self,
         local_path: &Path,
         remote_path: &str,
         progress: Option<&AtomicU64>,
         cancel: Option<&AtomicBool>,
+        preserve: bool,
     ) -> Result<()> {
         let handle = self
             .ssh_handle
@@ -297,7 +358,14 @@ impl SftpBackend {
             },
             cancel,
         )
-        .await
+        .await?;
+
+        if preserve {
+            PreservedAttrs::from_local(&local_meta)
+                .apply_remote(&self.sftp, remote_path)
+                .await?;
+        }
+        Ok(())
     }
 
     pub async fn delete(&self, path: &str) -> Result<()> {
@@ -326,6 +394,21 @@ impl SftpBackend {
             .with_context(|| format!("Failed to rename {from} to {to}"))
     }
 
+    pub async fn symlink(&self, target: &str, link_path: &str) -> Result<()> {
+        create_symlink(&self.sftp, target, link_path).await
+    }
+
+    pub async fn checksum(&self, path: &str) -> Result<[u8; 32]> {
+        let file = self
+            .sftp
+            .open(path)
+            .await
+            .with_context(|| format!("Failed to open remote file: {path}"))?;
+        super::sha256_reader(file)
+            .await
+            .with_context(|| format!("Failed to read remote file: {path}"))
+    }
+
     /// Open a new SFTP session on the existing SSH connection.
     /// Used for background transfers so they don't share a channel with the browser.
     pub async fn open_sftp_session(&self) -> Result<SftpSession> {
@@ -386,6 +469,17 @@ impl SftpBackend {
     }
 }
 
+/// Create a remote symlink at `link_path` pointing at `target`.
+///
+/// OpenSSH's sftp-server reads the two SSH_FXP_SYMLINK paths in the opposite
+/// order to the draft spec (target first), and almost every server is
+/// OpenSSH, so the arguments are sent in its order.
+pub async fn create_symlink(sftp: &SftpSession, target: &str, link_path: &str) -> Result<()> {
+    sftp.symlink(target, link_path)
+        .await
+        .with_context(|| format!("Failed to create symlink {link_path} -> {target}"))
+}
+
 /// Recursively remove a directory via SFTP.
 async fn rmdir_recursive(sftp: &SftpSession, path: &str) -> Result<()> {
     let entries = sftp
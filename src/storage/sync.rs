@@ -0,0 +1,820 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use anyhow::{Context, Result, bail};
+use glob::{MatchOptions, Pattern};
+
+use super::{Backend, FileEntry};
+use crate::sftp::shortcuts::format_bytes;
+
+/// Options for a one-way directory sync (like `rsync -rt`).
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Compare file contents (SHA-256) instead of size and mtime.
+    pub checksum: bool,
+    /// Delete destination entries that don't exist in the source.
+    pub delete: bool,
+    /// Only sync files matching one of these globs (all files when empty).
+    pub include: Vec<String>,
+    /// Skip files and directories matching any of these globs.
+    pub exclude: Vec<String>,
+}
+
+/// Compiled include/exclude globs.
+///
+/// A pattern containing `/` is matched against the path relative to the sync
+/// root (a leading `/` is optional); any other pattern against the name alone,
+/// so `*.log` matches at every depth. A trailing `/` is ignored. Excludes apply
+/// to files and directories — an excluded directory is skipped entirely — while
+/// includes only decide which files are synced.
+pub struct SyncFilter {
+    include: Vec<(Pattern, bool)>,
+    exclude: Vec<(Pattern, bool)>,
+}
+
+impl SyncFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: compile_patterns(include)?,
+            exclude: compile_patterns(exclude)?,
+        })
+    }
+
+    pub fn is_excluded(&self, relative: &str) -> bool {
+        self.exclude.iter().any(|p| pattern_matches(p, relative))
+    }
+
+    pub fn is_included(&self, relative: &str) -> bool {
+        self.include.is_empty() || self.include.iter().any(|p| pattern_matches(p, relative))
+    }
+
+    fn has_includes(&self) -> bool {
+        !self.include.is_empty()
+    }
+}
+
+/// Compile globs into `(pattern, anchored)` pairs, where anchored patterns
+/// match the relative path rather than the name.
+fn compile_patterns(patterns: &[String]) -> Result<Vec<(Pattern, bool)>> {
+    patterns
+        .iter()
+        .map(|raw| {
+            let trimmed = raw.trim_end_matches('/');
+            let anchored = trimmed.contains('/');
+            let pattern = Pattern::new(trimmed.trim_start_matches('/'))
+                .with_context(|| format!("Invalid glob pattern '{raw}'"))?;
+            Ok((pattern, anchored))
+        })
+        .collect()
+}
+
+fn pattern_matches((pattern, anchored): &(Pattern, bool), relative: &str) -> bool {
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    };
+    if *anchored {
+        pattern.matches_with(relative, options)
+    } else {
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+        pattern.matches_with(name, options)
+    }
+}
+
+/// Why a file is copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncReason {
+    /// Missing from the destination.
+    New,
+    Size,
+    /// Same size, different modification time.
+    Modified,
+    /// Same size, different contents (checksum mode).
+    Checksum,
+}
+
+/// One step of a sync plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncOp {
+    Mkdir,
+    Copy {
+        reason: SyncReason,
+        size: u64,
+    },
+    /// Directories are removed recursively.
+    Delete {
+        is_dir: bool,
+    },
+    /// Left alone on both sides.
+    Skip {
+        reason: &'static str,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncItem {
+    /// Path relative to both sync roots, `/`-separated.
+    pub relative: String,
+    pub op: SyncOp,
+}
+
+/// The differences between two directory trees, in path order.
+#[derive(Debug, Clone)]
+pub struct SyncPlan {
+    pub src_root: String,
+    pub dst_root: String,
+    /// The destination directory doesn't exist yet.
+    pub create_root: bool,
+    pub items: Vec<SyncItem>,
+}
+
+impl SyncPlan {
+    /// Whether running the plan would change nothing.
+    pub fn is_empty(&self) -> bool {
+        !self.create_root
+            && self
+                .items
+                .iter()
+                .all(|i| matches!(i.op, SyncOp::Skip { .. }))
+    }
+
+    pub fn copy_count(&self) -> usize {
+        self.count(|op| matches!(op, SyncOp::Copy { .. }))
+    }
+
+    pub fn delete_count(&self) -> usize {
+        self.count(|op| matches!(op, SyncOp::Delete { .. }))
+    }
+
+    /// Bytes the copies will transfer.
+    pub fn copy_bytes(&self) -> u64 {
+        self.items
+            .iter()
+            .map(|i| match i.op {
+                SyncOp::Copy { size, .. } => size,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    fn count(&self, pred: impl Fn(&SyncOp) -> bool) -> usize {
+        self.items.iter().filter(|i| pred(&i.op)).count()
+    }
+
+    /// One-line summary, e.g. "3 to copy (1.2MB), 1 dir to create, 2 to delete".
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "Already in sync".to_string();
+        }
+        let mkdirs = self.count(|op| *op == SyncOp::Mkdir) + usize::from(self.create_root);
+        let skipped = self.count(|op| matches!(op, SyncOp::Skip { .. }));
+        let mut parts = Vec::new();
+        if self.copy_count() > 0 {
+            parts.push(format!(
+                "{} to copy ({})",
+                self.copy_count(),
+                format_bytes(self.copy_bytes())
+            ));
+        }
+        if mkdirs > 0 {
+            parts.push(format!(
+                "{mkdirs} dir{} to create",
+                if mkdirs == 1 { "" } else { "s" }
+            ));
+        }
+        if self.delete_count() > 0 {
+            parts.push(format!("{} to delete", self.delete_count()));
+        }
+        if skipped > 0 {
+            parts.push(format!("{skipped} skipped"));
+        }
+        parts.join(", ")
+    }
+
+    /// Dry-run listing: `+` new, `~` changed, `-` deleted, `!` skipped.
+    pub fn preview_lines(&self) -> Vec<String> {
+        self.items
+            .iter()
+            .map(|item| {
+                let rel = &item.relative;
+                match &item.op {
+                    SyncOp::Mkdir => format!("+ {rel}/"),
+                    SyncOp::Copy {
+                        reason: SyncReason::New,
+                        size,
+                    } => format!("+ {rel} ({})", format_bytes(*size)),
+                    SyncOp::Copy { reason, size } => {
+                        let why = match reason {
+                            SyncReason::Size => "size differs",
+                            SyncReason::Modified => "modified",
+                            _ => "content differs",
+                        };
+                        format!("~ {rel} ({}, {why})", format_bytes(*size))
+                    }
+                    SyncOp::Delete { is_dir: true } => format!("- {rel}/"),
+                    SyncOp::Delete { is_dir: false } => format!("- {rel}"),
+                    SyncOp::Skip { reason } => format!("! {rel} (skipped: {reason})"),
+                }
+            })
+            .collect()
+    }
+}
+
+/// What running a plan did.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub created: usize,
+    pub copied: usize,
+    pub deleted: usize,
+    /// One message per failed step; the rest of the plan still runs.
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    File,
+    Dir,
+    Link,
+}
+
+/// A file, directory or symlink below a sync root.
+#[derive(Debug, Clone, Copy)]
+pub struct TreeNode {
+    pub kind: NodeKind,
+    pub size: u64,
+    /// Unix seconds.
+    pub mtime: Option<i64>,
+}
+
+impl From<&FileEntry> for TreeNode {
+    fn from(entry: &FileEntry) -> Self {
+        let kind = if entry.is_symlink() {
+            NodeKind::Link
+        } else if entry.is_dir {
+            NodeKind::Dir
+        } else {
+            NodeKind::File
+        };
+        Self {
+            kind,
+            size: entry.size,
+            mtime: entry.modified.map(|m| m.timestamp()),
+        }
+    }
+}
+
+/// Compare `src_root` with `dst_root` and plan what it takes to make the
+/// destination match.
+///
+/// Symlinks are never followed or copied, and nothing is written through a
+/// symlink on the destination; they are reported as skipped instead.
+pub async fn plan(
+    src: &Backend,
+    src_root: &str,
+    dst: &Backend,
+    dst_root: &str,
+    options: &SyncOptions,
+) -> Result<SyncPlan> {
+    if matches!(src, Backend::Sftp(_)) && matches!(dst, Backend::Sftp(_)) {
+        bail!("Syncing between two remote hosts is not supported");
+    }
+    let filter = SyncFilter::new(&options.include, &options.exclude)?;
+
+    let root = src
+        .stat(src_root)
+        .await
+        .with_context(|| format!("Sync source not found: {src_root}"))?;
+    if !root.is_dir || root.is_symlink() {
+        bail!("Sync source is not a directory: {src_root}");
+    }
+    let src_tree = scan_tree(src, src_root, &filter).await?;
+
+    let (dst_tree, create_root) = match dst.stat(dst_root).await {
+        Ok(entry) if entry.is_dir && !entry.is_symlink() => {
+            (scan_tree(dst, dst_root, &filter).await?, false)
+        }
+        Ok(_) => bail!("Sync destination is not a directory: {dst_root}"),
+        Err(e) if super::is_not_found(&e) => (BTreeMap::new(), true),
+        Err(e) => return Err(e.context(format!("Failed to read sync destination: {dst_root}"))),
+    };
+
+    let mut items = Vec::new();
+    // Paths whose subtree must not be touched (type mismatches and symlinks)
+    let mut blocked: Vec<String> = Vec::new();
+    let mut new_dirs = Vec::new();
+
+    for (rel, s) in &src_tree {
+        if blocked.iter().any(|b| is_under(rel, b)) {
+            continue;
+        }
+        let skip = |reason| SyncItem {
+            relative: rel.clone(),
+            op: SyncOp::Skip { reason },
+        };
+        match (s.kind, dst_tree.get(rel).map(|d| d.kind)) {
+            (NodeKind::Link, _) => items.push(skip("symlink")),
+            (_, Some(NodeKind::Link)) => {
+                items.push(skip("destination is a symlink"));
+                blocked.push(rel.clone());
+            }
+            (NodeKind::Dir, None) => new_dirs.push(rel.clone()),
+            (NodeKind::Dir, Some(NodeKind::Dir)) => {}
+            (NodeKind::File, None) => items.push(SyncItem {
+                relative: rel.clone(),
+                op: SyncOp::Copy {
+                    reason: SyncReason::New,
+                    size: s.size,
+                },
+            }),
+            (NodeKind::File, Some(NodeKind::File)) => {
+                let d = &dst_tree[rel];
+                let reason = if s.size != d.size {
+                    Some(SyncReason::Size)
+                } else if options.checksum {
+                    let (a, b) = (
+                        src.checksum(&join(src_root, rel)).await?,
+                        dst.checksum(&join(dst_root, rel)).await?,
+                    );
+                    (a != b).then_some(SyncReason::Checksum)
+                } else {
+                    match (s.mtime, d.mtime) {
+                        (Some(a), Some(b)) if a != b => Some(SyncReason::Modified),
+                        _ => None,
+                    }
+                };
+                if let Some(reason) = reason {
+                    items.push(SyncItem {
+                        relative: rel.clone(),
+                        op: SyncOp::Copy {
+                            reason,
+                            size: s.size,
+                        },
+                    });
+                }
+            }
+            (NodeKind::File, Some(NodeKind::Dir)) | (NodeKind::Dir, Some(NodeKind::File)) => {
+                items.push(skip("file/directory mismatch"));
+                blocked.push(rel.clone());
+            }
+        }
+    }
+
+    // With includes, only create the directories something is copied into
+    let new_dirs: Vec<SyncItem> = new_dirs
+        .into_iter()
+        .filter(|dir| {
+            !filter.has_includes()
+                || items
+                    .iter()
+                    .any(|i| matches!(i.op, SyncOp::Copy { .. }) && is_under(&i.relative, dir))
+        })
+        .map(|relative| SyncItem {
+            relative,
+            op: SyncOp::Mkdir,
+        })
+        .collect();
+    items.extend(new_dirs);
+
+    if options.delete {
+        let mut deleted_dirs: Vec<&str> = Vec::new();
+        for (rel, d) in &dst_tree {
+            if src_tree.contains_key(rel)
+                || blocked.iter().any(|b| is_under(rel, b))
+                || deleted_dirs.iter().any(|dir| is_under(rel, dir))
+            {
+                continue;
+            }
+            if d.kind == NodeKind::Dir {
+                // Removing the whole directory could take non-included files with it
+                if filter.has_includes() {
+                    continue;
+                }
+                deleted_dirs.push(rel);
+            }
+            items.push(SyncItem {
+                relative: rel.clone(),
+                op: SyncOp::Delete {
+                    is_dir: d.kind == NodeKind::Dir,
+                },
+            });
+        }
+    }
+
+    items.sort_by(|a, b| a.relative.cmp(&b.relative));
+    Ok(SyncPlan {
+        src_root: src_root.to_string(),
+        dst_root: dst_root.to_string(),
+        create_root,
+        items,
+    })
+}
+
+/// Run `plan`: create directories, copy files, then delete. Copies preserve
+/// mode and times so the next comparison sees the files as equal. A failed
+/// step is recorded in the report and the rest of the plan still runs.
+/// `progress` counts bytes copied and `files_done` copies attempted.
+pub async fn execute(
+    plan: &SyncPlan,
+    src: &Backend,
+    dst: &Backend,
+    progress: &AtomicU64,
+    files_done: &AtomicU64,
+    cancel: &AtomicBool,
+) -> Result<SyncReport> {
+    let mut report = SyncReport::default();
+    if plan.create_root {
+        dst.mkdir(&plan.dst_root).await?;
+        report.created += 1;
+    }
+
+    for item in &plan.items {
+        if cancel.load(Ordering::Relaxed) {
+            bail!("Sync cancelled");
+        }
+        let dst_path = join(&plan.dst_root, &item.relative);
+        let result = match item.op {
+            SyncOp::Mkdir => dst.mkdir(&dst_path).await.map(|_| report.created += 1),
+            SyncOp::Copy { .. } => {
+                let src_path = join(&plan.src_root, &item.relative);
+                let copied = copy_file(src, dst, &src_path, &dst_path, progress, cancel)
+                    .await
+                    .map(|_| report.copied += 1);
+                files_done.fetch_add(1, Ordering::Relaxed);
+                copied
+            }
+            _ => continue,
+        };
+        if let Err(e) = result {
+            report.errors.push(format!("{}: {e:#}", item.relative));
+        }
+    }
+
+    for item in &plan.items {
+        let SyncOp::Delete { is_dir } = item.op else {
+            continue;
+        };
+        let dst_path = join(&plan.dst_root, &item.relative);
+        let result = if is_dir {
+            dst.rmdir(&dst_path).await
+        } else {
+            dst.delete(&dst_path).await
+        };
+        match result {
+            Ok(()) => report.deleted += 1,
+            Err(e) => report.errors.push(format!("{}: {e:#}", item.relative)),
+        }
+    }
+    Ok(report)
+}
+
+/// Copy one file between the two sides with attributes preserved.
+async fn copy_file(
+    src: &Backend,
+    dst: &Backend,
+    src_path: &str,
+    dst_path: &str,
+    progress: &AtomicU64,
+    cancel: &AtomicBool,
+) -> Result<()> {
+    match src {
+        Backend::Local(_) => {
+            dst.upload_with_progress(Path::new(src_path), dst_path, progress, cancel, true)
+                .await
+        }
+        Backend::Sftp(_) => {
+            src.download_with_progress(src_path, Path::new(dst_path), progress, cancel, true)
+                .await
+        }
+    }
+}
+
+/// List everything below `root` that passes `filter`, keyed by relative path.
+/// Symlinks are recorded but not descended into.
+pub async fn scan_tree(
+    backend: &Backend,
+    root: &str,
+    filter: &SyncFilter,
+) -> Result<BTreeMap<String, TreeNode>> {
+    let mut tree = BTreeMap::new();
+    let mut pending = vec![String::new()];
+    while let Some(dir) = pending.pop() {
+        for entry in backend.list(&join(root, &dir)).await? {
+            let relative = if dir.is_empty() {
+                entry.name.clone()
+            } else {
+                format!("{dir}/{}", entry.name)
+            };
+            if filter.is_excluded(&relative) {
+                continue;
+            }
+            let node = TreeNode::from(&entry);
+            match node.kind {
+                NodeKind::Dir => pending.push(relative.clone()),
+                _ if !filter.is_included(&relative) => continue,
+                _ => {}
+            }
+            tree.insert(relative, node);
+        }
+    }
+    Ok(tree)
+}
+
+/// Join a relative path onto a sync root.
+fn join(root: &str, relative: &str) -> String {
+    if relative.is_empty() {
+        root.to_string()
+    } else {
+        format!("{}/{relative}", root.trim_end_matches('/'))
+    }
+}
+
+/// Whether `path` is `dir` itself or below it.
+fn is_under(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::local_backend::LocalBackend;
+    use std::fs;
+
+    fn local(dir: &Path) -> Backend {
+        Backend::Local(LocalBackend::new(dir.to_str().unwrap()).unwrap())
+    }
+
+    fn set_mtime(path: &Path, secs: u64) {
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    /// Source and destination trees sharing one unchanged file.
+    fn setup() -> (tempfile::TempDir, String, String) {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("src");
+        let dst = tmp.path().join("dst");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::create_dir_all(dst.join("stale")).unwrap();
+        fs::write(src.join("same.txt"), "same").unwrap();
+        fs::write(dst.join("same.txt"), "same").unwrap();
+        set_mtime(&src.join("same.txt"), 1_600_000_000);
+        set_mtime(&dst.join("same.txt"), 1_600_000_000);
+        fs::write(src.join("grown.txt"), "longer").unwrap();
+        fs::write(dst.join("grown.txt"), "short").unwrap();
+        fs::write(src.join("sub/new.txt"), "new").unwrap();
+        fs::write(dst.join("stale/old.txt"), "old").unwrap();
+        fs::write(dst.join("extra.txt"), "extra").unwrap();
+        (
+            tmp,
+            src.to_string_lossy().to_string(),
+            dst.to_string_lossy().to_string(),
+        )
+    }
+
+    fn ops(plan: &SyncPlan) -> Vec<(&str, &SyncOp)> {
+        plan.items
+            .iter()
+            .map(|i| (i.relative.as_str(), &i.op))
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_matches_names_and_anchored_paths() {
+        let filter =
+            SyncFilter::new(&["*.rs".into()], &["target/".into(), "/docs/*.md".into()]).unwrap();
+        assert!(filter.is_excluded("target"));
+        assert!(filter.is_excluded("crates/x/target"));
+        assert!(filter.is_excluded("docs/intro.md"));
+        assert!(!filter.is_excluded("docs/deep/intro.md"));
+        assert!(filter.is_included("src/deep/main.rs"));
+        assert!(!filter.is_included("Cargo.toml"));
+        assert!(SyncFilter::new(&["[".into()], &[]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_plan_reports_new_changed_and_extraneous() {
+        let (tmp, src_root, dst_root) = setup();
+        let backend = local(tmp.path());
+
+        let options = SyncOptions::default();
+        let plan = plan(&backend, &src_root, &backend, &dst_root, &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            ops(&plan),
+            vec![
+                (
+                    "grown.txt",
+                    &SyncOp::Copy {
+                        reason: SyncReason::Size,
+                        size: 6
+                    }
+                ),
+                ("sub", &SyncOp::Mkdir),
+                (
+                    "sub/new.txt",
+                    &SyncOp::Copy {
+                        reason: SyncReason::New,
+                        size: 3
+                    }
+                ),
+            ]
+        );
+        assert_eq!(plan.summary(), "2 to copy (9B), 1 dir to create");
+
+        let options = SyncOptions {
+            delete: true,
+            ..SyncOptions::default()
+        };
+        let plan = super::plan(&backend, &src_root, &backend, &dst_root, &options)
+            .await
+            .unwrap();
+        let lines = plan.preview_lines();
+        assert!(lines.contains(&"- extra.txt".to_string()));
+        // The stale directory goes as a whole, not file by file
+        assert!(lines.contains(&"- stale/".to_string()));
+        assert!(!lines.iter().any(|l| l.contains("old.txt")));
+    }
+
+    #[tokio::test]
+    async fn test_execute_then_replan_is_empty() {
+        let (tmp, src_root, dst_root) = setup();
+        let backend = local(tmp.path());
+        let options = SyncOptions {
+            delete: true,
+            ..SyncOptions::default()
+        };
+
+        let first = plan(&backend, &src_root, &backend, &dst_root, &options)
+            .await
+            .unwrap();
+        let report = execute(
+            &first,
+            &backend,
+            &backend,
+            &AtomicU64::new(0),
+            &AtomicU64::new(0),
+            &AtomicBool::new(false),
+        )
+        .await
+        .unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!((report.created, report.copied, report.deleted), (1, 2, 2));
+
+        let dst = Path::new(&dst_root);
+        assert_eq!(fs::read_to_string(dst.join("sub/new.txt")).unwrap(), "new");
+        assert!(!dst.join("stale").exists());
+
+        let second = plan(&backend, &src_root, &backend, &dst_root, &options)
+            .await
+            .unwrap();
+        assert!(second.is_empty(), "{:?}", second.items);
+        assert_eq!(second.summary(), "Already in sync");
+    }
+
+    #[tokio::test]
+    async fn test_checksum_mode_compares_contents() {
+        let (tmp, src_root, dst_root) = setup();
+        let (src, dst) = (Path::new(&src_root), Path::new(&dst_root));
+        // Same size, different mtime, same contents
+        set_mtime(&src.join("same.txt"), 1_700_000_000);
+        fs::write(src.join("edit.txt"), "aaaa").unwrap();
+        fs::write(dst.join("edit.txt"), "bbbb").unwrap();
+        set_mtime(&src.join("edit.txt"), 1_600_000_000);
+        set_mtime(&dst.join("edit.txt"), 1_600_000_000);
+        let backend = local(tmp.path());
+
+        let quick = plan(
+            &backend,
+            &src_root,
+            &backend,
+            &dst_root,
+            &SyncOptions::default(),
+        )
+        .await
+        .unwrap();
+        let changed: Vec<&str> = ops(&quick).iter().map(|(rel, _)| *rel).collect();
+        assert!(changed.contains(&"same.txt"));
+        assert!(!changed.contains(&"edit.txt"));
+
+        let options = SyncOptions {
+            checksum: true,
+            ..SyncOptions::default()
+        };
+        let thorough = plan(&backend, &src_root, &backend, &dst_root, &options)
+            .await
+            .unwrap();
+        let changed: Vec<(&str, &SyncOp)> = ops(&thorough);
+        assert!(changed.contains(&(
+            "edit.txt",
+            &SyncOp::Copy {
+                reason: SyncReason::Checksum,
+                size: 4
+            }
+        )));
+        assert!(!changed.iter().any(|(rel, _)| *rel == "same.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_filters_limit_copies_and_deletions() {
+        let (tmp, src_root, dst_root) = setup();
+        let backend = local(tmp.path());
+        let options = SyncOptions {
+            delete: true,
+            include: vec!["*.txt".into()],
+            exclude: vec!["sub".into(), "extra.txt".into()],
+            ..SyncOptions::default()
+        };
+        let plan = plan(&backend, &src_root, &backend, &dst_root, &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            plan.preview_lines(),
+            vec![
+                "~ grown.txt (6B, size differs)".to_string(),
+                // Only included files go when includes are set, never whole dirs
+                "- stale/old.txt".to_string(),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_are_skipped() {
+        let (tmp, src_root, dst_root) = setup();
+        let (src, dst) = (Path::new(&src_root), Path::new(&dst_root));
+        std::os::unix::fs::symlink("same.txt", src.join("alias")).unwrap();
+        // A destination link is never written through
+        let elsewhere = tmp.path().join("elsewhere");
+        fs::create_dir(&elsewhere).unwrap();
+        fs::remove_dir_all(dst.join("stale")).unwrap();
+        std::os::unix::fs::symlink(&elsewhere, dst.join("sub")).unwrap();
+        let backend = local(tmp.path());
+
+        let plan = plan(
+            &backend,
+            &src_root,
+            &backend,
+            &dst_root,
+            &SyncOptions::default(),
+        )
+        .await
+        .unwrap();
+        let lines = plan.preview_lines();
+        assert!(lines.contains(&"! alias (skipped: symlink)".to_string()));
+        assert!(lines.contains(&"! sub (skipped: destination is a symlink)".to_string()));
+        assert!(!lines.iter().any(|l| l.contains("sub/new.txt")));
+    }
+
+    #[tokio::test]
+    async fn test_missing_destination_root_is_created() {
+        let (tmp, src_root, _) = setup();
+        let dst_root = tmp.path().join("fresh").to_string_lossy().to_string();
+        let backend = local(tmp.path());
+        let plan = plan(
+            &backend,
+            &src_root,
+            &backend,
+            &dst_root,
+            &SyncOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(plan.create_root);
+        let report = execute(
+            &plan,
+            &backend,
+            &backend,
+            &AtomicU64::new(0),
+            &AtomicU64::new(0),
+            &AtomicBool::new(false),
+        )
+        .await
+        .unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(
+            fs::read_to_string(Path::new(&dst_root).join("grown.txt")).unwrap(),
+            "longer"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_destination_root_is_an_error() {
+        let (tmp, src_root, dst_root) = setup();
+        // Stat fails with "not a directory", which must not read as "missing"
+        let dst_root = Path::new(&dst_root).join("same.txt/nested");
+        let backend = local(tmp.path());
+        let result = plan(
+            &backend,
+            &src_root,
+            &backend,
+            dst_root.to_str().unwrap(),
+            &SyncOptions::default(),
+        )
+        .await;
+        assert!(result.is_err());
+    }
+}
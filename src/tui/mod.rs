@@ -1346,10 +1346,20 @@ fn handle_list_key(app: &mut App, key: KeyEvent) {
         KeyCode::Char('d') => {
             if let Some(idx) = app.selected_bookmark_index() {
                 let bookmark = &app.config.bookmarks[idx];
+                if app.config.team.owns_bookmark(bookmark) {
+                    let msg = team_read_only_message(&bookmark.name);
+                    app.set_status(msg);
+                    return;
+                }
                 app.confirm_state = Some(ConfirmState::new(bookmark));
                 app.screen = Screen::DeleteConfirm(idx);
             } else if let Some(group_idx) = app.selected_group_index() {
                 let group = &app.config.groups[group_idx];
+                if app.config.team.owns_group(group) {
+                    let msg = team_read_only_message(&group.name);
+                    app.set_status(msg);
+                    return;
+                }
                 app.confirm_state = Some(ConfirmState::new_group(group));
                 app.screen = Screen::DeleteConfirm(GROUP_INDEX_MARKER + group_idx);
             }
@@ -1373,6 +1383,11 @@ fn handle_list_key(app: &mut App, key: KeyEvent) {
     }
 }
 
+/// Status shown when trying to delete an entry that comes from a team config.
+fn team_read_only_message(name: &str) -> String {
+    format!("'{name}' comes from a read-only team config; edit it to override it locally")
+}
+
 /// Handle key events in the session list view (when groups exist).
 ///
 /// Navigation moves through sessions within groups, skipping group headers.
@@ -1809,6 +1824,9 @@ fn try_save_unified_form(app: &mut App) {
                         let mut updated = bookmark;
                         updated.last_connected = original.last_connected;
                         updated.connect_count = original.connect_count;
+                        updated.use_agent = original.use_agent;
+                        updated.certificate_file = original.certificate_file.clone();
+                        updated.tunnels = original.tunnels.clone();
                         app.config.bookmarks[idx] = updated;
                     }
                     Screen::EditForm(EditTarget::Group, idx) => {
@@ -2043,6 +2061,9 @@ mod tests {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         }
     }
 
@@ -2058,6 +2079,7 @@ mod tests {
                 sample_bookmark("test-runner", "testing"),
             ],
             groups: vec![],
+            ..Default::default()
         };
         App::new(config)
     }
@@ -2194,7 +2216,7 @@ mod tests {
 
     #[test]
     fn test_drain_events_limit_is_positive() {
-        assert!(DRAIN_EVENTS_LIMIT > 0);
+        const { assert!(DRAIN_EVENTS_LIMIT > 0) };
     }
 
     #[test]
@@ -2310,6 +2332,9 @@ mod tests {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         };
         app.config.bookmarks.push(new_bookmark);
         app.refilter();
@@ -2512,6 +2537,7 @@ mod tests {
                 sample_bookmark("test-runner", "testing"),
             ],
             groups,
+            ..Default::default()
         };
         App::new(config)
     }
@@ -2609,6 +2635,20 @@ mod tests {
         assert!(matches!(app.screen, Screen::DeleteConfirm(_)));
     }
 
+    #[test]
+    fn test_team_entries_cannot_be_deleted() {
+        let mut app = sample_app();
+        let idx = app.selected_bookmark_index().unwrap();
+        let team_bookmark = app.config.bookmarks[idx].clone();
+        app.config.team.bookmarks.push(team_bookmark);
+
+        let d = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE);
+        handle_key_event(&mut app, d);
+        assert!(matches!(app.screen, Screen::List));
+        let (msg, _) = app.status_message.as_ref().unwrap();
+        assert!(msg.contains("read-only team config"));
+    }
+
     #[test]
     fn test_unified_list_home_end() {
         let mut app = app_with_groups(vec![sample_group()]);
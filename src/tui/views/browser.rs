@@ -1,5 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Duration;
@@ -17,8 +18,11 @@ use russh_sftp::client::SftpSession;
 use futures::future::join_all;
 
 use crate::sftp::pipeline;
+use crate::sftp::preserve::PreservedAttrs;
 use crate::sftp::shortcuts::{format_bytes, format_bytes_per_sec, format_duration};
-use crate::storage::{Backend, FileEntry};
+use crate::ssh::exec_guard;
+use crate::storage::sync::{self, NodeKind, SyncFilter, SyncOptions, SyncPlan, TreeNode};
+use crate::storage::{Backend, FileEntry, LinkKind};
 use crate::tui::theme::ThemeColors;
 
 /// Poll timeout when idle (no timed state changes pending).
@@ -87,6 +91,49 @@ pub struct PaneState {
     pub cwd: String,
     pub marked: HashSet<usize>,
     pub list_state: ListState,
+    /// Result of the last directory compare (`c` / `C`), by entry name.
+    /// Cleared whenever the listing is refreshed.
+    compare: HashMap<String, CompareMark>,
+}
+
+/// How an entry compares with the same name in the other pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareMark {
+    /// Missing from the other pane.
+    New,
+    Newer,
+    Older,
+    /// Same modification time, different size.
+    SizeDiffers,
+}
+
+impl CompareMark {
+    const ALL: [CompareMark; 4] = [Self::New, Self::Newer, Self::Older, Self::SizeDiffers];
+
+    /// Legend symbol shown after the size column.
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::New => "+",
+            Self::Newer => ">",
+            Self::Older => "<",
+            Self::SizeDiffers => "\u{2260}",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::Newer => "newer",
+            Self::Older => "older",
+            Self::SizeDiffers => "size differs",
+        }
+    }
+
+    /// Whether the entry gets marked for copying to the other pane.
+    /// Older entries are only tagged — the other side has the newer copy.
+    fn should_copy(self) -> bool {
+        self != Self::Older
+    }
 }
 
 impl PaneState {
@@ -100,6 +147,7 @@ impl PaneState {
             cwd,
             marked: HashSet::new(),
             list_state,
+            compare: HashMap::new(),
         }
     }
 
@@ -162,6 +210,11 @@ pub enum InputMode {
         input: String,
         source: FileEntry,
     },
+    /// Name for a new symlink to `target`, created next to it.
+    SymlinkPrompt {
+        input: String,
+        target: FileEntry,
+    },
     ConfirmDelete {
         entries: Vec<(String, String, bool, u64)>,
     }, // (path, name, is_dir, size)
@@ -198,6 +251,27 @@ pub enum InputMode {
     HelpOverlay,
     /// Quick directory jump prompt (Ctrl+G).
     GotoPrompt(String),
+    /// Dry-run preview of syncing the `source_side` pane's directory into the
+    /// other pane's. `d` and `c` toggle the options and re-run the comparison.
+    SyncPreview {
+        plan: SyncPlan,
+        source_side: Side,
+        options: SyncOptions,
+        scroll: u16,
+    },
+    /// Typed confirmation before a sync deletes files on a production remote.
+    SyncConfirm {
+        plan: SyncPlan,
+        source_side: Side,
+        input: String,
+    },
+    /// Upload an edited remote file back (see `BrowserState::pending_edit`).
+    /// Shown when the remote file changed since download or the host is production.
+    ConfirmEditUpload {
+        name: String,
+        conflict: Option<String>,
+        is_production: bool,
+    },
 }
 
 /// Info needed to retry a failed transfer from the completion popup.
@@ -243,6 +317,8 @@ struct TransferTarget {
     name: String,
     size: u64,
     is_dir: bool,
+    /// Recreate the source symlink (pointing here) instead of copying data.
+    link_target: Option<String>,
 }
 
 /// A pipelined SFTP worker session for high-throughput transfers.
@@ -454,6 +530,9 @@ fn format_transfer_summary(
     elapsed: Duration,
     is_move: bool,
 ) -> String {
+    if let Some(summary) = &result.summary {
+        return summary.clone();
+    }
     let verb_past = if is_move { "Moved" } else { "Copied" };
     let verb_noun = if is_move { "Move" } else { "Copy" };
     let elapsed_secs = elapsed.as_secs();
@@ -497,6 +576,8 @@ struct TransferResult {
     copied: usize,
     total: usize,
     last_error: Option<String>,
+    /// Finished status line for a sync, shown instead of the copy/move wording.
+    summary: Option<String>,
 }
 
 /// A background file transfer running in a separate tokio task.
@@ -523,6 +604,8 @@ struct BackgroundTransfer {
     retry_targets: Vec<(String, String, bool, u64)>,
     retry_dst_cwd: String,
     retry_is_move: bool,
+    /// A directory sync (`y`) rather than a copy or move. Not retried.
+    is_sync: bool,
 }
 
 /// Overall browser state.
@@ -554,6 +637,14 @@ pub struct BrowserState {
     overwrite_policy: Arc<AtomicU64>,
     /// Scroll offset for the help overlay.
     help_scroll: u16,
+    /// Remote file edited with F4 that is waiting for upload confirmation.
+    pending_edit: Option<EditSession>,
+    /// Copy what symlinks point at instead of recreating the links.
+    /// Toggled in the copy dialog and kept for the rest of the session.
+    follow_links: bool,
+    /// Carry mode and times over on copy/move (like `scp -p`). Same lifetime
+    /// as `follow_links`.
+    preserve_attrs: bool,
 }
 
 /// Whether a pane shows a local or remote filesystem.
@@ -624,6 +715,9 @@ pub async fn run(
         needs_full_redraw: false,
         overwrite_policy: Arc::new(AtomicU64::new(0)),
         help_scroll: 0,
+        pending_edit: None,
+        follow_links: false,
+        preserve_attrs: false,
     };
 
     // Initial load
@@ -670,6 +764,7 @@ pub async fn run(
                             copied: 0,
                             total: 0,
                             last_error: Some("Transfer task panicked".into()),
+                            summary: None,
                         },
                     };
 
@@ -690,7 +785,7 @@ pub async fn run(
                         let total_bytes = transfer.progress.bytes_done_all.load(Ordering::Relaxed);
                         let completion_msg =
                             format_transfer_summary(&result, total_bytes, elapsed, is_move);
-                        let retry = if result.last_error.is_some() {
+                        let retry = if result.last_error.is_some() && !transfer.is_sync {
                             Some(RetryInfo {
                                 targets: transfer.retry_targets,
                                 direction: transfer.direction,
@@ -1195,23 +1290,7 @@ async fn handle_key(
                         Ok(()) => {
                             let pager =
                                 std::env::var("PAGER").unwrap_or_else(|_| "less".to_string());
-                            // Leave TUI mode for pager
-                            crossterm::terminal::disable_raw_mode()?;
-                            crossterm::execute!(
-                                std::io::stdout(),
-                                crossterm::terminal::LeaveAlternateScreen,
-                                crossterm::cursor::Show,
-                            )?;
-                            let _ = std::process::Command::new(&pager)
-                                .arg(temp_file.as_os_str())
-                                .status();
-                            // Re-enter TUI mode
-                            crossterm::terminal::enable_raw_mode()?;
-                            crossterm::execute!(
-                                std::io::stdout(),
-                                crossterm::terminal::EnterAlternateScreen,
-                                crossterm::cursor::Hide,
-                            )?;
+                            let _ = run_outside_tui(&[pager], &temp_file)?;
                             state.status_message = None;
                             state.needs_full_redraw = true;
                         }
@@ -1224,6 +1303,15 @@ async fn handle_key(
             }
         }
 
+        KeyCode::F(4) | KeyCode::Char('e') => {
+            let pane = active_pane_mut(left_pane, right_pane, state);
+            if let Some(entry) = pane.selected_entry().cloned()
+                && !entry.is_dir
+            {
+                edit_file(&entry, left_pane, right_pane, state, left, right).await?;
+            }
+        }
+
         KeyCode::Char('v') | KeyCode::Insert => {
             let pane = active_pane_mut(left_pane, right_pane, state);
             pane.toggle_mark();
@@ -1300,6 +1388,75 @@ async fn handle_key(
             }
         }
 
+        KeyCode::Char('L') => {
+            // Create a symlink to the selected entry in the same directory
+            let pane = active_pane_mut(left_pane, right_pane, state);
+            if let Some(entry) = pane.selected_entry().cloned()
+                && entry.name != ".."
+            {
+                state.popup_focus = 0;
+                state.input_mode = InputMode::SymlinkPrompt {
+                    input: format!("{}-link", entry.name),
+                    target: entry,
+                };
+            }
+        }
+
+        KeyCode::Char('c') | KeyCode::Char('C') => {
+            // MC-style compare directories; `C` also looks inside shared subdirectories
+            let recursive = key.code == KeyCode::Char('C');
+            match compare_panes(
+                left_pane,
+                right_pane,
+                left,
+                right,
+                recursive,
+                state.show_hidden,
+            )
+            .await
+            {
+                Ok(()) => {
+                    state.status_message = Some(compare_summary(left_pane, right_pane));
+                }
+                Err(e) => {
+                    tracing::error!("compare failed: {e:#}");
+                    state.status_message = Some(format!("Compare error: {e}"));
+                }
+            }
+        }
+
+        KeyCode::Char('y') => {
+            // Sync the active pane's directory into the other pane's (dry run first)
+            let (src_label, dst_label) = match state.active_pane {
+                Side::Left => (state.left_label, state.right_label),
+                Side::Right => (state.right_label, state.left_label),
+            };
+            if src_label == dst_label {
+                state.status_message =
+                    Some("Sync requires one local and one remote pane".to_string());
+            } else {
+                let source_side = state.active_pane;
+                let options = SyncOptions::default();
+                match plan_pane_sync(left_pane, right_pane, left, right, source_side, &options)
+                    .await
+                {
+                    Ok(plan) => {
+                        state.popup_focus = 0;
+                        state.input_mode = InputMode::SyncPreview {
+                            plan,
+                            source_side,
+                            options,
+                            scroll: 0,
+                        };
+                    }
+                    Err(e) => {
+                        tracing::error!("sync compare failed: {e:#}");
+                        state.status_message = Some(format!("Sync error: {e}"));
+                    }
+                }
+            }
+        }
+
         KeyCode::F(6) => {
             // MC-style Move: copy to other pane then delete sources
             let pane = active_pane_mut(left_pane, right_pane, state);
@@ -1392,6 +1549,263 @@ async fn handle_key(
     Ok(BrowserAction::Continue)
 }
 
+/// A remote file downloaded for editing (F4), kept until its upload is settled.
+struct EditSession {
+    /// Private temp directory holding the local copy; removed on drop.
+    temp_dir: tempfile::TempDir,
+    local_path: PathBuf,
+    /// Remote metadata at download time, compared again before uploading.
+    remote: FileEntry,
+    /// Pane the file was opened from.
+    side: Side,
+}
+
+impl EditSession {
+    /// Keep the local copy on disk (e.g. when the upload doesn't happen) and return its path.
+    fn keep(self) -> PathBuf {
+        let _ = self.temp_dir.keep();
+        self.local_path
+    }
+}
+
+/// Edit the selected file in `$VISUAL`/`$EDITOR` (F4).
+///
+/// Local files are opened in place. Remote files are downloaded to a private
+/// temp directory and uploaded back if the editor changed them. The upload is
+/// confirmed first when the remote file changed in the meantime or the host
+/// is production.
+async fn edit_file(
+    entry: &FileEntry,
+    left_pane: &mut PaneState,
+    right_pane: &mut PaneState,
+    state: &mut BrowserState,
+    left: &mut Backend,
+    right: &mut Backend,
+) -> Result<()> {
+    let editor = resolve_editor(std::env::var("VISUAL").ok(), std::env::var("EDITOR").ok());
+    let side = state.active_pane;
+    let label = match side {
+        Side::Left => state.left_label,
+        Side::Right => state.right_label,
+    };
+
+    if label == PaneLabel::Local {
+        let status = run_outside_tui(&editor, Path::new(&entry.path))?;
+        state.needs_full_redraw = true;
+        state.status_message = match status {
+            Ok(status) if !status.success() => Some(format!("Editor exited with {status}")),
+            Ok(_) => None,
+            Err(e) => Some(format!("Failed to start editor '{}': {e}", editor[0])),
+        };
+        let pane = active_pane_mut(left_pane, right_pane, state);
+        let backend = active_backend_mut(left, right, state);
+        return refresh_pane(pane, backend, state).await;
+    }
+
+    state.status_message = Some(format!("Downloading {}...", entry.name));
+    let backend = active_backend_mut(left, right, state);
+    let session = match download_for_edit(backend, &entry.path, side).await {
+        Ok(session) => session,
+        Err(e) => {
+            tracing::error!("edit download failed: {}: {e:#}", entry.path);
+            state.status_message = Some(format!("Download error: {e}"));
+            return Ok(());
+        }
+    };
+
+    let before = file_digest(&session.local_path)?;
+    let status = run_outside_tui(&editor, &session.local_path)?;
+    state.needs_full_redraw = true;
+    let changed = file_digest(&session.local_path)? != before;
+    match status {
+        Err(e) => {
+            state.status_message = Some(format!("Failed to start editor '{}': {e}", editor[0]));
+            return Ok(());
+        }
+        Ok(status) if !status.success() => {
+            state.status_message = Some(if changed {
+                format!(
+                    "Editor exited with {status}; not uploading. Edited copy kept at {}",
+                    session.keep().display()
+                )
+            } else {
+                format!("Editor exited with {status}")
+            });
+            return Ok(());
+        }
+        Ok(_) => {}
+    }
+    if !changed {
+        state.status_message = Some(format!("No changes to {}", session.remote.name));
+        return Ok(());
+    }
+
+    let conflict = match backend.stat(&session.remote.path).await {
+        Ok(current) => edit_conflict(&session.remote, &current),
+        Err(e) => Some(format!("Remote file can't be checked: {e}")),
+    };
+    let is_production = is_production_remote(state);
+    let name = session.remote.name.clone();
+    state.pending_edit = Some(session);
+    if conflict.is_none() && !is_production {
+        return upload_pending_edit(left_pane, right_pane, state, left, right).await;
+    }
+    // Default to Cancel when uploading would overwrite someone else's change
+    state.popup_focus = if conflict.is_some() { 1 } else { 0 };
+    state.input_mode = InputMode::ConfirmEditUpload {
+        name,
+        conflict,
+        is_production,
+    };
+    Ok(())
+}
+
+/// Download `remote_path` into a fresh private temp directory for editing.
+/// Like transfers, the data lands in a `.part` file that is renamed on success;
+/// on failure the temp directory (and the `.part` in it) is removed.
+async fn download_for_edit(
+    backend: &Backend,
+    remote_path: &str,
+    side: Side,
+) -> Result<EditSession> {
+    let remote = backend.stat(remote_path).await?;
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("sshore-edit-");
+    // Remote files may hold secrets: keep the copy readable by this user only
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(std::fs::Permissions::from_mode(0o700));
+    }
+    let temp_dir = builder
+        .tempdir()
+        .context("Failed to create temp directory")?;
+    let local_path = temp_dir.path().join(&remote.name);
+    let part_path = PathBuf::from(format!("{}{PART_FILE_SUFFIX}", local_path.display()));
+    backend.download(remote_path, &part_path).await?;
+    std::fs::rename(&part_path, &local_path)
+        .with_context(|| format!("Failed to rename {}", part_path.display()))?;
+    Ok(EditSession {
+        temp_dir,
+        local_path,
+        remote,
+        side,
+    })
+}
+
+/// Upload the pending edited file over its remote original and refresh its pane.
+/// If the upload fails, the edited copy is kept on disk so no work is lost.
+async fn upload_pending_edit(
+    left_pane: &mut PaneState,
+    right_pane: &mut PaneState,
+    state: &mut BrowserState,
+    left: &mut Backend,
+    right: &mut Backend,
+) -> Result<()> {
+    let Some(session) = state.pending_edit.take() else {
+        return Ok(());
+    };
+    let (pane, backend) = match session.side {
+        Side::Left => (left_pane, left),
+        Side::Right => (right_pane, right),
+    };
+    let name = session.remote.name.clone();
+    state.status_message = Some(
+        match backend
+            .upload(&session.local_path, &session.remote.path)
+            .await
+        {
+            Ok(()) => format!("Uploaded {name}"),
+            Err(e) => {
+                tracing::error!("edit upload failed: {}: {e:#}", session.remote.path);
+                format!(
+                    "Upload of {name} failed: {e}. Edited copy kept at {}",
+                    session.keep().display()
+                )
+            }
+        },
+    );
+    refresh_pane(pane, backend, state).await
+}
+
+/// Describe how a remote file changed since it was downloaded, if it did.
+fn edit_conflict(downloaded: &FileEntry, current: &FileEntry) -> Option<String> {
+    if current.size != downloaded.size {
+        Some(format!(
+            "Remote size changed since download ({} -> {})",
+            format_bytes(downloaded.size),
+            format_bytes(current.size)
+        ))
+    } else if current.modified != downloaded.modified {
+        let when = current
+            .modified
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "unknown".to_string());
+        Some(format!(
+            "Remote file was modified since download (at {when})"
+        ))
+    } else {
+        None
+    }
+}
+
+/// The editor command from `$VISUAL`, then `$EDITOR`, falling back to `vi`.
+/// Split on whitespace so values like `code --wait` work.
+fn resolve_editor(visual: Option<String>, editor: Option<String>) -> Vec<String> {
+    [visual, editor]
+        .into_iter()
+        .flatten()
+        .map(|value| {
+            value
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .find(|words| !words.is_empty())
+        .unwrap_or_else(|| vec!["vi".to_string()])
+}
+
+/// SHA-256 of a file's content, used to tell whether the editor changed it.
+fn file_digest(path: &Path) -> Result<[u8; 32]> {
+    use sha2::{Digest, Sha256};
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(hasher.finalize().into())
+}
+
+/// Suspend the TUI, run `command` with `file` as its last argument, then
+/// restore the TUI. The outer error is a terminal failure; the inner result
+/// is whether the command could be run at all.
+fn run_outside_tui(
+    command: &[String],
+    file: &Path,
+) -> Result<std::io::Result<std::process::ExitStatus>> {
+    crossterm::terminal::disable_raw_mode()?;
+    crossterm::execute!(
+        std::io::stdout(),
+        crossterm::terminal::LeaveAlternateScreen,
+        crossterm::cursor::Show,
+    )?;
+    let status = std::process::Command::new(&command[0])
+        .args(&command[1..])
+        .arg(file)
+        .status();
+    crossterm::terminal::enable_raw_mode()?;
+    crossterm::execute!(
+        std::io::stdout(),
+        crossterm::terminal::EnterAlternateScreen,
+        crossterm::cursor::Hide,
+    )?;
+    Ok(status)
+}
+
 /// Extract the last component (directory name) from a path.
 fn dir_basename(path: &str) -> Option<String> {
     std::path::Path::new(path)
@@ -1426,6 +1840,7 @@ fn refilter_pane(pane: &mut PaneState, filter: &Option<String>) {
                 size: 0,
                 modified: None,
                 permissions: None,
+                link: None,
             },
         );
     }
@@ -1475,11 +1890,13 @@ async fn refresh_pane(
             size: 0,
             modified: None,
             permissions: None,
+            link: None,
         },
     );
 
     pane.entries = entries;
     pane.marked.clear();
+    pane.compare.clear();
 
     // Clamp selection
     if pane.selected >= pane.entries.len() {
@@ -1691,7 +2108,26 @@ fn draw(
             draw_mkdir_popup(frame, size, input, state.popup_focus);
         }
         InputMode::RenamePrompt { input, source } => {
-            draw_rename_popup(frame, size, &source.name, input, state.popup_focus);
+            draw_rename_popup(
+                frame,
+                size,
+                " Rename / Move ",
+                &source.name,
+                " Rename to:",
+                input,
+                state.popup_focus,
+            );
+        }
+        InputMode::SymlinkPrompt { input, target } => {
+            draw_rename_popup(
+                frame,
+                size,
+                " Symlink ",
+                &target.name,
+                " Link name:",
+                input,
+                state.popup_focus,
+            );
         }
         InputMode::CopyConfirm {
             targets,
@@ -1708,6 +2144,8 @@ fn draw(
                 dst_cwd,
                 state.popup_focus,
                 *is_move,
+                state.follow_links,
+                state.preserve_attrs,
             );
         }
         InputMode::TransferPopup => {
@@ -1757,6 +2195,51 @@ fn draw(
         InputMode::GotoPrompt(input) => {
             draw_goto_popup(frame, size, input, state.popup_focus);
         }
+        InputMode::SyncPreview {
+            plan,
+            source_side,
+            options,
+            scroll,
+        } => {
+            let needs_confirm = sync_needs_typed_confirm(state, plan, *source_side);
+            draw_sync_preview_popup(
+                frame,
+                size,
+                plan,
+                options,
+                *scroll,
+                needs_confirm,
+                state.popup_focus,
+            );
+        }
+        InputMode::SyncConfirm { plan, input, .. } => {
+            draw_rename_popup(
+                frame,
+                size,
+                " Confirm Sync ",
+                &format!(
+                    "\u{26a0} PRODUCTION: delete {} path(s)",
+                    plan.delete_count()
+                ),
+                " Type 'yes' to proceed:",
+                input,
+                state.popup_focus,
+            );
+        }
+        InputMode::ConfirmEditUpload {
+            name,
+            conflict,
+            is_production,
+        } => {
+            draw_edit_upload_popup(
+                frame,
+                size,
+                name,
+                conflict.as_deref(),
+                *is_production,
+                state.popup_focus,
+            );
+        }
         _ => {}
     }
 }
@@ -1923,7 +2406,9 @@ fn draw_mkdir_popup(frame: &mut Frame, area: Rect, input: &str, popup_focus: usi
 fn draw_rename_popup(
     frame: &mut Frame,
     area: Rect,
+    title: &str,
     original_name: &str,
+    label: &str,
     input: &str,
     popup_focus: usize,
 ) {
@@ -1934,7 +2419,7 @@ fn draw_rename_popup(
     }
 
     let block = Block::default()
-        .title(" Rename / Move ")
+        .title(title)
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Cyan));
 
@@ -1961,7 +2446,7 @@ fn draw_rename_popup(
 
     // Label
     frame.render_widget(
-        Paragraph::new(" Rename to:").style(Style::default().fg(Color::White)),
+        Paragraph::new(label).style(Style::default().fg(Color::White)),
         Rect::new(inner.x, inner.y + 1, inner.width, 1),
     );
 
@@ -2244,34 +2729,34 @@ fn draw_delete_confirm_popup(
     render_button_row(&["OK", "Cancel"], popup_focus, inner, y, frame);
 }
 
-/// Fixed popup width for transfer dialogs.
-const POPUP_WIDTH: u16 = 60;
-
-/// Wider popup width for overwrite confirmation (5 buttons on one row).
-const OVERWRITE_POPUP_WIDTH: u16 = 72;
-
-/// Draw the MC-style copy/move confirmation popup.
-fn draw_copy_confirm_popup(
+/// Draw the confirmation popup for uploading an edited file back.
+/// Shows a red border and warning line for production, and what changed
+/// remotely when the upload would overwrite a newer version.
+fn draw_edit_upload_popup(
     frame: &mut Frame,
     area: Rect,
-    targets: &[(String, String, bool, u64)],
-    _direction: TransferDirection,
-    dst_cwd: &str,
+    name: &str,
+    conflict: Option<&str>,
+    is_production: bool,
     popup_focus: usize,
-    is_move: bool,
 ) {
-    let popup_h: u16 = 9;
+    let extra_lines = u16::from(is_production) + u16::from(conflict.is_some());
+    // Layout: border(1) + warnings(0-2) + question(1) + blank(1) + separator(1) + buttons(1) + border(1)
+    let popup_h: u16 = 6 + extra_lines;
     let popup_area = centered_fixed_rect(POPUP_WIDTH, popup_h, area);
     if popup_area.width < 20 || popup_area.height < popup_h {
         return;
     }
 
-    let title = if is_move { " Move " } else { " Copy " };
-
+    let border_color = if is_production || conflict.is_some() {
+        Color::Red
+    } else {
+        Color::Cyan
+    };
     let block = Block::default()
-        .title(title)
+        .title(" Upload Edited File ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(border_color));
 
     frame.render_widget(Clear, popup_area);
     frame.render_widget(block, popup_area);
@@ -2282,42 +2767,259 @@ fn draw_copy_confirm_popup(
         popup_area.width.saturating_sub(4),
         popup_area.height.saturating_sub(2),
     );
+    let mut y = inner.y;
 
-    // "Copy/Move <name> to:" or "Copy/Move N files to:"
-    let verb = if is_move { "Move" } else { "Copy" };
-    let copy_label = if targets.len() == 1 {
-        let name = &targets[0].1;
-        let max_name = (inner.width as usize).saturating_sub(12);
-        format!("{verb} \"{}\" to:", truncate_name(name, max_name))
-    } else {
-        format!("{verb} {} file(s) to:", targets.len())
-    };
-    frame.render_widget(
-        Paragraph::new(copy_label).style(Style::default().fg(Color::White)),
-        Rect::new(inner.x, inner.y, inner.width, 1),
-    );
+    if is_production {
+        frame.render_widget(
+            Paragraph::new("\u{26a0} PRODUCTION")
+                .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Rect::new(inner.x, y, inner.width, 1),
+        );
+        y += 1;
+    }
+    if let Some(conflict) = conflict {
+        frame.render_widget(
+            Paragraph::new(truncate_name(conflict, inner.width as usize))
+                .style(Style::default().fg(Color::Yellow)),
+            Rect::new(inner.x, y, inner.width, 1),
+        );
+        y += 1;
+    }
 
-    // Destination path on highlighted background
-    let field_y = inner.y + 1;
-    let field_w = inner.width;
-    let max_visible = field_w as usize;
-    let display_dst = if dst_cwd.len() > max_visible {
-        let skip = dst_cwd.len() - max_visible.saturating_sub(3);
-        format!("...{}", &dst_cwd[skip..])
-    } else {
-        dst_cwd.to_string()
-    };
-    let pad = max_visible.saturating_sub(display_dst.len());
-    let field_line = Line::from(vec![
-        Span::styled(
-            display_dst,
-            Style::default().fg(Color::White).bg(Color::DarkGray),
-        ),
-        Span::styled(" ".repeat(pad), Style::default().bg(Color::DarkGray)),
-    ]);
+    let question = format!(
+        "Upload \"{}\"?",
+        truncate_name(name, inner.width.saturating_sub(10) as usize)
+    );
     frame.render_widget(
-        Paragraph::new(field_line),
-        Rect::new(inner.x, field_y, field_w, 1),
+        Paragraph::new(question).style(Style::default().fg(Color::White)),
+        Rect::new(inner.x, y, inner.width, 1),
+    );
+    y += 2;
+
+    let sep_line = "\u{2500}".repeat(inner.width as usize);
+    frame.render_widget(
+        Paragraph::new(sep_line).style(Style::default().fg(border_color)),
+        Rect::new(inner.x, y, inner.width, 1),
+    );
+    y += 1;
+
+    render_button_row(&["Upload", "Cancel"], popup_focus, inner, y, frame);
+}
+
+/// Wider popup width for the sync preview (paths plus change reasons).
+const SYNC_POPUP_WIDTH: u16 = 76;
+
+/// Rows of the sync preview list shown at once.
+const SYNC_PREVIEW_ROWS: usize = 12;
+
+/// Draw the sync dry-run preview: roots, summary, the change list and options.
+fn draw_sync_preview_popup(
+    frame: &mut Frame,
+    area: Rect,
+    plan: &SyncPlan,
+    options: &SyncOptions,
+    scroll: u16,
+    needs_confirm: bool,
+    popup_focus: usize,
+) {
+    let lines = plan.preview_lines();
+    let list_h = lines.len().clamp(1, SYNC_PREVIEW_ROWS) as u16;
+    let warn_line = u16::from(needs_confirm);
+    // Layout: border(1) + roots(1) + summary(1) + warning(0-1) + separator(1) + list
+    //         + separator(1) + options(1) + separator(1) + buttons(1) + border(1)
+    let popup_h: u16 = 9 + warn_line + list_h;
+    let popup_area = centered_fixed_rect(SYNC_POPUP_WIDTH, popup_h, area);
+    if popup_area.width < 20 || popup_area.height < popup_h {
+        return;
+    }
+
+    let border_color = if needs_confirm {
+        Color::Red
+    } else {
+        Color::Cyan
+    };
+    let block = Block::default()
+        .title(" Sync (dry run) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(border_color));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let inner = Rect::new(
+        popup_area.x + 2,
+        popup_area.y + 1,
+        popup_area.width.saturating_sub(4),
+        popup_area.height.saturating_sub(2),
+    );
+    let width = inner.width as usize;
+    let row = |y: u16| Rect::new(inner.x, y, inner.width, 1);
+    let separator =
+        || Paragraph::new("\u{2500}".repeat(width)).style(Style::default().fg(border_color));
+    let mut y = inner.y;
+
+    let half = width.saturating_sub(4) / 2;
+    let roots = format!(
+        "{} \u{2192} {}",
+        truncate_name(&plan.src_root, half),
+        truncate_name(&plan.dst_root, half)
+    );
+    frame.render_widget(
+        Paragraph::new(roots).style(Style::default().fg(Color::White)),
+        row(y),
+    );
+    y += 1;
+    frame.render_widget(
+        Paragraph::new(plan.summary()).style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+        row(y),
+    );
+    y += 1;
+    if needs_confirm {
+        frame.render_widget(
+            Paragraph::new("\u{26a0} PRODUCTION: deletions need typed confirmation")
+                .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            row(y),
+        );
+        y += 1;
+    }
+    frame.render_widget(separator(), row(y));
+    y += 1;
+
+    if lines.is_empty() {
+        frame.render_widget(
+            Paragraph::new(" Nothing to do").style(Style::default().fg(Color::DarkGray)),
+            row(y),
+        );
+    } else {
+        let max_scroll = lines.len().saturating_sub(list_h as usize);
+        let start = (scroll as usize).min(max_scroll);
+        for (i, line) in lines.iter().skip(start).take(list_h as usize).enumerate() {
+            let color = match line.as_bytes().first() {
+                Some(b'+') => Color::Green,
+                Some(b'~') => Color::Yellow,
+                Some(b'-') => Color::Red,
+                _ => Color::DarkGray,
+            };
+            frame.render_widget(
+                Paragraph::new(truncate_name(line, width)).style(Style::default().fg(color)),
+                row(y + i as u16),
+            );
+        }
+    }
+    y += list_h;
+
+    frame.render_widget(separator(), row(y));
+    y += 1;
+    let check = |on: bool| if on { "x" } else { " " };
+    frame.render_widget(
+        Paragraph::new(format!(
+            " [{}] Delete extraneous (d)  [{}] Compare checksums (c)",
+            check(options.delete),
+            check(options.checksum)
+        ))
+        .style(Style::default().fg(Color::White)),
+        row(y),
+    );
+    y += 1;
+    frame.render_widget(separator(), row(y));
+    y += 1;
+    render_button_row(&["Sync", "Cancel"], popup_focus, inner, y, frame);
+}
+
+/// Fixed popup width for transfer dialogs.
+const POPUP_WIDTH: u16 = 60;
+
+/// Wider popup width for overwrite confirmation (5 buttons on one row).
+const OVERWRITE_POPUP_WIDTH: u16 = 72;
+
+/// Draw the MC-style copy/move confirmation popup.
+#[allow(clippy::too_many_arguments)]
+fn draw_copy_confirm_popup(
+    frame: &mut Frame,
+    area: Rect,
+    targets: &[(String, String, bool, u64)],
+    _direction: TransferDirection,
+    dst_cwd: &str,
+    popup_focus: usize,
+    is_move: bool,
+    follow_links: bool,
+    preserve_attrs: bool,
+) {
+    let popup_h: u16 = 9;
+    let popup_area = centered_fixed_rect(POPUP_WIDTH, popup_h, area);
+    if popup_area.width < 20 || popup_area.height < popup_h {
+        return;
+    }
+
+    let title = if is_move { " Move " } else { " Copy " };
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let inner = Rect::new(
+        popup_area.x + 2,
+        popup_area.y + 1,
+        popup_area.width.saturating_sub(4),
+        popup_area.height.saturating_sub(2),
+    );
+
+    // "Copy/Move <name> to:" or "Copy/Move N files to:"
+    let verb = if is_move { "Move" } else { "Copy" };
+    let copy_label = if targets.len() == 1 {
+        let name = &targets[0].1;
+        let max_name = (inner.width as usize).saturating_sub(12);
+        format!("{verb} \"{}\" to:", truncate_name(name, max_name))
+    } else {
+        format!("{verb} {} file(s) to:", targets.len())
+    };
+    frame.render_widget(
+        Paragraph::new(copy_label).style(Style::default().fg(Color::White)),
+        Rect::new(inner.x, inner.y, inner.width, 1),
+    );
+
+    // Destination path on highlighted background
+    let field_y = inner.y + 1;
+    let field_w = inner.width;
+    let max_visible = field_w as usize;
+    let display_dst = if dst_cwd.len() > max_visible {
+        let skip = dst_cwd.len() - max_visible.saturating_sub(3);
+        format!("...{}", &dst_cwd[skip..])
+    } else {
+        dst_cwd.to_string()
+    };
+    let pad = max_visible.saturating_sub(display_dst.len());
+    let field_line = Line::from(vec![
+        Span::styled(
+            display_dst,
+            Style::default().fg(Color::White).bg(Color::DarkGray),
+        ),
+        Span::styled(" ".repeat(pad), Style::default().bg(Color::DarkGray)),
+    ]);
+    frame.render_widget(
+        Paragraph::new(field_line),
+        Rect::new(inner.x, field_y, field_w, 1),
+    );
+
+    // Options: symlink handling (recreate links by default, or copy what
+    // they point at) and attribute preservation
+    let check = |on: bool| if on { "x" } else { " " };
+    frame.render_widget(
+        Paragraph::new(format!(
+            " [{}] Follow symlinks (l)  [{}] Preserve attrs (p)",
+            check(follow_links),
+            check(preserve_attrs)
+        ))
+        .style(Style::default().fg(Color::White)),
+        Rect::new(inner.x, inner.y + 2, inner.width, 1),
     );
 
     // Total size info
@@ -2403,6 +3105,8 @@ fn draw_transfer_popup(frame: &mut Frame, area: Rect, state: &BrowserState) {
 
     let is_move = transfer.delete_sources.is_some();
     let dir_label = match (transfer.direction, is_move) {
+        (TransferDirection::LocalToRemote, _) if transfer.is_sync => "Syncing to Remote",
+        (TransferDirection::RemoteToLocal, _) if transfer.is_sync => "Syncing to Local",
         (TransferDirection::LocalToRemote, false) => "Copying to Remote",
         (TransferDirection::RemoteToLocal, false) => "Copying to Local",
         (TransferDirection::LocalToRemote, true) => "Moving to Remote",
@@ -2885,25 +3589,44 @@ fn draw_pane(
         .map(|(i, entry)| {
             let is_marked = pane.marked.contains(&i);
             let prefix = if is_marked { ">> " } else { "   " };
-            let icon = if entry.is_dir { "d " } else { "  " };
-            let size_str = if entry.is_dir {
+            let link_kind = entry.link.as_ref().map(|l| l.kind);
+            let icon = if link_kind.is_some() {
+                "l "
+            } else if entry.is_dir {
+                "d "
+            } else {
+                "  "
+            };
+            let size_str = if link_kind == Some(LinkKind::Broken) {
+                "<BROKEN>".to_string()
+            } else if entry.is_dir {
                 "<DIR>".to_string()
             } else {
                 format_bytes(entry.size)
             };
 
+            let compare = pane.compare.get(&entry.name).copied();
             let text = format!(
-                "{}{}{:<30} {:>8}",
+                "{}{}{:<30} {:>8}{}",
                 prefix,
                 icon,
-                truncate_name(&entry.name, 30),
+                truncate_name(&entry_display_name(entry), 30),
                 size_str,
+                compare
+                    .map(|m| format!(" {}", m.symbol()))
+                    .unwrap_or_default(),
             );
 
             let mut style = if is_marked {
                 Style::default()
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::BOLD)
+            } else if compare == Some(CompareMark::Older) {
+                Style::default().fg(Color::DarkGray)
+            } else if link_kind == Some(LinkKind::Broken) {
+                Style::default().fg(Color::Red)
+            } else if link_kind.is_some() {
+                Style::default().fg(Color::Magenta)
             } else if entry.is_dir {
                 Style::default().fg(Color::Cyan)
             } else {
@@ -2959,33 +3682,351 @@ fn draw_pane(
     frame.render_stateful_widget(list, area, &mut pane.list_state);
 }
 
+/// Name shown in the file list: symlinks also show where they point.
+fn entry_display_name(entry: &FileEntry) -> String {
+    match &entry.link {
+        Some(link) => format!("{} -> {}", entry.name, link.target),
+        None => entry.name.clone(),
+    }
+}
+
+/// Compare the two panes by name, size and mtime and mark in each pane what
+/// should be copied to the other (MC's "compare directories").
+///
+/// Directories present on both sides are only compared with `recursive`: their
+/// trees are scanned and the directory is tagged by what it contains.
+async fn compare_panes(
+    left_pane: &mut PaneState,
+    right_pane: &mut PaneState,
+    left: &Backend,
+    right: &Backend,
+    recursive: bool,
+    show_hidden: bool,
+) -> Result<()> {
+    let mut left_marks = compare_entries(&left_pane.entries, &right_pane.all_entries);
+    let mut right_marks = compare_entries(&right_pane.entries, &left_pane.all_entries);
+
+    if recursive {
+        let exclude = if show_hidden {
+            Vec::new()
+        } else {
+            vec![".*".to_string()]
+        };
+        let filter = SyncFilter::new(&[], &exclude)?;
+        let is_real_dir = |e: &FileEntry| e.is_dir && !e.is_symlink() && e.name != "..";
+        let shared: Vec<String> = left_pane
+            .all_entries
+            .iter()
+            .filter(|e| is_real_dir(e))
+            .filter(|e| {
+                right_pane
+                    .all_entries
+                    .iter()
+                    .any(|r| r.name == e.name && is_real_dir(r))
+            })
+            .map(|e| e.name.clone())
+            .collect();
+        for name in shared {
+            let join = |cwd: &str| format!("{}/{name}", cwd.trim_end_matches('/'));
+            let ours = sync::scan_tree(left, &join(&left_pane.cwd), &filter).await?;
+            let theirs = sync::scan_tree(right, &join(&right_pane.cwd), &filter).await?;
+            if let Some(mark) = compare_trees(&ours, &theirs) {
+                left_marks.insert(name.clone(), mark);
+            }
+            if let Some(mark) = compare_trees(&theirs, &ours) {
+                right_marks.insert(name, mark);
+            }
+        }
+    }
+
+    apply_compare_marks(left_pane, left_marks);
+    apply_compare_marks(right_pane, right_marks);
+    Ok(())
+}
+
+/// Compare `ours` with `theirs` by name. Files are compared by mtime, then
+/// size; directories and symlinks only by whether they exist on the other side.
+fn compare_entries(ours: &[FileEntry], theirs: &[FileEntry]) -> HashMap<String, CompareMark> {
+    let theirs: HashMap<&str, &FileEntry> = theirs
+        .iter()
+        .filter(|e| e.name != "..")
+        .map(|e| (e.name.as_str(), e))
+        .collect();
+    let is_file = |e: &FileEntry| !e.is_dir && !e.is_symlink();
+    ours.iter()
+        .filter(|e| e.name != "..")
+        .filter_map(|e| {
+            let mark = match theirs.get(e.name.as_str()) {
+                None => Some(CompareMark::New),
+                Some(t) if is_file(e) && is_file(t) => compare_file(
+                    (e.size, e.modified.map(|m| m.timestamp())),
+                    (t.size, t.modified.map(|m| m.timestamp())),
+                ),
+                Some(_) => None,
+            };
+            mark.map(|m| (e.name.clone(), m))
+        })
+        .collect()
+}
+
+/// Summarize how the tree `ours` compares with `theirs`: newer if anything in
+/// it is new or newer, else size-differs, else older; `None` when identical.
+fn compare_trees(
+    ours: &BTreeMap<String, TreeNode>,
+    theirs: &BTreeMap<String, TreeNode>,
+) -> Option<CompareMark> {
+    let mut result = None;
+    for (rel, node) in ours {
+        let mark = match theirs.get(rel) {
+            None => Some(CompareMark::New),
+            Some(t) if node.kind == NodeKind::File && t.kind == NodeKind::File => {
+                compare_file((node.size, node.mtime), (t.size, t.mtime))
+            }
+            Some(_) => None,
+        };
+        match mark {
+            Some(CompareMark::New | CompareMark::Newer) => return Some(CompareMark::Newer),
+            Some(CompareMark::SizeDiffers) => result = Some(CompareMark::SizeDiffers),
+            Some(CompareMark::Older) if result.is_none() => result = Some(CompareMark::Older),
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Compare two files given as `(size, mtime in unix seconds)`.
+fn compare_file(ours: (u64, Option<i64>), theirs: (u64, Option<i64>)) -> Option<CompareMark> {
+    match (ours.1, theirs.1) {
+        (Some(a), Some(b)) if a > b => Some(CompareMark::Newer),
+        (Some(a), Some(b)) if a < b => Some(CompareMark::Older),
+        _ if ours.0 != theirs.0 => Some(CompareMark::SizeDiffers),
+        _ => None,
+    }
+}
+
+/// Store compare results on a pane and mark what should be copied across,
+/// replacing any previous marks.
+fn apply_compare_marks(pane: &mut PaneState, marks: HashMap<String, CompareMark>) {
+    pane.marked = pane
+        .entries
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| marks.get(&e.name).is_some_and(|m| m.should_copy()))
+        .map(|(i, _)| i)
+        .collect();
+    pane.compare = marks;
+}
+
+/// Status line legend after a compare, counting both panes.
+fn compare_summary(left_pane: &PaneState, right_pane: &PaneState) -> String {
+    let marks: Vec<CompareMark> = left_pane
+        .compare
+        .values()
+        .chain(right_pane.compare.values())
+        .copied()
+        .collect();
+    if marks.is_empty() {
+        return "Compare: directories are identical".to_string();
+    }
+    let legend: Vec<String> = CompareMark::ALL
+        .iter()
+        .map(|kind| {
+            let count = marks.iter().filter(|m| *m == kind).count();
+            format!("{} {} {count}", kind.symbol(), kind.label())
+        })
+        .collect();
+    format!(
+        "Compare: {} \u{2014} {} marked, F5 copies them",
+        legend.join("  "),
+        left_pane.marked.len() + right_pane.marked.len()
+    )
+}
+
+/// Compare the `source_side` pane's directory with the other pane's.
+async fn plan_pane_sync(
+    left_pane: &PaneState,
+    right_pane: &PaneState,
+    left: &Backend,
+    right: &Backend,
+    source_side: Side,
+    options: &SyncOptions,
+) -> Result<SyncPlan> {
+    let (src, src_root, dst, dst_root) = match source_side {
+        Side::Left => (left, &left_pane.cwd, right, &right_pane.cwd),
+        Side::Right => (right, &right_pane.cwd, left, &left_pane.cwd),
+    };
+    sync::plan(src, src_root, dst, dst_root, options).await
+}
+
+/// Start a previewed sync as a background transfer with the progress popup,
+/// like F5: Esc cancels it and `b` hides the popup. The sync runs on its own
+/// SFTP channel so the browser stays responsive.
+async fn start_pane_sync(
+    plan: SyncPlan,
+    source_side: Side,
+    state: &mut BrowserState,
+    left: &Backend,
+    right: &Backend,
+) {
+    let (src, dst, dst_label) = match source_side {
+        Side::Left => (left, right, state.right_label),
+        Side::Right => (right, left, state.left_label),
+    };
+    let backends = async { anyhow::Ok((src.open_another().await?, dst.open_another().await?)) };
+    let (src, dst) = match backends.await {
+        Ok(backends) => backends,
+        Err(e) => {
+            tracing::error!("sync failed: {e:#}");
+            state.status_message = Some(format!("Sync error: {e}"));
+            return;
+        }
+    };
+
+    let progress = Arc::new(TransferProgress::new(
+        plan.copy_count() as u64,
+        plan.copy_bytes(),
+    ));
+    progress.scanning.store(false, Ordering::Relaxed);
+    let cancel = Arc::new(AtomicBool::new(false));
+    let description = plan.src_root.clone();
+
+    let bg_progress = Arc::clone(&progress);
+    let bg_cancel = Arc::clone(&cancel);
+    let handle = tokio::spawn(async move {
+        let result = sync::execute(
+            &plan,
+            &src,
+            &dst,
+            &bg_progress.bytes_done_all,
+            &bg_progress.files_done,
+            &bg_cancel,
+        )
+        .await;
+        sync_transfer_result(
+            result,
+            plan.copy_count(),
+            bg_progress.bytes_done_all.load(Ordering::Relaxed),
+        )
+    });
+
+    // Syncs never ask about overwrites; the sender is dropped right away
+    let (_, overwrite_rx) = tokio::sync::mpsc::channel(1);
+    state.background_transfers.push(BackgroundTransfer {
+        handle,
+        progress,
+        cancel,
+        skip: Arc::new(AtomicBool::new(false)),
+        dest_side: match source_side {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        },
+        direction: if dst_label == PaneLabel::Remote {
+            TransferDirection::LocalToRemote
+        } else {
+            TransferDirection::RemoteToLocal
+        },
+        description,
+        started_at: std::time::Instant::now(),
+        overwrite_rx,
+        overwrite_policy: Arc::new(AtomicU64::new(0)),
+        delete_sources: None,
+        source_side,
+        retry_targets: Vec::new(),
+        retry_dst_cwd: String::new(),
+        retry_is_move: false,
+        is_sync: true,
+    });
+    state.popup_transfer_index = state.background_transfers.len() - 1;
+    state.popup_focus = 0;
+    state.input_mode = InputMode::TransferPopup;
+}
+
+/// Turn the outcome of a background sync into a transfer result.
+/// Failed steps don't stop the sync; they're logged and counted in the summary.
+fn sync_transfer_result(
+    result: Result<sync::SyncReport>,
+    total: usize,
+    bytes: u64,
+) -> TransferResult {
+    match result {
+        Ok(report) => {
+            for error in &report.errors {
+                tracing::error!("sync failed: {error}");
+            }
+            let mut summary = format!(
+                "Synced: {} copied ({}), {} dir(s) created, {} deleted",
+                report.copied,
+                format_bytes(bytes),
+                report.created,
+                report.deleted
+            );
+            if let Some(first) = report.errors.first() {
+                summary.push_str(&format!(" — {} error(s): {first}", report.errors.len()));
+            }
+            TransferResult {
+                copied: report.copied,
+                total,
+                last_error: report.errors.first().cloned(),
+                summary: Some(summary),
+            }
+        }
+        Err(e) => {
+            tracing::error!("sync failed: {e:#}");
+            TransferResult {
+                copied: 0,
+                total,
+                last_error: Some(e.to_string()),
+                summary: Some(format!("Sync error: {e}")),
+            }
+        }
+    }
+}
+
+/// Whether running `plan` deletes files on a production remote, which needs
+/// "yes" typed in first.
+fn sync_needs_typed_confirm(state: &BrowserState, plan: &SyncPlan, source_side: Side) -> bool {
+    let dst_label = match source_side {
+        Side::Left => state.right_label,
+        Side::Right => state.left_label,
+    };
+    dst_label == PaneLabel::Remote
+        && exec_guard::is_production(&state.env)
+        && plan.delete_count() > 0
+}
+
 /// Check if the active pane is a production remote pane.
 fn is_production_remote(state: &BrowserState) -> bool {
     let label = match state.active_pane {
         Side::Left => state.left_label,
         Side::Right => state.right_label,
     };
-    state.env.eq_ignore_ascii_case("production") && label == PaneLabel::Remote
+    exec_guard::is_production(&state.env) && label == PaneLabel::Remote
 }
 
 /// Collect batch operation targets: marked entries if any, otherwise the selected entry (skip `..`).
+///
+/// Symlinks count as files even when they point at a directory, so deleting
+/// or moving one never recurses into its target.
 fn collect_batch_targets(pane: &PaneState) -> Vec<(String, String, bool, u64)> {
+    let target = |e: &FileEntry| {
+        (
+            e.path.clone(),
+            e.name.clone(),
+            e.is_dir && !e.is_symlink(),
+            e.size,
+        )
+    };
     if !pane.marked.is_empty() {
         pane.marked
             .iter()
             .filter_map(|&idx| pane.entries.get(idx))
             .filter(|e| e.name != "..")
-            .map(|e| (e.path.clone(), e.name.clone(), e.is_dir, e.size))
+            .map(target)
             .collect()
     } else if let Some(entry) = pane.selected_entry()
         && entry.name != ".."
     {
-        vec![(
-            entry.path.clone(),
-            entry.name.clone(),
-            entry.is_dir,
-            entry.size,
-        )]
+        vec![target(entry)]
     } else {
         vec![]
     }
@@ -3070,6 +4111,9 @@ fn transfer_label(transfers: &[BackgroundTransfer], idx: usize) -> String {
 
 /// Format a completion message for a finished background transfer.
 fn format_transfer_result(result: &TransferResult, description: &str, is_move: bool) -> String {
+    if let Some(summary) = &result.summary {
+        return summary.clone();
+    }
     let verb_past = if is_move { "Moved" } else { "Copied" };
     let verb_noun = if is_move { "Move" } else { "Copy" };
     if result.copied == 0 && result.last_error.is_none() {
@@ -3102,6 +4146,8 @@ struct WorkerPool {
     session_dead: Arc<AtomicBool>,
     /// If true, delete each source file immediately after successful transfer (move).
     is_move: bool,
+    /// Carry each file's mode and times over to the destination.
+    preserve: bool,
     /// Cache of directories already created on remote (avoids redundant mkdir calls).
     created_dirs: Arc<std::sync::Mutex<std::collections::HashSet<String>>>,
 }
@@ -3437,6 +4483,7 @@ fn spawn_worker(
                 &pool.progress,
                 &pool.cancel,
                 &pool.skip,
+                pool.preserve,
             );
 
             let prefetch_raw = Arc::clone(&raw);
@@ -3753,6 +4800,8 @@ async fn run_background_transfer(
     overwrite_tx: tokio::sync::mpsc::Sender<OverwriteQuery>,
     overwrite_policy: Arc<AtomicU64>,
     is_move: bool,
+    follow_links: bool,
+    preserve: bool,
 ) -> TransferResult {
     assert!(
         !initial_workers.is_empty(),
@@ -3773,26 +4822,33 @@ async fn run_background_transfer(
 
     // Expand directories into flat file lists
     let scan_start = std::time::Instant::now();
-    let targets = match expand_directory_targets(&scan_sftp, targets, direction, &progress).await {
-        Ok(t) => t,
-        Err(e) => {
-            progress.scanning.store(false, Ordering::Relaxed);
-            return TransferResult {
-                copied: 0,
-                total: 0,
-                last_error: Some(format!("Failed to expand directories: {e}")),
-            };
-        }
-    };
+    let targets =
+        match expand_directory_targets(&scan_sftp, targets, direction, follow_links, &progress)
+            .await
+        {
+            Ok(t) => t,
+            Err(e) => {
+                progress.scanning.store(false, Ordering::Relaxed);
+                return TransferResult {
+                    copied: 0,
+                    total: 0,
+                    last_error: Some(format!("Failed to expand directories: {e}")),
+                    summary: None,
+                };
+            }
+        };
     let scan_ms = scan_start.elapsed().as_millis();
 
     progress.scanning.store(false, Ordering::Relaxed);
 
-    // Separate dirs (create sequentially) and files
+    // Separate dirs (create sequentially), symlinks (recreated on the scan session) and files
     let mut dir_targets = Vec::new();
+    let mut link_targets = Vec::new();
     let mut file_targets = Vec::new();
     for target in targets {
-        if target.is_dir {
+        if target.link_target.is_some() {
+            link_targets.push(target);
+        } else if target.is_dir {
             dir_targets.push(target);
         } else {
             file_targets.push(target);
@@ -3821,6 +4877,7 @@ async fn run_background_transfer(
                     copied: 0,
                     total: file_targets.len(),
                     last_error: None,
+                    summary: None,
                 };
             }
             let result = tokio::fs::create_dir_all(&dir_target.dst_path)
@@ -3839,6 +4896,31 @@ async fn run_background_transfer(
         }
     }
 
+    // Recreate symlinks. Remote parents may not exist yet since uploads create
+    // them lazily, so make the scanned directories first (errors mean "exists").
+    let mut links_created = 0;
+    let mut link_error = None;
+    if !link_targets.is_empty() {
+        if direction == TransferDirection::LocalToRemote {
+            for dir_target in &dir_targets {
+                let _ = scan_sftp.create_dir(dir_target.dst_path.as_str()).await;
+            }
+        }
+        for target in &link_targets {
+            if cancel.load(Ordering::Relaxed) {
+                break;
+            }
+            let link = target.link_target.as_deref().unwrap_or_default();
+            match create_link_target(&scan_sftp, target, link, direction).await {
+                Ok(()) => links_created += 1,
+                Err(e) => {
+                    tracing::error!("symlink failed: {}: {e:#}", target.dst_path);
+                    link_error = Some(format!("{e:#}"));
+                }
+            }
+        }
+    }
+
     // Drop scan session — frees its SSH channel and mlocked buffers before
     // workers start filling their own channels with transfer data.
     tracing::debug!("MEM[bg:pre_drop_scan_sftp]: {:.1} MB RSS", rss_mb());
@@ -3860,9 +4942,10 @@ async fn run_background_transfer(
 
     if total == 0 {
         return TransferResult {
-            copied: 0,
-            total: 0,
-            last_error: None,
+            copied: links_created,
+            total: link_targets.len(),
+            last_error: link_error,
+            summary: None,
         };
     }
 
@@ -3880,6 +4963,7 @@ async fn run_background_transfer(
         overwrite_policy,
         session_dead: Arc::new(AtomicBool::new(false)),
         is_move,
+        preserve,
         created_dirs: Arc::new(std::sync::Mutex::new(std::collections::HashSet::new())),
     });
 
@@ -4007,9 +5091,10 @@ async fn run_background_transfer(
     );
 
     TransferResult {
-        copied: final_copied,
-        total,
-        last_error: final_error,
+        copied: final_copied + links_created,
+        total: total + link_targets.len(),
+        last_error: final_error.or(link_error),
+        summary: None,
     }
 }
 
@@ -4043,6 +5128,7 @@ async fn run_file_transfer(
     progress: &TransferProgress,
     cancel: &AtomicBool,
     skip: &AtomicBool,
+    preserve: bool,
 ) -> Result<()> {
     let combined_cancel = Arc::new(AtomicBool::new(false));
 
@@ -4094,6 +5180,14 @@ async fn run_file_transfer(
                     }
                 }
             }
+            if preserve {
+                PreservedAttrs::from_local(&local_meta)
+                    .apply_remote_handle(raw, handle_str)
+                    .await
+                    .with_context(|| {
+                        format!("Failed to preserve attributes on {}", target.dst_path)
+                    })?;
+            }
         }
         TransferDirection::RemoteToLocal => {
             // Ensure parent directory exists for nested files.
@@ -4185,9 +5279,17 @@ async fn run_file_transfer(
             match result {
                 Ok(()) => {
                     let _ = local_file.flush();
+                    drop(local_file);
                     std::fs::rename(&part_path, &target.dst_path).with_context(|| {
                         format!("Failed to rename {} to {}", part_path, target.dst_path)
                     })?;
+                    if preserve {
+                        let attrs = raw.fstat(handle_str.as_ref()).await.map_err(|e| {
+                            anyhow::anyhow!("Failed to stat {}: {e}", target.src_path)
+                        })?;
+                        PreservedAttrs::from_sftp(&attrs.attrs)
+                            .apply_local(Path::new(&target.dst_path))?;
+                    }
                 }
                 Err(e) => {
                     let _ = local_file.flush();
@@ -4324,6 +5426,7 @@ async fn start_copy_transfer(
                         name: name.clone(),
                         size: *size,
                         is_dir: *is_dir,
+                        link_target: None,
                     }
                 })
                 .collect();
@@ -4470,6 +5573,8 @@ async fn start_copy_transfer(
                 drop(extra_tx);
             }
 
+            let follow_links = state.follow_links;
+            let preserve = state.preserve_attrs;
             let handle = tokio::spawn(async move {
                 run_background_transfer(
                     scan_sftp,
@@ -4483,6 +5588,8 @@ async fn start_copy_transfer(
                     ow_tx,
                     bg_ow_policy,
                     is_move,
+                    follow_links,
+                    preserve,
                 )
                 .await
             });
@@ -4515,6 +5622,7 @@ async fn start_copy_transfer(
                 retry_targets: targets.clone(),
                 retry_dst_cwd: dst_cwd.to_string(),
                 retry_is_move: is_move,
+                is_sync: false,
             });
             state.popup_transfer_index = state.background_transfers.len() - 1;
 
@@ -4539,18 +5647,42 @@ async fn start_copy_transfer(
     Ok(())
 }
 
+/// An entry found while walking a directory tree for a transfer.
+struct WalkEntry {
+    /// Path relative to the walk's base.
+    relative: String,
+    is_dir: bool,
+    size: u64,
+    /// Set for a symlink that is recreated at the destination instead of followed.
+    link_target: Option<String>,
+}
+
 /// Recursively walk a remote directory via SFTP, returning all entries relative to `base`.
 ///
 /// Issues up to [`SFTP_SCAN_CONCURRENCY`] `read_dir` calls concurrently to hide
 /// network latency when scanning deep directory trees.
+///
+/// Symlinks are reported with their target and never descended into unless
+/// `follow_links` is set; then they're treated as what they point at. Each
+/// queued directory carries the canonical paths of its ancestors, and a link
+/// back to one of them is reported as a link rather than followed forever.
 async fn walk_remote_dir(
     sftp: &SftpSession,
     path: &str,
     base: &str,
+    follow_links: bool,
     scan_counter: Option<&AtomicU64>,
-) -> Result<Vec<(String, bool, u64)>> {
+) -> Result<Vec<WalkEntry>> {
     let mut result = Vec::new();
-    let mut dirs_to_visit = vec![path.to_string()];
+    let mut root_chain = Vec::new();
+    if follow_links {
+        root_chain.push(
+            sftp.canonicalize(path)
+                .await
+                .unwrap_or_else(|_| path.to_string()),
+        );
+    }
+    let mut dirs_to_visit = vec![(path.to_string(), root_chain)];
     let base_trimmed = base.trim_end_matches('/');
 
     while !dirs_to_visit.is_empty() {
@@ -4561,11 +5693,11 @@ async fn walk_remote_dir(
 
         let futures: Vec<_> = batch
             .iter()
-            .map(|dir_path| sftp.read_dir(dir_path.as_str()))
+            .map(|(dir_path, _)| sftp.read_dir(dir_path.as_str()))
             .collect();
         let results = join_all(futures).await;
 
-        for (dir_path, read_result) in batch.iter().zip(results) {
+        for ((dir_path, chain), read_result) in batch.iter().zip(results) {
             let entries = read_result
                 .with_context(|| format!("Failed to read remote directory: {dir_path}"))?;
             for entry in entries {
@@ -4579,14 +5711,62 @@ async fn walk_remote_dir(
                     .unwrap_or(&full_path)
                     .trim_start_matches('/')
                     .to_string();
-                let is_dir = entry.file_type().is_dir();
-                let size = entry.metadata().size.unwrap_or(0);
-                result.push((relative, is_dir, size));
+                let mut attrs = entry.metadata();
+                let mut link_target = None;
+                let mut canonical = None;
+                if attrs.is_symlink() {
+                    let resolved = match sftp.metadata(full_path.as_str()).await {
+                        Ok(resolved) if follow_links && resolved.is_dir() => {
+                            let real = sftp
+                                .canonicalize(full_path.as_str())
+                                .await
+                                .unwrap_or_else(|_| full_path.clone());
+                            let cycle = chain.contains(&real);
+                            canonical = Some(real);
+                            (!cycle).then_some(resolved)
+                        }
+                        Ok(resolved) if follow_links => Some(resolved),
+                        _ => None,
+                    };
+                    match resolved {
+                        Some(resolved) => attrs = resolved,
+                        // Not following, broken, or a cycle: copy the link itself
+                        None => {
+                            let target = sftp
+                                .read_link(full_path.as_str())
+                                .await
+                                .with_context(|| format!("Failed to read link: {full_path}"))?;
+                            link_target = Some(target);
+                        }
+                    }
+                }
+                let is_dir = link_target.is_none() && attrs.is_dir();
+                let size = if link_target.is_some() {
+                    0
+                } else {
+                    attrs.size.unwrap_or(0)
+                };
+                result.push(WalkEntry {
+                    relative,
+                    is_dir,
+                    size,
+                    link_target,
+                });
                 if let Some(counter) = scan_counter {
                     counter.fetch_add(1, Ordering::Relaxed);
                 }
                 if is_dir {
-                    dirs_to_visit.push(full_path);
+                    let mut child_chain = Vec::new();
+                    if follow_links {
+                        let parent = chain.last().map(String::as_str).unwrap_or_default();
+                        child_chain.clone_from(chain);
+                        child_chain.push(
+                            canonical.unwrap_or_else(|| {
+                                format!("{}/{name}", parent.trim_end_matches('/'))
+                            }),
+                        );
+                    }
+                    dirs_to_visit.push((full_path, child_chain));
                 }
             }
         }
@@ -4595,15 +5775,26 @@ async fn walk_remote_dir(
 }
 
 /// Recursively walk a local directory, returning all entries relative to `base`.
+///
+/// Symlinks are handled as in [`walk_remote_dir`].
 async fn walk_local_dir(
     path: &str,
     base: &str,
+    follow_links: bool,
     scan_counter: Option<&AtomicU64>,
-) -> Result<Vec<(String, bool, u64)>> {
+) -> Result<Vec<WalkEntry>> {
     let mut result = Vec::new();
-    let mut dirs_to_visit = vec![std::path::PathBuf::from(path)];
+    let mut root_chain = Vec::new();
+    if follow_links {
+        root_chain.push(
+            tokio::fs::canonicalize(path)
+                .await
+                .unwrap_or_else(|_| PathBuf::from(path)),
+        );
+    }
+    let mut dirs_to_visit = vec![(PathBuf::from(path), root_chain)];
 
-    while let Some(dir_path) = dirs_to_visit.pop() {
+    while let Some((dir_path, chain)) = dirs_to_visit.pop() {
         let mut read_dir = tokio::fs::read_dir(&dir_path)
             .await
             .with_context(|| format!("Failed to read local directory: {}", dir_path.display()))?;
@@ -4614,31 +5805,133 @@ async fn walk_local_dir(
                 .unwrap_or(&full_path)
                 .to_string_lossy()
                 .to_string();
-            let metadata = entry.metadata().await?;
-            let is_dir = metadata.is_dir();
-            let size = if is_dir { 0 } else { metadata.len() };
-            result.push((relative, is_dir, size));
+            // DirEntry::metadata doesn't follow symlinks
+            let mut metadata = entry.metadata().await?;
+            let mut link_target = None;
+            let mut canonical = None;
+            if metadata.file_type().is_symlink() {
+                let resolved = match tokio::fs::metadata(&full_path).await {
+                    Ok(resolved) if follow_links && resolved.is_dir() => {
+                        let real = tokio::fs::canonicalize(&full_path)
+                            .await
+                            .unwrap_or_else(|_| full_path.clone());
+                        let cycle = chain.contains(&real);
+                        canonical = Some(real);
+                        (!cycle).then_some(resolved)
+                    }
+                    Ok(resolved) if follow_links => Some(resolved),
+                    _ => None,
+                };
+                match resolved {
+                    Some(resolved) => metadata = resolved,
+                    None => {
+                        let target = tokio::fs::read_link(&full_path).await.with_context(|| {
+                            format!("Failed to read link: {}", full_path.display())
+                        })?;
+                        link_target = Some(target.to_string_lossy().to_string());
+                    }
+                }
+            }
+            let is_dir = link_target.is_none() && metadata.is_dir();
+            let size = if is_dir || link_target.is_some() {
+                0
+            } else {
+                metadata.len()
+            };
+            result.push(WalkEntry {
+                relative,
+                is_dir,
+                size,
+                link_target,
+            });
             if let Some(counter) = scan_counter {
                 counter.fetch_add(1, Ordering::Relaxed);
             }
             if is_dir {
-                dirs_to_visit.push(full_path);
+                let mut child_chain = Vec::new();
+                if follow_links {
+                    let parent = chain.last().cloned().unwrap_or_default();
+                    child_chain.clone_from(&chain);
+                    child_chain.push(canonical.unwrap_or_else(|| parent.join(entry.file_name())));
+                }
+                dirs_to_visit.push((full_path, child_chain));
             }
         }
     }
     Ok(result)
 }
 
+/// What a top-level copy source is once symlinks are taken into account.
+enum SourceKind {
+    /// A symlink to recreate at the destination, with its target.
+    Link(String),
+    /// A regular file or directory (or a followed link to one).
+    Path { is_dir: bool, size: u64 },
+}
+
+/// Inspect a copy source without following it unless `follow_links` is set.
+/// A broken link is always copied as a link. Returns `None` if the source
+/// can't be stat'ed, leaving the error to the transfer itself.
+async fn inspect_source(
+    sftp: &SftpSession,
+    path: &str,
+    direction: TransferDirection,
+    follow_links: bool,
+) -> Option<SourceKind> {
+    match direction {
+        TransferDirection::LocalToRemote => {
+            let lstat = tokio::fs::symlink_metadata(path).await.ok()?;
+            if !lstat.file_type().is_symlink() {
+                return None;
+            }
+            if follow_links && let Ok(resolved) = tokio::fs::metadata(path).await {
+                return Some(SourceKind::Path {
+                    is_dir: resolved.is_dir(),
+                    size: resolved.len(),
+                });
+            }
+            let target = tokio::fs::read_link(path).await.ok()?;
+            Some(SourceKind::Link(target.to_string_lossy().to_string()))
+        }
+        TransferDirection::RemoteToLocal => {
+            let lstat = sftp.symlink_metadata(path).await.ok()?;
+            if !lstat.is_symlink() {
+                return None;
+            }
+            if follow_links && let Ok(resolved) = sftp.metadata(path).await {
+                return Some(SourceKind::Path {
+                    is_dir: resolved.is_dir(),
+                    size: resolved.size.unwrap_or(0),
+                });
+            }
+            sftp.read_link(path).await.ok().map(SourceKind::Link)
+        }
+    }
+}
+
 /// Expand directory targets into flat file lists, creating destination directories as needed.
 async fn expand_directory_targets(
     sftp: &SftpSession,
     targets: Vec<TransferTarget>,
     direction: TransferDirection,
+    follow_links: bool,
     progress: &TransferProgress,
 ) -> Result<Vec<TransferTarget>> {
     let mut expanded = Vec::new();
 
-    for target in targets {
+    for mut target in targets {
+        match inspect_source(sftp, &target.src_path, direction, follow_links).await {
+            Some(SourceKind::Link(link)) => {
+                target.is_dir = false;
+                target.size = 0;
+                target.link_target = Some(link);
+            }
+            Some(SourceKind::Path { is_dir, size }) => {
+                target.is_dir = is_dir;
+                target.size = if is_dir { 0 } else { size };
+            }
+            None => {}
+        }
         if !target.is_dir {
             expanded.push(target);
             continue;
@@ -4650,6 +5943,7 @@ async fn expand_directory_targets(
                 walk_local_dir(
                     &target.src_path,
                     &target.src_path,
+                    follow_links,
                     Some(&progress.scan_entries_found),
                 )
                 .await?
@@ -4659,6 +5953,7 @@ async fn expand_directory_targets(
                     sftp,
                     &target.src_path,
                     &target.src_path,
+                    follow_links,
                     Some(&progress.scan_entries_found),
                 )
                 .await?
@@ -4672,10 +5967,12 @@ async fn expand_directory_targets(
             name: target.name.clone(),
             size: 0,
             is_dir: true,
+            link_target: None,
         });
 
         // Add all children
-        for (relative, is_dir, size) in children {
+        for child in children {
+            let relative = child.relative;
             let src = format!("{}/{}", target.src_path.trim_end_matches('/'), relative);
             let dst = format!("{}/{}", target.dst_path.trim_end_matches('/'), relative);
             let name = relative.rsplit('/').next().unwrap_or(&relative).to_string();
@@ -4683,13 +5980,54 @@ async fn expand_directory_targets(
                 src_path: src,
                 dst_path: dst,
                 name,
-                size,
-                is_dir,
+                size: child.size,
+                is_dir: child.is_dir,
+                link_target: child.link_target,
             });
         }
     }
-
-    Ok(expanded)
+
+    Ok(expanded)
+}
+
+/// Recreate a symlink found by the scan at its destination. An existing
+/// symlink there is replaced; anything else is left alone and reported.
+async fn create_link_target(
+    sftp: &SftpSession,
+    target: &TransferTarget,
+    link: &str,
+    direction: TransferDirection,
+) -> Result<()> {
+    let dst = &target.dst_path;
+    match direction {
+        TransferDirection::RemoteToLocal => {
+            if let Some(parent) = Path::new(dst).parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .with_context(|| format!("Failed to create local dir: {}", parent.display()))?;
+            }
+            if let Ok(existing) = tokio::fs::symlink_metadata(dst).await {
+                if !existing.file_type().is_symlink() {
+                    anyhow::bail!("{dst} already exists and is not a symlink");
+                }
+                tokio::fs::remove_file(dst)
+                    .await
+                    .with_context(|| format!("Failed to replace symlink: {dst}"))?;
+            }
+            crate::storage::local_backend::create_symlink(link, dst).await
+        }
+        TransferDirection::LocalToRemote => {
+            if let Ok(existing) = sftp.symlink_metadata(dst.as_str()).await {
+                if !existing.is_symlink() {
+                    anyhow::bail!("{dst} already exists and is not a symlink");
+                }
+                sftp.remove_file(dst.as_str())
+                    .await
+                    .with_context(|| format!("Failed to replace symlink: {dst}"))?;
+            }
+            crate::storage::sftp_backend::create_symlink(sftp, link, dst).await
+        }
+    }
 }
 
 /// Handle input modes: filter, mkdir prompt, rename prompt, confirm delete, pattern select,
@@ -4942,8 +6280,12 @@ async fn handle_input_mode(
         InputMode::MkdirPrompt(_)
             | InputMode::GotoPrompt(_)
             | InputMode::RenamePrompt { .. }
+            | InputMode::SymlinkPrompt { .. }
             | InputMode::ConfirmDelete { .. }
+            | InputMode::ConfirmEditUpload { .. }
             | InputMode::CopyConfirm { .. }
+            | InputMode::SyncPreview { .. }
+            | InputMode::SyncConfirm { .. }
     ) {
         let button_count = if matches!(state.input_mode, InputMode::CopyConfirm { .. }) {
             3
@@ -5037,7 +6379,12 @@ async fn handle_input_mode(
                     // Already set to Normal above
                 }
                 _ => {
-                    // Put it back for unrecognized keys
+                    match key.code {
+                        KeyCode::Char('l') => state.follow_links = !state.follow_links,
+                        KeyCode::Char('p') => state.preserve_attrs = !state.preserve_attrs,
+                        _ => {}
+                    }
+                    // Put it back after a toggle and for unrecognized keys
                     state.input_mode = InputMode::CopyConfirm {
                         targets,
                         direction,
@@ -5051,6 +6398,80 @@ async fn handle_input_mode(
         return Ok(());
     }
 
+    // Sync preview: Enter=focus-aware, d/c toggle options, arrows scroll, Esc=cancel
+    if matches!(state.input_mode, InputMode::SyncPreview { .. }) {
+        let focus = state.popup_focus;
+        let mode = std::mem::replace(&mut state.input_mode, InputMode::Normal);
+        if let InputMode::SyncPreview {
+            plan,
+            source_side,
+            mut options,
+            mut scroll,
+        } = mode
+        {
+            match key.code {
+                KeyCode::Enter if focus == 0 => {
+                    if plan.is_empty() {
+                        state.status_message = Some("Already in sync".to_string());
+                    } else if sync_needs_typed_confirm(state, &plan, source_side) {
+                        state.popup_focus = 0;
+                        state.input_mode = InputMode::SyncConfirm {
+                            plan,
+                            source_side,
+                            input: String::new(),
+                        };
+                    } else {
+                        start_pane_sync(plan, source_side, state, left, right).await;
+                    }
+                }
+                KeyCode::Enter | KeyCode::Esc => {
+                    state.status_message = Some("Sync cancelled.".to_string());
+                }
+                KeyCode::Char('d') | KeyCode::Char('c') => {
+                    if key.code == KeyCode::Char('d') {
+                        options.delete = !options.delete;
+                    } else {
+                        options.checksum = !options.checksum;
+                    }
+                    match plan_pane_sync(left_pane, right_pane, left, right, source_side, &options)
+                        .await
+                    {
+                        Ok(plan) => {
+                            state.input_mode = InputMode::SyncPreview {
+                                plan,
+                                source_side,
+                                options,
+                                scroll: 0,
+                            };
+                        }
+                        Err(e) => {
+                            tracing::error!("sync compare failed: {e:#}");
+                            state.status_message = Some(format!("Sync error: {e}"));
+                        }
+                    }
+                }
+                _ => {
+                    let max_scroll = plan.items.len().saturating_sub(1) as u16;
+                    scroll = match key.code {
+                        KeyCode::Up | KeyCode::Char('k') => scroll.saturating_sub(1),
+                        KeyCode::Down | KeyCode::Char('j') => scroll.saturating_add(1),
+                        KeyCode::PageUp => scroll.saturating_sub(10),
+                        KeyCode::PageDown => scroll.saturating_add(10),
+                        _ => scroll,
+                    }
+                    .min(max_scroll);
+                    state.input_mode = InputMode::SyncPreview {
+                        plan,
+                        source_side,
+                        options,
+                        scroll,
+                    };
+                }
+            }
+        }
+        return Ok(());
+    }
+
     // Handle char/backspace for live filter — re-filter on every keystroke
     if matches!(key.code, KeyCode::Char(_) | KeyCode::Backspace)
         && matches!(state.input_mode, InputMode::Filter(_))
@@ -5081,7 +6502,9 @@ async fn handle_input_mode(
             InputMode::MkdirPrompt(input)
             | InputMode::GotoPrompt(input)
             | InputMode::SelectPattern { input, .. } => Some(input),
-            InputMode::RenamePrompt { input, .. } => Some(input),
+            InputMode::RenamePrompt { input, .. }
+            | InputMode::SymlinkPrompt { input, .. }
+            | InputMode::SyncConfirm { input, .. } => Some(input),
             _ => None,
         };
         if let Some(input) = input_ref {
@@ -5099,6 +6522,26 @@ async fn handle_input_mode(
         }
     }
 
+    // Edited file upload confirmation: y/Enter on Upload uploads, anything else cancels
+    if matches!(state.input_mode, InputMode::ConfirmEditUpload { .. }) {
+        let upload = match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => true,
+            KeyCode::Enter => state.popup_focus == 0,
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => false,
+            _ => return Ok(()),
+        };
+        state.input_mode = InputMode::Normal;
+        if upload {
+            upload_pending_edit(left_pane, right_pane, state, left, right).await?;
+        } else if let Some(session) = state.pending_edit.take() {
+            state.status_message = Some(format!(
+                "Upload cancelled. Edited copy kept at {}",
+                session.keep().display()
+            ));
+        }
+        return Ok(());
+    }
+
     // Handle ConfirmDelete chars (y/n) separately
     if let InputMode::ConfirmDelete { .. } = &state.input_mode {
         let focus = state.popup_focus;
@@ -5275,6 +6718,37 @@ async fn handle_input_mode(
                 }
                 // else: Cancel — mode already set to Normal
             }
+            InputMode::SymlinkPrompt { input, target } => {
+                if state.popup_focus == 0 {
+                    // OK — create the link, pointing at the target by its relative name
+                    let link_name = input.trim().to_string();
+                    if link_name.is_empty()
+                        || link_name == "."
+                        || link_name == ".."
+                        || link_name.contains('/')
+                    {
+                        state.status_message = Some("Invalid name".to_string());
+                    } else {
+                        let pane = active_pane_mut(left_pane, right_pane, state);
+                        let link_path = format!("{}/{}", pane.cwd.trim_end_matches('/'), link_name);
+                        let backend = active_backend_mut(left, right, state);
+                        match backend.symlink(&target.name, &link_path).await {
+                            Ok(()) => {
+                                state.status_message =
+                                    Some(format!("Linked {link_name} → {}", target.name));
+                                let pane = active_pane_mut(left_pane, right_pane, state);
+                                let backend = active_backend_mut(left, right, state);
+                                refresh_pane(pane, backend, state).await?;
+                            }
+                            Err(e) => {
+                                tracing::error!("symlink failed: {link_path}: {e:#}");
+                                state.status_message = Some(format!("Symlink error: {e}"));
+                            }
+                        }
+                    }
+                }
+                // else: Cancel — mode already set to Normal
+            }
             InputMode::SelectPattern {
                 input,
                 selecting,
@@ -5313,12 +6787,25 @@ async fn handle_input_mode(
                     // mode already set to Normal
                 }
             }
+            InputMode::SyncConfirm {
+                plan,
+                source_side,
+                input,
+            } => {
+                if state.popup_focus == 0 && input.trim().eq_ignore_ascii_case("yes") {
+                    start_pane_sync(plan, source_side, state, left, right).await;
+                } else {
+                    state.status_message = Some("Sync cancelled.".to_string());
+                }
+            }
             InputMode::Normal
             | InputMode::ConfirmDelete { .. }
             | InputMode::CopyConfirm { .. }
+            | InputMode::SyncPreview { .. }
             | InputMode::TransferPopup
             | InputMode::OverwriteConfirm { .. }
             | InputMode::TransferComplete { .. }
+            | InputMode::ConfirmEditUpload { .. }
             | InputMode::HelpOverlay => {}
         }
         return Ok(());
@@ -5387,6 +6874,12 @@ fn build_browser_help_sections(theme: &ThemeColors) -> Vec<Line<'static>> {
 
     help_section_header(&mut lines, "File Operations", theme);
     help_key_hint(&mut lines, "F3", "View file (external pager)", theme);
+    help_key_hint(
+        &mut lines,
+        "F4 / e",
+        "Edit file in $EDITOR (uploads on save)",
+        theme,
+    );
     help_key_hint(
         &mut lines,
         "F5 / Space",
@@ -5402,6 +6895,13 @@ fn build_browser_help_sections(theme: &ThemeColors) -> Vec<Line<'static>> {
     help_key_hint(&mut lines, "F7", "Create new directory", theme);
     help_key_hint(&mut lines, "F8 / d", "Delete selected/marked", theme);
     help_key_hint(&mut lines, "r", "Rename / move", theme);
+    help_key_hint(&mut lines, "L", "Create symlink to selected", theme);
+    help_key_hint(
+        &mut lines,
+        "y",
+        "Sync active pane's directory into the other",
+        theme,
+    );
     lines.push(Line::from(""));
 
     help_section_header(&mut lines, "Selection", theme);
@@ -5415,6 +6915,33 @@ fn build_browser_help_sections(theme: &ThemeColors) -> Vec<Line<'static>> {
     help_key_hint(&mut lines, "-", "Deselect by pattern", theme);
     lines.push(Line::from(""));
 
+    help_section_header(&mut lines, "Compare Directories", theme);
+    help_key_hint(
+        &mut lines,
+        "c",
+        "Compare panes; marks what to copy across",
+        theme,
+    );
+    help_key_hint(
+        &mut lines,
+        "C",
+        "Compare, including shared subdirectories",
+        theme,
+    );
+    help_key_hint(
+        &mut lines,
+        "+ / >",
+        "New / newer than the other side",
+        theme,
+    );
+    help_key_hint(
+        &mut lines,
+        "< / \u{2260}",
+        "Older (not marked) / same time, size differs",
+        theme,
+    );
+    lines.push(Line::from(""));
+
     help_section_header(&mut lines, "Display & Filtering", theme);
     help_key_hint(&mut lines, "/", "Toggle filter mode (glob)", theme);
     help_key_hint(&mut lines, ".", "Toggle hidden files", theme);
@@ -5429,6 +6956,18 @@ fn build_browser_help_sections(theme: &ThemeColors) -> Vec<Line<'static>> {
 
     help_section_header(&mut lines, "Transfers", theme);
     help_key_hint(&mut lines, "p", "Show transfer progress popup", theme);
+    help_key_hint(
+        &mut lines,
+        "l",
+        "Copy dialog: copy links / follow them",
+        theme,
+    );
+    help_key_hint(
+        &mut lines,
+        "p",
+        "Copy dialog: preserve mode and times",
+        theme,
+    );
     help_key_hint(&mut lines, "Ctrl+R", "Refresh both panes", theme);
     lines.push(Line::from(""));
 
@@ -5536,6 +7075,16 @@ fn build_fkey_bar_hints<'a>(state: &BrowserState, theme: &ThemeColors) -> Vec<Sp
             ],
             theme,
         ),
+        InputMode::SyncPreview { .. } => hints(
+            &[
+                ("Enter", "Sync"),
+                ("d", "Delete extra"),
+                ("c", "Checksum"),
+                ("\u{2191}\u{2193}", "Scroll"),
+                ("Esc", "Cancel"),
+            ],
+            theme,
+        ),
         // Goto prompt
         InputMode::GotoPrompt(_) => hints(
             &[("Type", "Path"), ("Enter", "Go"), ("Esc", "Cancel")],
@@ -5546,6 +7095,7 @@ fn build_fkey_bar_hints<'a>(state: &BrowserState, theme: &ThemeColors) -> Vec<Sp
             &[
                 ("F1/?", "Help"),
                 ("F3", "View"),
+                ("F4", "Edit"),
                 ("F5", "Copy"),
                 ("F6", "Move"),
                 ("F7", "Mkdir"),
@@ -5667,6 +7217,7 @@ mod tests {
             size,
             modified: None,
             permissions: None,
+            link: None,
         }
     }
 
@@ -5678,6 +7229,7 @@ mod tests {
             size: 100,
             modified: Some(Utc::now() - chrono::Duration::seconds(secs_ago)),
             permissions: None,
+            link: None,
         }
     }
 
@@ -5994,6 +7546,7 @@ mod tests {
             copied: 3,
             total: 5,
             last_error: Some("connection reset".into()),
+            summary: None,
         };
         let retry = if result.last_error.is_some() {
             Some(RetryInfo {
@@ -6020,6 +7573,7 @@ mod tests {
             copied: 5,
             total: 5,
             last_error: None,
+            summary: None,
         };
         let retry: Option<RetryInfo> = if result.last_error.is_some() {
             Some(RetryInfo {
@@ -6104,6 +7658,7 @@ mod tests {
             copied: 1,
             total: 1,
             last_error: None,
+            summary: None,
         };
         let msg = format_transfer_summary(&result, 14_900_000, Duration::from_secs(12), false);
         assert!(msg.starts_with('\u{2713}'));
@@ -6119,6 +7674,7 @@ mod tests {
             copied: 3,
             total: 3,
             last_error: None,
+            summary: None,
         };
         let msg = format_transfer_summary(&result, 14_900_000, Duration::from_secs(12), false);
         assert!(msg.contains("3 files"));
@@ -6133,6 +7689,7 @@ mod tests {
             copied: 2,
             total: 5,
             last_error: Some("connection lost".into()),
+            summary: None,
         };
         let msg = format_transfer_summary(&result, 5_000_000, Duration::from_secs(8), false);
         assert!(msg.starts_with("Failed:"));
@@ -6146,6 +7703,7 @@ mod tests {
             copied: 0,
             total: 5,
             last_error: None,
+            summary: None,
         };
         let msg = format_transfer_summary(&result, 0, Duration::from_secs(1), false);
         assert_eq!(msg, "Copy cancelled");
@@ -6157,6 +7715,7 @@ mod tests {
             copied: 2,
             total: 2,
             last_error: None,
+            summary: None,
         };
         let msg = format_transfer_summary(&result, 1_000_000, Duration::from_secs(5), true);
         assert!(msg.contains("Moved"));
@@ -6169,6 +7728,7 @@ mod tests {
             copied: 0,
             total: 3,
             last_error: None,
+            summary: None,
         };
         let msg = format_transfer_summary(&result, 0, Duration::from_secs(0), true);
         assert_eq!(msg, "Move cancelled");
@@ -6181,6 +7741,7 @@ mod tests {
             copied: 1,
             total: 1,
             last_error: None,
+            summary: None,
         };
         let msg = format_transfer_summary(&result, 500, Duration::from_millis(50), false);
         assert!(msg.contains("1 file"));
@@ -6215,6 +7776,8 @@ mod tests {
         assert!(text.contains("Delete"), "should list delete");
         assert!(text.contains("Rename"), "should list rename");
         assert!(text.contains("Create new directory"), "should list mkdir");
+        assert!(text.contains("F4 / e"), "should list edit");
+        assert!(text.contains("Create symlink"), "should list symlink");
     }
 
     #[test]
@@ -6304,6 +7867,9 @@ mod tests {
             needs_full_redraw: false,
             overwrite_policy: Arc::new(AtomicU64::new(0)),
             help_scroll: 0,
+            pending_edit: None,
+            follow_links: false,
+            preserve_attrs: false,
         };
         let spans = build_fkey_bar_hints(&state, &theme);
         let text: String = spans.iter().map(|s| s.content.to_string()).collect();
@@ -6311,6 +7877,7 @@ mod tests {
         assert!(text.contains("F1/?"), "normal mode should show F1/?");
         assert!(text.contains("Quit"), "normal mode should show Quit");
         assert!(text.contains("Copy"), "normal mode should show Copy");
+        assert!(text.contains("Edit"), "normal mode should show Edit");
     }
 
     #[test]
@@ -6336,6 +7903,9 @@ mod tests {
             needs_full_redraw: false,
             overwrite_policy: Arc::new(AtomicU64::new(0)),
             help_scroll: 0,
+            pending_edit: None,
+            follow_links: false,
+            preserve_attrs: false,
         };
         let spans = build_fkey_bar_hints(&state, &theme);
         let text: String = spans.iter().map(|s| s.content.to_string()).collect();
@@ -6367,6 +7937,9 @@ mod tests {
             needs_full_redraw: false,
             overwrite_policy: Arc::new(AtomicU64::new(0)),
             help_scroll: 0,
+            pending_edit: None,
+            follow_links: false,
+            preserve_attrs: false,
         };
         let spans = build_fkey_bar_hints(&state, &theme);
         let text: String = spans.iter().map(|s| s.content.to_string()).collect();
@@ -6401,6 +7974,9 @@ mod tests {
             needs_full_redraw: false,
             overwrite_policy: Arc::new(AtomicU64::new(0)),
             help_scroll: 0,
+            pending_edit: None,
+            follow_links: false,
+            preserve_attrs: false,
         };
         let spans = build_fkey_bar_hints(&state, &theme);
         let text: String = spans.iter().map(|s| s.content.to_string()).collect();
@@ -6409,6 +7985,98 @@ mod tests {
         assert!(text.contains("Cancel"), "goto mode should show Cancel");
     }
 
+    #[test]
+    fn test_sync_transfer_result_keeps_sync_summary() {
+        let report = sync::SyncReport {
+            created: 1,
+            copied: 2,
+            deleted: 1,
+            errors: vec!["a.txt: permission denied".into()],
+        };
+        let result = sync_transfer_result(Ok(report), 3, 2048);
+        assert_eq!(result.copied, 2);
+        assert_eq!(
+            result.last_error.as_deref(),
+            Some("a.txt: permission denied")
+        );
+        let msg = format_transfer_result(&result, "/home/me/site", false);
+        assert!(msg.starts_with("Synced: 2 copied (2.0KB), 1 dir(s) created, 1 deleted"));
+        assert!(msg.contains("1 error(s): a.txt: permission denied"));
+        assert_eq!(
+            format_transfer_summary(&result, 2048, Duration::from_secs(1), false),
+            msg
+        );
+
+        let result = sync_transfer_result(Err(anyhow::anyhow!("Sync cancelled")), 3, 0);
+        assert_eq!(
+            format_transfer_result(&result, "/home/me/site", false),
+            "Sync error: Sync cancelled"
+        );
+    }
+
+    #[test]
+    fn test_sync_typed_confirm_only_for_production_deletions() {
+        let theme = resolve_theme("default");
+        let mut plan = SyncPlan {
+            src_root: "/home/me/site".into(),
+            dst_root: "/var/www".into(),
+            create_root: false,
+            items: vec![sync::SyncItem {
+                relative: "index.html".into(),
+                op: sync::SyncOp::Copy {
+                    reason: sync::SyncReason::New,
+                    size: 10,
+                },
+            }],
+        };
+        let mut state = BrowserState {
+            active_pane: Side::Left,
+            show_hidden: false,
+            sort_by: SortField::Name,
+            sort_asc: true,
+            filter: None,
+            input_mode: InputMode::SyncPreview {
+                plan: plan.clone(),
+                source_side: Side::Left,
+                options: SyncOptions::default(),
+                scroll: 0,
+            },
+            status_message: None,
+            bookmark_name: "test".to_string(),
+            env: "production".to_string(),
+            left_label: PaneLabel::Local,
+            right_label: PaneLabel::Remote,
+            background_transfers: Vec::new(),
+            theme: theme.clone(),
+            popup_focus: 0,
+            popup_transfer_index: 0,
+            overwrite_response_tx: None,
+            needs_full_redraw: false,
+            overwrite_policy: Arc::new(AtomicU64::new(0)),
+            help_scroll: 0,
+            pending_edit: None,
+            follow_links: false,
+            preserve_attrs: false,
+        };
+        let text: String = build_fkey_bar_hints(&state, &theme)
+            .iter()
+            .map(|s| s.content.to_string())
+            .collect();
+        assert!(text.contains("Delete extra") && text.contains("Checksum"));
+
+        // Copies alone don't need it, even on production
+        assert!(!sync_needs_typed_confirm(&state, &plan, Side::Left));
+        plan.items.push(sync::SyncItem {
+            relative: "old.html".into(),
+            op: sync::SyncOp::Delete { is_dir: false },
+        });
+        assert!(sync_needs_typed_confirm(&state, &plan, Side::Left));
+        // Deleting on the local side is never guarded
+        assert!(!sync_needs_typed_confirm(&state, &plan, Side::Right));
+        state.env = "staging".to_string();
+        assert!(!sync_needs_typed_confirm(&state, &plan, Side::Left));
+    }
+
     // --- .part file pattern (Phase 1B.3) ---
 
     #[test]
@@ -6528,6 +8196,7 @@ mod tests {
             copied: 2,
             total: 5,
             last_error: Some("SFTP read failed: channel closed".into()),
+            summary: None,
         };
 
         // This mirrors the logic in the transfer completion handler.
@@ -6575,6 +8244,7 @@ mod tests {
             copied: 5,
             total: 5,
             last_error: None,
+            summary: None,
         };
         let retry: Option<RetryInfo> = if result.last_error.is_some() {
             Some(RetryInfo {
@@ -6598,4 +8268,309 @@ mod tests {
             "successful TransferComplete must have retry: None"
         );
     }
+
+    // --- Remote edit (F4) ---
+
+    #[test]
+    fn test_resolve_editor_prefers_visual_then_editor() {
+        assert_eq!(
+            resolve_editor(Some("code --wait".into()), Some("nano".into())),
+            vec!["code", "--wait"]
+        );
+        assert_eq!(
+            resolve_editor(Some("  ".into()), Some("nano".into())),
+            vec!["nano"]
+        );
+        assert_eq!(resolve_editor(None, None), vec!["vi"]);
+    }
+
+    #[test]
+    fn test_edit_conflict_detects_size_and_mtime_changes() {
+        let downloaded = file_entry_with_modified("app.conf", 3600);
+        assert_eq!(edit_conflict(&downloaded, &downloaded.clone()), None);
+
+        let mut grown = downloaded.clone();
+        grown.size += 10;
+        let msg = edit_conflict(&downloaded, &grown).unwrap();
+        assert!(msg.contains("size changed"), "{msg}");
+
+        let mut touched = downloaded.clone();
+        touched.modified = Some(Utc::now());
+        let msg = edit_conflict(&downloaded, &touched).unwrap();
+        assert!(msg.contains("modified since download"), "{msg}");
+    }
+
+    #[test]
+    fn test_file_digest_detects_content_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("f.txt");
+        std::fs::write(&path, "a = 1\n").unwrap();
+        let before = file_digest(&path).unwrap();
+        std::fs::write(&path, "a = 1\n").unwrap();
+        assert_eq!(file_digest(&path).unwrap(), before);
+        std::fs::write(&path, "a = 2\n").unwrap();
+        assert_ne!(file_digest(&path).unwrap(), before);
+    }
+
+    #[tokio::test]
+    async fn test_download_for_edit_uses_private_temp_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("app.conf");
+        std::fs::write(&source, "listen 80;\n").unwrap();
+        let backend = Backend::Local(
+            crate::storage::local_backend::LocalBackend::new(dir.path().to_str().unwrap()).unwrap(),
+        );
+
+        let session = download_for_edit(&backend, source.to_str().unwrap(), Side::Right)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&session.local_path).unwrap(),
+            "listen 80;\n"
+        );
+        assert_eq!(session.remote.size, 11);
+        let part = format!("{}{PART_FILE_SUFFIX}", session.local_path.display());
+        assert!(!Path::new(&part).exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(session.temp_dir.path())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o077, 0, "temp dir must be private");
+        }
+
+        // Dropping the session removes the local copy; keep() leaves it behind
+        let temp_path = session.temp_dir.path().to_path_buf();
+        drop(session);
+        assert!(!temp_path.exists());
+
+        let session = download_for_edit(&backend, source.to_str().unwrap(), Side::Right)
+            .await
+            .unwrap();
+        let kept = session.keep();
+        assert!(kept.exists());
+        std::fs::remove_dir_all(kept.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_download_for_edit_failure_leaves_nothing_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Backend::Local(
+            crate::storage::local_backend::LocalBackend::new(dir.path().to_str().unwrap()).unwrap(),
+        );
+        let missing = dir.path().join("missing.conf");
+        assert!(
+            download_for_edit(&backend, missing.to_str().unwrap(), Side::Left)
+                .await
+                .is_err()
+        );
+    }
+
+    // --- compare directories ---
+
+    fn dated(name: &str, size: u64, secs: i64) -> FileEntry {
+        FileEntry {
+            modified: chrono::DateTime::from_timestamp(secs, 0),
+            ..file_entry(name, false, size)
+        }
+    }
+
+    #[test]
+    fn test_compare_entries_legend() {
+        let ours = vec![
+            file_entry("..", true, 0),
+            dated("only-here.txt", 1, 100),
+            dated("newer.txt", 1, 200),
+            dated("older.txt", 1, 100),
+            dated("resized.txt", 5, 100),
+            dated("same.txt", 1, 100),
+            file_entry("shared-dir", true, 0),
+            file_entry("new-dir", true, 0),
+        ];
+        let theirs = vec![
+            dated("newer.txt", 1, 100),
+            dated("older.txt", 1, 200),
+            dated("resized.txt", 3, 100),
+            dated("same.txt", 1, 100),
+            file_entry("shared-dir", true, 0),
+        ];
+        let marks = compare_entries(&ours, &theirs);
+        assert_eq!(marks.len(), 5);
+        assert_eq!(marks["only-here.txt"], CompareMark::New);
+        assert_eq!(marks["new-dir"], CompareMark::New);
+        assert_eq!(marks["newer.txt"], CompareMark::Newer);
+        assert_eq!(marks["older.txt"], CompareMark::Older);
+        assert_eq!(marks["resized.txt"], CompareMark::SizeDiffers);
+
+        // Everything but the older file is marked, ready for F5
+        let mut pane = PaneState::new("/test".into());
+        pane.entries = ours;
+        apply_compare_marks(&mut pane, marks);
+        let marked: Vec<&str> = pane
+            .marked
+            .iter()
+            .map(|&i| pane.entries[i].name.as_str())
+            .collect();
+        assert_eq!(marked.len(), 4);
+        assert!(!marked.contains(&"older.txt"));
+        assert!(compare_summary(&pane, &PaneState::new("/".into())).contains("< older 1"));
+    }
+
+    #[tokio::test]
+    async fn test_compare_panes_recursive_tags_shared_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (l, r) = (dir.path().join("l"), dir.path().join("r"));
+        for root in [&l, &r] {
+            std::fs::create_dir_all(root.join("conf/nested")).unwrap();
+            std::fs::create_dir_all(root.join("same")).unwrap();
+            std::fs::write(root.join("same/a.txt"), "a").unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(root.join("same/a.txt"))
+                .unwrap()
+                .set_modified(std::time::UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+                .unwrap();
+        }
+        std::fs::write(l.join("conf/nested/extra.txt"), "x").unwrap();
+        let backend = |p: &Path| {
+            Backend::Local(
+                crate::storage::local_backend::LocalBackend::new(p.to_str().unwrap()).unwrap(),
+            )
+        };
+        let (mut left, mut right) = (backend(&l), backend(&r));
+        let state = BrowserState {
+            active_pane: Side::Left,
+            show_hidden: false,
+            sort_by: SortField::Name,
+            sort_asc: true,
+            filter: None,
+            input_mode: InputMode::Normal,
+            status_message: None,
+            bookmark_name: "test".to_string(),
+            env: "development".to_string(),
+            left_label: PaneLabel::Local,
+            right_label: PaneLabel::Local,
+            background_transfers: Vec::new(),
+            theme: resolve_theme("default"),
+            popup_focus: 0,
+            popup_transfer_index: 0,
+            overwrite_response_tx: None,
+            needs_full_redraw: false,
+            overwrite_policy: Arc::new(AtomicU64::new(0)),
+            help_scroll: 0,
+            pending_edit: None,
+            follow_links: false,
+            preserve_attrs: false,
+        };
+        let mut left_pane = PaneState::new(String::new());
+        let mut right_pane = PaneState::new(String::new());
+        refresh_pane(&mut left_pane, &mut left, &state)
+            .await
+            .unwrap();
+        refresh_pane(&mut right_pane, &mut right, &state)
+            .await
+            .unwrap();
+
+        // A flat compare sees the same names on both sides
+        compare_panes(&mut left_pane, &mut right_pane, &left, &right, false, false)
+            .await
+            .unwrap();
+        assert!(left_pane.compare.is_empty() && right_pane.compare.is_empty());
+
+        compare_panes(&mut left_pane, &mut right_pane, &left, &right, true, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            left_pane.compare.get("conf"),
+            Some(&CompareMark::Newer),
+            "conf has a file the right side lacks"
+        );
+        assert!(!left_pane.compare.contains_key("same"));
+        assert!(right_pane.compare.is_empty());
+        assert_eq!(left_pane.marked.len(), 1);
+
+        // Refreshing drops stale results
+        refresh_pane(&mut left_pane, &mut left, &state)
+            .await
+            .unwrap();
+        assert!(left_pane.compare.is_empty());
+    }
+
+    // --- symlinks ---
+
+    fn link_entry(name: &str, kind: LinkKind, target: &str) -> FileEntry {
+        FileEntry {
+            is_dir: kind == LinkKind::Dir,
+            link: Some(crate::storage::LinkInfo {
+                kind,
+                target: target.into(),
+            }),
+            ..file_entry(name, false, 0)
+        }
+    }
+
+    #[test]
+    fn test_entry_display_name_shows_link_target() {
+        assert_eq!(entry_display_name(&file_entry("a.txt", false, 1)), "a.txt");
+        assert_eq!(
+            entry_display_name(&link_entry("current", LinkKind::Dir, "releases/42")),
+            "current -> releases/42"
+        );
+    }
+
+    #[test]
+    fn test_batch_targets_treat_links_as_files() {
+        let mut pane = PaneState::new("/test".into());
+        pane.entries = vec![
+            file_entry("dir", true, 0),
+            link_entry("current", LinkKind::Dir, "dir"),
+        ];
+        pane.marked.extend([0, 1]);
+        let targets = collect_batch_targets(&pane);
+        let is_dir = |name: &str| targets.iter().find(|t| t.1 == name).unwrap().2;
+        assert!(is_dir("dir"));
+        // Deleting or moving a link must never recurse into its target
+        assert!(!is_dir("current"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_walk_local_dir_does_not_follow_links_by_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("real")).unwrap();
+        std::fs::write(root.join("real/file.txt"), "hello").unwrap();
+        std::os::unix::fs::symlink("real", root.join("alias")).unwrap();
+        // A link back to an ancestor would loop forever if followed naively
+        std::os::unix::fs::symlink("..", root.join("real/up")).unwrap();
+        let root = root.to_str().unwrap();
+
+        let entries = walk_local_dir(root, root, false, None).await.unwrap();
+        let mut relative: Vec<&str> = entries.iter().map(|e| e.relative.as_str()).collect();
+        relative.sort_unstable();
+        assert_eq!(relative, ["alias", "real", "real/file.txt", "real/up"]);
+        let alias = entries.iter().find(|e| e.relative == "alias").unwrap();
+        assert!(!alias.is_dir);
+        assert_eq!(alias.link_target.as_deref(), Some("real"));
+
+        // Following copies what links point at; a link back to an ancestor
+        // is kept as a link instead of looping
+        let entries = walk_local_dir(root, root, true, None).await.unwrap();
+        let mut files: Vec<&str> = entries
+            .iter()
+            .filter(|e| !e.is_dir && e.link_target.is_none())
+            .map(|e| e.relative.as_str())
+            .collect();
+        files.sort_unstable();
+        assert_eq!(files, ["alias/file.txt", "real/file.txt"]);
+        let links: Vec<&str> = entries
+            .iter()
+            .filter(|e| e.link_target.is_some())
+            .map(|e| e.relative.as_str())
+            .collect();
+        assert!(links.iter().all(|l| l.ends_with("/up")), "{links:?}");
+        assert_eq!(links.len(), 2);
+    }
 }
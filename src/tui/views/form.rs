@@ -487,6 +487,9 @@ impl BookmarkForm {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         })
     }
 }
@@ -930,6 +933,9 @@ impl UnifiedForm {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         })
     }
 
@@ -2598,6 +2604,9 @@ mod tests {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         }
     }
 
@@ -2607,6 +2616,7 @@ mod tests {
             profiles: vec![],
             bookmarks: vec![sample_bookmark()],
             groups: vec![],
+            ..Default::default()
         }
     }
 
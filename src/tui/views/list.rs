@@ -98,10 +98,14 @@ fn render_group_session_tree(frame: &mut Frame, area: Rect, app: &App) {
             Style::default().fg(tc.accent).add_modifier(Modifier::BOLD)
         };
 
-        let header_line = Line::from(vec![
+        let mut header_spans = vec![
             env_span,
             Span::styled(format!("  {}", group_label), header_style),
-        ]);
+        ];
+        if app.config.team.owns_group(group) {
+            header_spans.push(Span::styled(TEAM_MARKER, Style::default().fg(tc.fg_muted)));
+        }
+        let header_line = Line::from(header_spans);
 
         rows.push(Row::new(vec![Cell::from(header_line)]));
         display_indices.push(group_idx * 1000); // group header marker
@@ -476,7 +480,12 @@ fn render_bookmark_table(frame: &mut Frame, area: Rect, app: &App) {
                 };
                 let session_indicator = format!(" ({} sessions)", group.sessions.len());
                 let name_display = format!("{}{}", group.name, session_indicator);
-                let name_cell = Cell::from(name_display).style(name_style);
+                let name_cell = name_cell(
+                    name_display,
+                    app.config.team.owns_group(group),
+                    name_style,
+                    tc,
+                );
                 let host_style = if is_selected {
                     Style::default().fg(tc.fg).bg(tc.highlight)
                 } else {
@@ -520,7 +529,12 @@ fn render_bookmark_table(frame: &mut Frame, area: Rect, app: &App) {
                     bookmark.profile.as_deref(),
                     bookmark.snippets.len(),
                 );
-                let name_cell = Cell::from(name_display).style(name_style);
+                let name_cell = name_cell(
+                    name_display,
+                    app.config.team.owns_bookmark(bookmark),
+                    name_style,
+                    tc,
+                );
                 let host_style = if is_selected {
                     Style::default().fg(tc.fg).bg(tc.highlight)
                 } else {
@@ -622,6 +636,22 @@ fn render_no_matches(frame: &mut Frame, area: Rect, theme: &ThemeColors) {
     frame.render_widget(paragraph, area);
 }
 
+/// Suffix marking entries that come from a read-only team config.
+const TEAM_MARKER: &str = " \u{2691} team";
+
+/// Name cell for the list table, with the team marker for team-owned entries.
+fn name_cell(display: String, is_team: bool, style: Style, tc: &ThemeColors) -> Cell<'static> {
+    if is_team {
+        Cell::from(Line::from(vec![
+            Span::raw(display),
+            Span::styled(TEAM_MARKER, Style::default().fg(tc.fg_muted)),
+        ]))
+        .style(style)
+    } else {
+        Cell::from(display).style(style)
+    }
+}
+
 /// Build the display string for a bookmark name in the list view.
 ///
 /// Appends a `[profile-name]` indicator when a profile is assigned,
@@ -682,6 +712,9 @@ mod tests {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         }
     }
 
@@ -718,6 +751,7 @@ mod tests {
             profiles: vec![],
             bookmarks: vec![],
             groups,
+            ..Default::default()
         }
     }
 
@@ -727,6 +761,7 @@ mod tests {
             profiles: vec![],
             bookmarks,
             groups: vec![],
+            ..Default::default()
         }
     }
 
@@ -23,6 +23,9 @@ fn sample_bookmark(name: &str, env: &str) -> Bookmark {
         connect_timeout_secs: None,
         ssh_options: std::collections::BTreeMap::new(),
         profile: None,
+        use_agent: None,
+        certificate_file: None,
+        tunnels: vec![],
     }
 }
 
@@ -36,6 +39,7 @@ fn sample_config() -> AppConfig {
             sample_bookmark("dev-local", "development"),
         ],
         groups: vec![],
+        ..Default::default()
     }
 }
 
@@ -70,6 +74,39 @@ fn test_export_import_roundtrip() {
     }
 }
 
+#[test]
+fn test_encrypted_export_import_roundtrip() {
+    use age::secrecy::ExposeSecret;
+    use config::bundle::{self, Encryption, SignatureStatus};
+
+    let original = sample_config();
+    let exported = config::export_bookmarks(&original, None, &[], None, false).unwrap();
+
+    let identity = age::x25519::Identity::generate();
+    let dir = tempfile::tempdir().unwrap();
+    let key_path = dir.path().join("team.key");
+    fs::write(&key_path, identity.to_string().expose_secret()).unwrap();
+
+    let encryption = Encryption::Recipients(vec![identity.to_public().to_string()]);
+    let sealed = bundle::seal(&exported, None, &encryption).unwrap();
+    for bm in &original.bookmarks {
+        assert!(!sealed.contains(&bm.host), "{} leaked into bundle", bm.host);
+    }
+
+    let opened = bundle::open(
+        sealed.as_bytes(),
+        &[key_path.to_string_lossy().into_owned()],
+        |_| panic!("no passphrase expected"),
+    )
+    .unwrap();
+    assert!(opened.encrypted);
+    assert_eq!(opened.signature, SignatureStatus::Unsigned);
+
+    let (imported, _) = config::parse_sshore_export(&opened.content).unwrap();
+    let names: Vec<&str> = imported.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(names, vec!["prod-web-01", "staging-api", "dev-local"]);
+}
+
 #[test]
 fn test_export_import_with_env_filter() {
     let config = sample_config();
@@ -107,6 +144,9 @@ fn test_import_overwrite_vs_skip() {
         connect_timeout_secs: None,
         ssh_options: std::collections::BTreeMap::new(),
         profile: None,
+        use_agent: None,
+        certificate_file: None,
+        tunnels: vec![],
     }];
 
     let imported = vec![
@@ -128,6 +168,9 @@ fn test_import_overwrite_vs_skip() {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         },
         Bookmark {
             name: "server-b".into(),
@@ -147,6 +190,9 @@ fn test_import_overwrite_vs_skip() {
             connect_timeout_secs: None,
             ssh_options: std::collections::BTreeMap::new(),
             profile: None,
+            use_agent: None,
+            certificate_file: None,
+            tunnels: vec![],
         },
     ];
 
@@ -177,6 +223,9 @@ fn test_import_overwrite_vs_skip() {
         connect_timeout_secs: None,
         ssh_options: std::collections::BTreeMap::new(),
         profile: None,
+        use_agent: None,
+        certificate_file: None,
+        tunnels: vec![],
     }];
 
     let result2 = merge_imports(&mut existing2, imported, true);
@@ -238,6 +238,9 @@ fn test_import_skip_existing() {
         ssh_options: std::collections::BTreeMap::new(),
         connect_timeout_secs: None,
         profile: None,
+        use_agent: None,
+        certificate_file: None,
+        tunnels: vec![],
     }];
 
     let imported = import_fixture("hosts.csv", ImportSourceKind::Csv, None, &[]);
@@ -272,6 +275,9 @@ fn test_import_overwrite_existing() {
         ssh_options: std::collections::BTreeMap::new(),
         connect_timeout_secs: None,
         profile: None,
+        use_agent: None,
+        certificate_file: None,
+        tunnels: vec![],
     }];
 
     let imported = import_fixture("hosts.csv", ImportSourceKind::Csv, None, &[]);
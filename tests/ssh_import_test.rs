@@ -153,6 +153,9 @@ Host server-b
         connect_timeout_secs: None,
         ssh_options: std::collections::BTreeMap::new(),
         profile: None,
+        use_agent: None,
+        certificate_file: None,
+        tunnels: vec![],
     }];
 
     let result = merge_imports(&mut existing, imported, false);
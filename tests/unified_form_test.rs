@@ -28,6 +28,9 @@ fn sample_bookmark() -> Bookmark {
         connect_timeout_secs: None,
         ssh_options: std::collections::BTreeMap::new(),
         profile: None,
+        use_agent: None,
+        certificate_file: None,
+        tunnels: vec![],
     }
 }
 